@@ -23,12 +23,12 @@
 
 ;;; Commentary:
 
-;; Time values come in several formats.  The oldest format is a cons
-;; cell of the form (HIGH . LOW).  This format is obsolete, but still
-;; supported.  The other formats are the lists (HIGH LOW), (HIGH LOW
-;; USEC), and (HIGH LOW USEC PSEC).  These formats specify the time
-;; value equal to HIGH * 2^16 + LOW + USEC * 10^-6 + PSEC * 10^-12
-;; seconds, where missing components are treated as zero.  HIGH can be
+;; Time values come in several formats.  The main ones are the lists
+;; (HIGH LOW), (HIGH LOW USEC), and (HIGH LOW USEC PSEC).  These
+;; formats specify the time value equal to HIGH * 2^16 + LOW + USEC *
+;; 10^-6 + PSEC * 10^-12 seconds, where missing components are treated
+;; as zero.  A cons cell of two integers (TICKS . HZ) stands for
+;; TICKS/HZ seconds; it replaces the obsolete (HIGH . LOW).  HIGH can be
 ;; negative, either because the value is a time difference, or because
 ;; it represents a time stamp before the epoch.  Typically, there are
 ;; more time values than the underlying system time type supports,
@@ -91,6 +91,9 @@ list (HIGH LOW MICRO PICO)."
 						 (setq lo 0)
 						 (setq hi (1+ hi))))))))
 			       (list hi lo us ps)))
+			    ((integerp (cdr-safe ,gensym))
+			     ;; (TICKS . HZ).
+			     (time-add ,gensym 0))
 			    (t ,gensym)))
 			  (,high (pop ,gensym))
 			  ,low ,micro)
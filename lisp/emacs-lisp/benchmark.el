@@ -32,13 +32,15 @@
 ;;; Code:
 
 (defmacro benchmark-elapse (&rest forms)
-  "Return the time in seconds elapsed for execution of FORMS."
+  "Return the time in seconds elapsed for execution of FORMS.
+The time is measured with `current-monotonic-time', so it is not
+affected by changes to the system time."
   (declare (indent 0) (debug t))
   (let ((t1 (make-symbol "t1")))
     `(let (,t1)
-       (setq ,t1 (current-time))
+       (setq ,t1 (current-monotonic-time))
        ,@forms
-       (float-time (time-subtract nil ,t1)))))
+       (float-time (time-subtract (current-monotonic-time) ,t1)))))
 
 ;;;###autoload
 (defmacro benchmark-run (&optional repetitions &rest forms)
@@ -3512,8 +3512,8 @@ If it is nil, then the handler is \"byte-compile-SYMBOL.\""
 (byte-defop-compiler set-marker	2-3)
 (byte-defop-compiler match-beginning	1)
 (byte-defop-compiler match-end	1)
-(byte-defop-compiler upcase		1)
-(byte-defop-compiler downcase		1)
+(byte-defop-compiler upcase		byte-compile-one-arg-or-call)
+(byte-defop-compiler downcase		byte-compile-one-arg-or-call)
 (byte-defop-compiler string=		2)
 (byte-defop-compiler string<		2)
 (byte-defop-compiler (string-equal byte-string=) 2)
@@ -3607,6 +3607,14 @@ These implicitly `and' together a bunch of two-arg bytecodes."
 	  ((= len 4) (byte-compile-three-args form))
 	  (t (byte-compile-subr-wrong-args form "2-3")))))
 
+(defun byte-compile-one-arg-or-call (form)
+  "Compile FORM with its opcode if it has exactly one argument.
+The opcode takes no optional arguments, so other forms are compiled
+as normal calls."
+  (if (= (length form) 2)
+      (byte-compile-one-arg form)
+    (byte-compile-normal-call form)))
+
 (defun byte-compile-noop (_form)
   (byte-compile-constant nil))
 
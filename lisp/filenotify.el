@@ -22,8 +22,8 @@
 ;;; Commentary
 
 ;; This package is an abstraction layer from the different low-level
-;; file notification packages `inotify', `kqueue', `gfilenotify' and
-;; `w32notify'.
+;; file notification packages `inotify', `kqueue', `gfilenotify',
+;; `w32notify' and `filewatch'.
 
 ;;; Code:
 
@@ -35,7 +35,8 @@
    ((featurep 'inotify) 'inotify)
    ((featurep 'kqueue) 'kqueue)
    ((featurep 'gfilenotify) 'gfilenotify)
-   ((featurep 'w32notify) 'w32notify))
+   ((featurep 'w32notify) 'w32notify)
+   ((featurep 'filewatch) 'filewatch))
   "Non-nil when Emacs has been compiled with file notification support.
 The value is the name of the low-level file notification package
 to be used for local file systems.  Remote file notifications
@@ -44,13 +45,34 @@ could use another implementation.")
 (cl-defstruct (file-notify--watch
                (:constructor nil)
                (:constructor
-                file-notify--watch-make (directory filename callback)))
+                file-notify--watch-make (directory filename callback
+                                                   &optional library)))
   ;; Watched directory
   directory
   ;; Watched relative filename, nil if watching the directory.
   filename
   ;; Function to propagate events to
-  callback)
+  callback
+  ;; Low-level package used for the watch, nil for a file name handler.
+  library)
+
+(defcustom file-notify-coalesce-events t
+  "Non-nil means to watch local files with the `filewatch' package.
+That package merges the events for a file that arrive within
+`filewatch-coalesce-delay' seconds of each other, and reports a
+renamed file with a single `renamed' event.  This keeps the number
+of events down while many files change at once, for example during
+a version control checkout.  It has no effect if Emacs has not been
+built with `filewatch'."
+  :type 'boolean
+  :group 'files
+  :version "27.1")
+
+(defun file-notify--local-library ()
+  "Return the file notification package to use for a new local watch."
+  (if (and file-notify-coalesce-events (featurep 'filewatch))
+      'filewatch
+    file-notify--library))
 
 (defun file-notify--watch-absolute-filename (watch)
   "Return the absolute filename observed by WATCH."
@@ -117,7 +139,7 @@ Could be different from the directory watched by the backend library."
       (or  (and (stringp (nth 2 event)) (nth 2 event)) "")
       (file-notify--watch-directory watch)))))
 
-;; Only `gfilenotify' could return two file names.
+;; Only `gfilenotify' and `filewatch' could return two file names.
 (defun file-notify--event-file1-name (event)
   "Return second file name of file notification event, or nil.
 This is available in case a file has been moved."
@@ -175,10 +197,9 @@ EVENT is the cadr of the event in `file-notify-handle-event'
           ;; Map action.  We ignore all events which cannot be mapped.
           (setq action
                 (cond
-                 ((memq action
-                        '(attribute-changed changed created deleted renamed))
+                 ((memq action '(attribute-changed changed created deleted))
                   action)
-                 ((memq action '(moved rename))
+                 ((memq action '(moved rename renamed))
                   ;; The kqueue rename event does not return file1 in
                   ;; case a file monitor is established.
                   (if (setq file1 (file-notify--event-file1-name event))
@@ -312,6 +333,7 @@ FILE is the name of the file whose event is being reported."
 	      (if (file-directory-p file)
 		  file
 		(file-name-directory file))))
+        (library (file-notify--local-library))
         desc func l-flags)
 
     (unless (file-directory-p dir)
@@ -324,43 +346,48 @@ FILE is the name of the file whose event is being reported."
 
       ;; Check, whether Emacs has been compiled with file notification
       ;; support.
-      (unless file-notify--library
+      (unless library
 	(signal 'file-notify-error
 		'("No file notification package available")))
 
       ;; Determine low-level function to be called.
       (setq func
 	    (cond
-	     ((eq file-notify--library 'inotify) 'inotify-add-watch)
-	     ((eq file-notify--library 'kqueue) 'kqueue-add-watch)
-	     ((eq file-notify--library 'gfilenotify) 'gfile-add-watch)
-	     ((eq file-notify--library 'w32notify) 'w32notify-add-watch)))
+	     ((eq library 'inotify) 'inotify-add-watch)
+	     ((eq library 'kqueue) 'kqueue-add-watch)
+	     ((eq library 'gfilenotify) 'gfile-add-watch)
+	     ((eq library 'w32notify) 'w32notify-add-watch)
+	     ((eq library 'filewatch) 'filewatch-add-watch)))
 
       ;; Determine respective flags.
-      (if (eq file-notify--library 'gfilenotify)
-	  (setq l-flags (append '(watch-mounts send-moved) flags))
+      (cond
+       ((eq library 'gfilenotify)
+	(setq l-flags (append '(watch-mounts send-moved) flags)))
+       ((eq library 'filewatch)
+        (setq l-flags flags))
+       (t
 	(when (memq 'change flags)
 	  (setq
 	   l-flags
 	   (cond
-	    ((eq file-notify--library 'inotify)
+	    ((eq library 'inotify)
 	     '(create delete delete-self modify move-self move))
-	    ((eq file-notify--library 'kqueue)
+	    ((eq library 'kqueue)
 	     '(create delete write extend rename))
-	    ((eq file-notify--library 'w32notify)
+	    ((eq library 'w32notify)
 	     '(file-name directory-name size last-write-time)))))
 	(when (memq 'attribute-change flags)
 	  (push (cond
-                 ((eq file-notify--library 'inotify) 'attrib)
-                 ((eq file-notify--library 'kqueue) 'attrib)
-                 ((eq file-notify--library 'w32notify) 'attributes))
-                l-flags)))
+                 ((eq library 'inotify) 'attrib)
+                 ((eq library 'kqueue) 'attrib)
+                 ((eq library 'w32notify) 'attributes))
+                l-flags))))
 
       ;; Call low-level function.
       (setq desc (funcall
                   ;; kqueue does not report file changes in directory
                   ;; monitor.  So we must watch the file itself.
-                  func (if (eq file-notify--library 'kqueue) file dir)
+                  func (if (eq library 'kqueue) file dir)
                   l-flags 'file-notify-callback)))
 
     ;; We do not want to enter quoted file names into the hash.
@@ -371,7 +398,7 @@ FILE is the name of the file whose event is being reported."
     (let ((watch (file-notify--watch-make
                   dir
                   (unless (file-directory-p file) (file-name-nondirectory file))
-                  callback)))
+                  callback (unless handler library))))
       (puthash desc watch file-notify-descriptors))
     ;; Return descriptor.
     desc))
@@ -390,11 +417,13 @@ DESCRIPTOR should be an object returned by `file-notify-add-watch'."
               (funcall handler 'file-notify-rm-watch descriptor)
 
             (funcall
-             (cond
-              ((eq file-notify--library 'inotify) 'inotify-rm-watch)
-              ((eq file-notify--library 'kqueue) 'kqueue-rm-watch)
-              ((eq file-notify--library 'gfilenotify) 'gfile-rm-watch)
-              ((eq file-notify--library 'w32notify) 'w32notify-rm-watch))
+             (let ((library (file-notify--watch-library watch)))
+               (cond
+                ((eq library 'inotify) 'inotify-rm-watch)
+                ((eq library 'kqueue) 'kqueue-rm-watch)
+                ((eq library 'gfilenotify) 'gfile-rm-watch)
+                ((eq library 'w32notify) 'w32notify-rm-watch)
+                ((eq library 'filewatch) 'filewatch-rm-watch)))
              descriptor))
         (file-notify-error nil)))
     ;; Modify `file-notify-descriptors'.
@@ -412,11 +441,13 @@ DESCRIPTOR should be an object returned by `file-notify-add-watch'."
                ;; local file notification support.
                (funcall handler 'file-notify-valid-p descriptor)
              (funcall
-              (cond
-               ((eq file-notify--library 'inotify) 'inotify-valid-p)
-               ((eq file-notify--library 'kqueue) 'kqueue-valid-p)
-               ((eq file-notify--library 'gfilenotify) 'gfile-valid-p)
-               ((eq file-notify--library 'w32notify) 'w32notify-valid-p))
+              (let ((library (file-notify--watch-library watch)))
+                (cond
+                 ((eq library 'inotify) 'inotify-valid-p)
+                 ((eq library 'kqueue) 'kqueue-valid-p)
+                 ((eq library 'gfilenotify) 'gfile-valid-p)
+                 ((eq library 'w32notify) 'w32notify-valid-p)
+                 ((eq library 'filewatch) 'filewatch-valid-p)))
               descriptor))
            t))))
 
@@ -810,6 +810,15 @@ order, and files from each directory are sorted in alphabetical order.
 Each file name appears in the returned list in its absolute form.
 Optional argument INCLUDE-DIRECTORIES non-nil means also include in the
 output directories whose names match REGEXP."
+  (if (not (find-file-name-handler (expand-file-name dir)
+                                   'directory-files-walk))
+      (directory-files-walk dir regexp include-directories)
+    (files--directory-files-recursively dir regexp include-directories)))
+
+(defun files--directory-files-recursively (dir regexp
+                                               &optional include-directories)
+  "Lisp implementation of `directory-files-recursively'.
+Used for file names with a file name handler."
   (let ((result nil)
 	(files nil)
 	;; When DIR is "/", remote file names like "/method:" could
@@ -824,7 +833,7 @@ output directories whose names match REGEXP."
 	      ;; Don't follow symlinks to other directories.
 	      (unless (file-symlink-p full-file)
 		(setq result
-		      (nconc result (directory-files-recursively
+		      (nconc result (files--directory-files-recursively
 				     full-file regexp include-directories))))
 	      (when (and include-directories
 			 (string-match regexp leaf))
@@ -1382,6 +1391,15 @@ the variable `temporary-file-directory' is returned."
           default-directory
         temporary-file-directory))))
 
+(defvar files--temp-directories-to-delete nil
+  "Directories made by `make-temp-file' to be deleted when Emacs exits.")
+
+(defun files--delete-temp-directories ()
+  "Delete the directories in `files--temp-directories-to-delete'."
+  (dolist (dir files--temp-directories-to-delete)
+    (ignore-errors (delete-directory dir t)))
+  (setq files--temp-directories-to-delete nil))
+
 (defun make-temp-file (prefix &optional dir-flag suffix text)
   "Create a temporary file.
 The returned file name (created by appending some random characters at the end
@@ -1390,19 +1408,27 @@ is guaranteed to point to a newly created file.
 You can then use `write-region' to write new data into the file.
 
 If DIR-FLAG is non-nil, create a new empty directory instead of a file.
+The directory is readable only by its owner.  If DIR-FLAG is the
+symbol `delete-on-exit', the directory and its contents are also
+deleted when Emacs exits.
 
 If SUFFIX is non-nil, add that at the end of the file name.
 
 If TEXT is a string, insert it into the new file; DIR-FLAG should be nil.
 Otherwise the file will be empty."
-  (let ((absolute-prefix
-	 (if (or (zerop (length prefix)) (member prefix '("." "..")))
-	     (concat (file-name-as-directory temporary-file-directory) prefix)
-	   (expand-file-name prefix temporary-file-directory))))
-    (if (find-file-name-handler absolute-prefix 'write-region)
-        (files--make-magic-temp-file absolute-prefix dir-flag suffix text)
-      (make-temp-file-internal absolute-prefix
-			       (if dir-flag t) (or suffix "") text))))
+  (let* ((absolute-prefix
+	  (if (or (zerop (length prefix)) (member prefix '("." "..")))
+	      (concat (file-name-as-directory temporary-file-directory) prefix)
+	    (expand-file-name prefix temporary-file-directory)))
+	 (file
+	  (if (find-file-name-handler absolute-prefix 'write-region)
+	      (files--make-magic-temp-file absolute-prefix dir-flag suffix text)
+	    (make-temp-file-internal absolute-prefix
+				     (if dir-flag t) (or suffix "") text))))
+    (when (eq dir-flag 'delete-on-exit)
+      (push file files--temp-directories-to-delete)
+      (add-hook 'kill-emacs-hook #'files--delete-temp-directories))
+    file))
 
 (defun files--make-magic-temp-file (absolute-prefix
                                     &optional dir-flag suffix text)
@@ -2023,6 +2049,17 @@ OP-TYPE specifies the file operation being performed (for message to user)."
 				    (file-size-human-readable size) op-type))))
     (user-error "Aborted")))
 
+(defun abort-if-file-binary (size filename)
+  "Refuse to visit FILENAME as text if it is large and looks binary.
+The file is large if SIZE is larger than `large-file-warning-threshold',
+and binary if `file-content-type' says so.  Such a file can still be
+visited with `find-file-literally'."
+  (when (and large-file-warning-threshold size
+	     (> size large-file-warning-threshold)
+	     (eq (cdr (ignore-errors (file-content-type filename))) 'binary))
+    (user-error "File %s is a large binary file; use `find-file-literally'"
+		(file-name-nondirectory filename))))
+
 (defun warn-maybe-out-of-memory (size)
   "Warn if an attempt to open file of SIZE bytes may run out of memory."
   (when (and (numberp size) (not (zerop size))
@@ -2101,6 +2138,8 @@ the various files."
 		  (setq buf other))))
 	;; Check to see if the file looks uncommonly large.
 	(when (not (or buf nowarn))
+	  (unless rawfile
+	    (abort-if-file-binary (nth 7 attributes) filename))
 	  (abort-if-file-too-large (nth 7 attributes) "open" filename)
 	  (warn-maybe-out-of-memory (nth 7 attributes)))
 	(if buf
@@ -7256,8 +7295,10 @@ If the function `system-move-file-to-trash' is defined, call it
 Otherwise, if `trash-directory' is non-nil, move FILENAME to that
  directory.
 Otherwise, trash FILENAME using the freedesktop.org conventions,
- like the GNOME, KDE and XFCE desktop environments.  Emacs only
- moves files to \"home trash\", ignoring per-volume trashcans."
+ like the GNOME, KDE and XFCE desktop environments.  If Emacs has
+ `trash-file', files on other file systems than the home directory
+ go to the trashcan of their own volume; otherwise Emacs only moves
+ files to \"home trash\", ignoring per-volume trashcans."
   (interactive "fMove file to trash: ")
   (cond (trash-directory
 	 ;; If `trash-directory' is non-nil, move the file there.
@@ -7281,6 +7322,10 @@ Otherwise, trash FILENAME using the freedesktop.org conventions,
 	;; If `system-move-file-to-trash' is defined, use it.
 	((fboundp 'system-move-file-to-trash)
 	 (system-move-file-to-trash filename))
+	;; Use the native implementation of the freedesktop.org method.
+	((and (fboundp 'trash-file)
+	      (not (find-file-name-handler filename 'trash-file)))
+	 (trash-file filename))
 	;; Otherwise, use the freedesktop.org method, as specified at
 	;; http://freedesktop.org/wiki/Specifications/trash-spec
 	(t
@@ -363,6 +363,15 @@ fn build_ignored_paths() -> Vec<&'static str> {
     #[cfg(not(feature = "window-system-x11"))]
     ignored_paths.push("xsettings.rs");
 
+    #[cfg(not(feature = "file-notify"))]
+    ignored_paths.push("filewatch.rs");
+
+    #[cfg(not(unix))]
+    ignored_paths.push("timezone.rs");
+
+    #[cfg(not(unix))]
+    ignored_paths.push("trash.rs");
+
     ignored_paths
 }
 
@@ -1,16 +1,18 @@
 use std::ffi::{CStr, CString};
 use std::io;
+use std::ptr;
 
 use libc::{self, c_char, c_int, EEXIST, EINVAL};
 
 #[cfg(unix)]
-use libc::{open, O_CLOEXEC, O_CREAT, O_EXCL, O_RDWR};
+use libc::{mkdir, open, O_CLOEXEC, O_CREAT, O_EXCL, O_RDWR};
 
 use rand::{rngs::OsRng, RngCore};
 
 #[cfg(windows)]
 extern "C" {
     fn sys_open(filename: *const c_char, flags: c_int, mode: c_int) -> c_int;
+    fn sys_mkdir(filename: *const c_char, mode: c_int) -> c_int;
 }
 
 #[cfg(test)]
@@ -54,6 +56,55 @@ pub fn make_temporary_file(template: String, flags: i32) -> Result<(i32, String)
     Err(EEXIST)
 }
 
+/// Like `rust_make_temp`, but create a directory with mode 0700.  The
+/// last SUFFIX_LEN bytes of TEMPLATE are a suffix that follows the
+/// `XXXXXX`.  Return 0 on success, or -1 with errno set on failure.
+#[no_mangle]
+pub unsafe extern "C" fn rust_make_temp_dir(template: *mut c_char, suffix_len: c_int) -> c_int {
+    let save_errno = errno::errno();
+    let bytes = CStr::from_ptr(template).to_bytes();
+    let split = bytes.len().saturating_sub(suffix_len as usize);
+
+    match make_temporary_directory(&bytes[..split], &bytes[split..]) {
+        Ok(name) => {
+            errno::set_errno(save_errno);
+            // The name has the same length as the template.
+            ptr::copy_nonoverlapping(name.as_ptr(), template as *mut u8, name.len());
+            0
+        }
+
+        Err(error_code) => {
+            errno::set_errno(errno::Errno(error_code));
+            -1
+        }
+    }
+}
+
+/// Create a new directory, readable only by its owner, whose name is
+/// TEMPLATE with the trailing `XXXXXX` replaced, followed by SUFFIX.
+/// Return the name of the directory, or the errno value on failure.
+/// Only name collisions are retried, so that a missing or unwritable
+/// parent is reported at once.  The names are raw bytes, as file names
+/// need not be valid UTF-8.
+pub fn make_temporary_directory(template: &[u8], suffix: &[u8]) -> Result<Vec<u8>, i32> {
+    if !template.ends_with(b"XXXXXX") {
+        return Err(EINVAL);
+    }
+    let random = template.len() - 6..template.len();
+    let mut name = [template, suffix].concat();
+    for _ in 0..NUM_RETRIES {
+        fill_random_name(&mut name[random.clone()]);
+        let attempt = CString::new(name.clone()).map_err(|_| EINVAL)?;
+        match create_temporary_directory(&attempt) {
+            Ok(()) => return Ok(name),
+            Err(ref err) if err.raw_os_error() == Some(EEXIST) => continue,
+            Err(err) => return Err(err.raw_os_error().unwrap_or(EINVAL)),
+        }
+    }
+
+    Err(EEXIST)
+}
+
 fn validate_template(template: String) -> Result<String, i32> {
     if !template.ends_with("XXXXXX") {
         Err(EINVAL)
@@ -67,7 +118,11 @@ fn generate_temporary_filename(name: &mut String) {
     assert!(len >= 6);
     let name_vec = unsafe { &mut name.as_mut_vec() };
 
-    let bytes = &mut name_vec[len - 6..len];
+    fill_random_name(&mut name_vec[len - 6..len]);
+}
+
+/// Replace BYTES with random letters and digits.
+fn fill_random_name(bytes: &mut [u8]) {
     {
         let mut rng = OsRng::new().unwrap();
         rng.fill_bytes(bytes);
@@ -109,6 +164,22 @@ fn open_temporary_file(name: &CString, flags: c_int) -> io::Result<c_int> {
     }
 }
 
+#[cfg(unix)]
+fn create_temporary_directory(name: &CString) -> io::Result<()> {
+    match unsafe { mkdir(name.as_ptr(), 0o700) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+fn create_temporary_directory(name: &CString) -> io::Result<()> {
+    match unsafe { sys_mkdir(name.as_ptr(), 0o700) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
 #[test]
 #[should_panic]
 fn test_generate_bad_temporary_filename() {
@@ -178,3 +249,58 @@ fn test_rust_make_temp_error_inval() {
     let error = errno::errno();
     assert!(file_handle == -1 && error == errno::Errno(EINVAL));
 }
+
+#[test]
+fn test_make_temporary_directory() {
+    let mut tmpdir = env::temp_dir();
+    tmpdir.push(".emacs-XXXXXX");
+    let template = tmpdir.to_string_lossy().into_owned();
+    let name = make_temporary_directory(template.as_bytes(), b".d").unwrap();
+    assert!(name.ends_with(b".d"));
+    assert_eq!(name.len(), template.len() + 2);
+    let name = String::from_utf8(name).unwrap();
+    let metadata = std::fs::metadata(&name).unwrap();
+    assert!(metadata.is_dir());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o700);
+    }
+    std::fs::remove_dir(&name).unwrap();
+}
+
+#[test]
+fn test_make_temporary_directory_errors() {
+    let mut tmpdir = env::temp_dir();
+    tmpdir.push(".emacs-XXXX");
+    let bad_template = tmpdir.to_string_lossy().into_owned();
+    assert_eq!(
+        make_temporary_directory(bad_template.as_bytes(), b""),
+        Err(EINVAL)
+    );
+
+    // A missing parent directory is not retried as a collision.
+    let mut tmpdir = env::temp_dir();
+    tmpdir.push(".emacs-does-not-exist-XXXXXX");
+    tmpdir.push("XXXXXX");
+    let template = tmpdir.to_string_lossy().into_owned();
+    assert_eq!(
+        make_temporary_directory(template.as_bytes(), b""),
+        Err(libc::ENOENT)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_make_temporary_directory_non_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+    let mut tmpdir = env::temp_dir();
+    tmpdir.push(std::ffi::OsStr::from_bytes(b".emacs-\xff-XXXXXX"));
+    let template = CString::new(tmpdir.as_os_str().as_bytes()).unwrap();
+    let raw_ptr = template.into_raw();
+    assert_eq!(unsafe { rust_make_temp_dir(raw_ptr, 0) }, 0);
+    let name = unsafe { CString::from_raw(raw_ptr) };
+    let name = std::ffi::OsStr::from_bytes(name.as_bytes());
+    assert!(std::fs::metadata(name).unwrap().is_dir());
+    std::fs::remove_dir(name).unwrap();
+}
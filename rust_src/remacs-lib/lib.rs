@@ -22,11 +22,11 @@ pub use crate::{
     // Used by make-docfile
     docfile::scan_rust_file,
     // Used for creating temporary files in emacs
-    files::rust_make_temp,
+    files::{make_temporary_directory, make_temporary_file, rust_make_temp, rust_make_temp_dir},
 
     math::{rust_count_one_bits, rust_count_trailing_zero_bits},
 
-    time::current_timespec,
+    time::{boottime_timespec, current_timespec, monotonic_timespec},
 };
 
 #[cfg(all(not(test), target_os = "windows"))]
@@ -4,6 +4,9 @@ use crate::time_crate::now;
 use libc::c_long;
 use libc::timespec as c_timespec;
 
+#[cfg(not(unix))]
+use std::time::Instant;
+
 /// Return current system time.
 #[no_mangle]
 pub extern "C" fn current_timespec() -> c_timespec {
@@ -13,3 +16,66 @@ pub extern "C" fn current_timespec() -> c_timespec {
         tv_nsec: c_long::from(ts.nsec),
     }
 }
+
+/// Return the time of a clock that is not affected by changes to the
+/// system time.  Its origin is unspecified.
+#[cfg(unix)]
+pub fn monotonic_timespec() -> c_timespec {
+    clock_timespec(libc::CLOCK_MONOTONIC)
+}
+
+/// Like `monotonic_timespec`, but the clock also runs while the system
+/// is suspended, and its origin is the time the system booted.
+#[cfg(unix)]
+pub fn boottime_timespec() -> c_timespec {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let clock = libc::CLOCK_BOOTTIME;
+    // On macOS, CLOCK_MONOTONIC already counts time spent asleep.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let clock = libc::CLOCK_MONOTONIC;
+    clock_timespec(clock)
+}
+
+#[cfg(unix)]
+fn clock_timespec(clock: libc::clockid_t) -> c_timespec {
+    let mut ts = c_timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // This can only fail for an invalid clock.
+    let ret = unsafe { libc::clock_gettime(clock, &mut ts) };
+    assert_eq!(ret, 0, "clock_gettime failed");
+    ts
+}
+
+#[cfg(not(unix))]
+lazy_static! {
+    static ref MONOTONIC_ORIGIN: Instant = Instant::now();
+}
+
+/// Return the time of a clock that is not affected by changes to the
+/// system time.  Its origin is the first call.
+#[cfg(not(unix))]
+pub fn monotonic_timespec() -> c_timespec {
+    let elapsed = MONOTONIC_ORIGIN.elapsed();
+    c_timespec {
+        tv_sec: elapsed.as_secs() as libc::time_t,
+        tv_nsec: elapsed.subsec_nanos() as c_long,
+    }
+}
+
+/// There is no separate boot-time clock here; this is the same as
+/// `monotonic_timespec`.
+#[cfg(not(unix))]
+pub fn boottime_timespec() -> c_timespec {
+    monotonic_timespec()
+}
+
+#[test]
+fn test_monotonic_timespec() {
+    let before = monotonic_timespec();
+    let after = monotonic_timespec();
+    assert!((after.tv_sec, after.tv_nsec) >= (before.tv_sec, before.tv_nsec));
+    let boot = boottime_timespec();
+    assert!(boot.tv_sec >= 0 && boot.tv_nsec >= 0 && boot.tv_nsec < 1_000_000_000);
+}
@@ -0,0 +1,456 @@
+//! Arbitrary precision integers.
+//!
+//! Integers that do not fit in a fixnum are stored as bignums, a
+//! pseudovector holding the sign and the magnitude of the number.
+//! Every integer operation returns a fixnum whenever the result fits
+//! in one, so a bignum is never in fixnum range and two integers are
+//! `eql' exactly when they are `='.
+
+use std::cmp::Ordering;
+use std::mem;
+use std::ptr;
+use std::slice;
+
+use libc::{c_char, c_int, ptrdiff_t};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+
+use crate::{
+    floatfns,
+    hashtable::LispHashTableRef,
+    lisp::{ExternalPtr, LispObject, LispStructuralEqual},
+    math::ArithOp,
+    numbers::LispNumber,
+    remacs_sys::{allocate_pseudovector, equal_kind, pvec_type, EmacsDouble, EmacsInt},
+    remacs_sys::{Lisp_Bignum, Lisp_Type},
+    remacs_sys::{Qintegerp, Qoverflow_error},
+    vectors::{LispVectorlikeRef, HEADER_SIZE, WORD_SIZE},
+};
+
+/// The largest number of bits in the magnitude of a bignum.  An
+/// operation whose result would be larger signals `overflow-error'.
+pub const BIGNUM_MAX_BITS: usize = 65536;
+
+pub type LispBignumRef = ExternalPtr<Lisp_Bignum>;
+
+impl LispBignumRef {
+    pub fn is_negative(self) -> bool {
+        self.negative
+    }
+
+    pub fn digits(&self) -> &[u32] {
+        unsafe { self.digits.as_slice(self.ndigits as usize) }
+    }
+
+    pub fn to_bigint(self) -> BigInt {
+        let sign = if self.negative {
+            Sign::Minus
+        } else {
+            Sign::Plus
+        };
+        BigInt::from_slice(sign, self.digits())
+    }
+
+    pub fn to_float(self) -> EmacsDouble {
+        bigint_to_float(&self.to_bigint())
+    }
+}
+
+impl From<LispBignumRef> for LispObject {
+    fn from(b: LispBignumRef) -> Self {
+        Self::tag_ptr(b, Lisp_Type::Lisp_Vectorlike)
+    }
+}
+
+impl LispStructuralEqual for LispBignumRef {
+    fn equal(
+        &self,
+        other: Self,
+        _kind: equal_kind::Type,
+        _depth: i32,
+        _ht: &mut LispHashTableRef,
+    ) -> bool {
+        self.negative == other.negative && self.digits() == other.digits()
+    }
+}
+
+impl LispVectorlikeRef {
+    pub fn as_bignum(self) -> Option<LispBignumRef> {
+        if self.is_pseudovector(pvec_type::PVEC_BIGNUM) {
+            Some(self.cast())
+        } else {
+            None
+        }
+    }
+}
+
+impl LispObject {
+    pub fn is_bignum(self) -> bool {
+        self.as_vectorlike()
+            .map_or(false, |v| v.is_pseudovector(pvec_type::PVEC_BIGNUM))
+    }
+
+    pub fn as_bignum(self) -> Option<LispBignumRef> {
+        self.as_vectorlike().and_then(LispVectorlikeRef::as_bignum)
+    }
+
+    /// Return N as a fixnum, or as a bignum if it is out of fixnum range.
+    pub fn from_integer(n: EmacsInt) -> Self {
+        if Self::fixnum_overflow(n) {
+            Self::from(BigInt::from(n))
+        } else {
+            Self::from_fixnum(n)
+        }
+    }
+
+    /// Return the value of an integer, which may be a fixnum or a bignum.
+    pub fn as_bigint(self) -> Option<BigInt> {
+        if let Some(n) = self.as_fixnum() {
+            Some(BigInt::from(n))
+        } else {
+            self.as_bignum().map(LispBignumRef::to_bigint)
+        }
+    }
+
+    pub fn as_bigint_or_error(self) -> BigInt {
+        self.as_bigint()
+            .unwrap_or_else(|| wrong_type!(Qintegerp, self))
+    }
+}
+
+impl From<BigInt> for LispObject {
+    fn from(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(i) if !Self::fixnum_overflow(i as EmacsInt) => Self::from_fixnum(i as EmacsInt),
+            _ => make_bignum(&n),
+        }
+    }
+}
+
+/// Signal `overflow-error' if an integer with BITS bits would be too
+/// large to represent.  Callers use this to refuse an operation before
+/// spending time and memory on computing its result.
+pub fn check_bignum_bits(bits: usize) {
+    if bits > BIGNUM_MAX_BITS {
+        xsignal!(Qoverflow_error);
+    }
+}
+
+/// Allocate a bignum holding N, which must be out of fixnum range.
+fn make_bignum(n: &BigInt) -> LispObject {
+    check_bignum_bits(n.bits());
+    let (sign, digits) = n.to_u32_digits();
+    let nbytes = unsafe { offset_of!(Lisp_Bignum, digits) } - *HEADER_SIZE
+        + digits.len() * mem::size_of::<u32>();
+    let memlen = ((nbytes + *WORD_SIZE - 1) / *WORD_SIZE) as c_int;
+    let mut bignum = LispBignumRef::new(unsafe {
+        allocate_pseudovector(memlen, 0, memlen, pvec_type::PVEC_BIGNUM) as *mut Lisp_Bignum
+    });
+    bignum.negative = sign == Sign::Minus;
+    bignum.ndigits = digits.len() as ptrdiff_t;
+    unsafe {
+        bignum
+            .digits
+            .as_mut_slice(digits.len())
+            .copy_from_slice(&digits)
+    };
+    bignum.into()
+}
+
+/// Convert N to the nearest float, or to an infinity if it is too
+/// large.
+pub fn bigint_to_float(n: &BigInt) -> EmacsDouble {
+    n.to_f64().unwrap_or_else(|| {
+        if n.is_negative() {
+            EmacsDouble::NEG_INFINITY
+        } else {
+            EmacsDouble::INFINITY
+        }
+    })
+}
+
+/// Return the integer nearest to the integral float F, or `None' if F
+/// is an infinity or a NaN.
+pub fn float_to_bigint(f: EmacsDouble) -> Option<BigInt> {
+    BigInt::from_f64(f)
+}
+
+/// Compare the integer N to the float F exactly, without rounding N.
+/// Return `None' if F is a NaN.
+pub fn compare_integer_float(n: &BigInt, f: EmacsDouble) -> Option<Ordering> {
+    if f.is_nan() {
+        None
+    } else if f.is_infinite() {
+        Some(if f > 0.0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        })
+    } else {
+        let t = f.trunc();
+        match n.cmp(&BigInt::from_f64(t).unwrap()) {
+            // N is the integral part of F, so the fraction decides.
+            Ordering::Equal => 0.0.partial_cmp(&(f - t)),
+            ord => Some(ord),
+        }
+    }
+}
+
+/// Driver for arithmetic operations whose result no longer fits in a
+/// fixnum.  ACCUM is the exact result of the operation on the
+/// arguments before ARGSTART.
+pub fn bignum_arith_driver(
+    mut accum: BigInt,
+    argstart: usize,
+    code: ArithOp,
+    args: &[LispObject],
+) -> LispObject {
+    for (i, &val) in args[argstart..].iter().enumerate() {
+        let argnum = argstart + i;
+        let next = match val.as_number_coerce_marker_or_error() {
+            LispNumber::Float(_) => {
+                let accum = bigint_to_float(&accum);
+                return floatfns::float_arith_driver(accum, argnum, code, args).into();
+            }
+            LispNumber::Fixnum(n) => BigInt::from(n),
+            LispNumber::Bignum(b) => b.to_bigint(),
+        };
+        accum = match code {
+            ArithOp::Add => accum + next,
+            ArithOp::Sub => {
+                if argnum > 0 {
+                    accum - next
+                } else if args.len() == 1 {
+                    -next
+                } else {
+                    next
+                }
+            }
+            ArithOp::Mult => {
+                check_bignum_bits(accum.bits() + next.bits());
+                accum * next
+            }
+            ArithOp::Div => {
+                if args.len() > 1 && argnum == 0 {
+                    next
+                } else {
+                    if next.is_zero() {
+                        arith_error!();
+                    }
+                    accum / next
+                }
+            }
+            ArithOp::Logand => accum & next,
+            ArithOp::Logior => accum | next,
+            ArithOp::Logxor => accum ^ next,
+        };
+    }
+    accum.into()
+}
+
+/// Return the number of one bits in the two's complement representation
+/// of N if it is not negative, or of zero bits if it is.
+pub fn bigint_logcount(n: &BigInt) -> usize {
+    let magnitude = if n.is_negative() {
+        !n.clone()
+    } else {
+        n.clone()
+    };
+    magnitude
+        .to_u32_digits()
+        .1
+        .iter()
+        .map(|d| d.count_ones() as usize)
+        .sum()
+}
+
+/// Format N for `format', the way the C library's `printf' formats a
+/// fixnum with CONVERSION (one of `d', `i', `o', `x' or `X') and the
+/// given flags.  PRECISION is the minimum number of digits, and the
+/// digits are padded with zeros to a total length of ZERO_WIDTH.  Unlike
+/// `printf', negative numbers are written with a minus sign in every
+/// base.
+pub fn format_bigint(
+    n: &BigInt,
+    conversion: u8,
+    plus_flag: bool,
+    space_flag: bool,
+    sharp_flag: bool,
+    precision: Option<usize>,
+    zero_width: usize,
+) -> String {
+    let (radix, prefix) = match conversion {
+        b'o' => (8, "0"),
+        b'x' => (16, "0x"),
+        b'X' => (16, "0X"),
+        _ => (10, ""),
+    };
+    let mut digits = n.abs().to_str_radix(radix);
+    if conversion == b'X' {
+        digits.make_ascii_uppercase();
+    }
+
+    let sign = if n.is_negative() {
+        "-"
+    } else if plus_flag {
+        "+"
+    } else if space_flag {
+        " "
+    } else {
+        ""
+    };
+    let prefix = if sharp_flag && !n.is_zero() {
+        prefix
+    } else {
+        ""
+    };
+
+    let mut ndigits = precision.map_or(digits.len(), |p| p.max(digits.len()));
+    if precision.is_none() {
+        ndigits = ndigits.max(zero_width.saturating_sub(sign.len() + prefix.len()));
+    }
+
+    let mut result = String::with_capacity(sign.len() + prefix.len() + ndigits);
+    result.push_str(sign);
+    result.push_str(prefix);
+    for _ in digits.len()..ndigits {
+        result.push('0');
+    }
+    result.push_str(&digits);
+    result
+}
+
+/// Return the bignum or fixnum written as the LEN digits at DIGITS, in
+/// base BASE, negated if NEGATIVE.  The reader calls this for integers
+/// that do not fit in a fixnum.
+#[no_mangle]
+pub unsafe extern "C" fn make_bignum_str(
+    digits: *const c_char,
+    len: ptrdiff_t,
+    base: c_int,
+    negative: bool,
+) -> LispObject {
+    let digits = slice::from_raw_parts(digits as *const u8, len as usize);
+    // Refuse numbers with far too many digits before converting them.
+    check_bignum_bits(((digits.len() - 1) as f64 * f64::from(base).log2()) as usize);
+    let magnitude = BigUint::parse_bytes(digits, base as u32).unwrap();
+    let sign = if negative { Sign::Minus } else { Sign::Plus };
+    BigInt::from_biguint(sign, magnitude).into()
+}
+
+/// Return the representation of the bignum BIGNUM in base BASE, as a
+/// unibyte string.
+#[no_mangle]
+pub extern "C" fn bignum_to_string(bignum: LispObject, base: c_int) -> LispObject {
+    let n = bignum.as_bigint_or_error();
+    LispObject::from(n.to_str_radix(base as u32).as_str())
+}
+
+/// Return the string that `format' inserts for the integer ARG with an
+/// integer CONVERSION.  See `format_bigint'; a negative PRECISION means
+/// there is none.
+#[no_mangle]
+pub extern "C" fn format_bignum(
+    arg: LispObject,
+    conversion: c_char,
+    plus_flag: bool,
+    space_flag: bool,
+    sharp_flag: bool,
+    precision: ptrdiff_t,
+    zero_width: ptrdiff_t,
+) -> LispObject {
+    let n = arg.as_bigint_or_error();
+    let precision = if precision < 0 {
+        None
+    } else {
+        Some(precision as usize)
+    };
+    let s = format_bigint(
+        &n,
+        conversion as u8,
+        plus_flag,
+        space_flag,
+        sharp_flag,
+        precision,
+        zero_width as usize,
+    );
+    LispObject::from(s.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(n: i64, conversion: u8, precision: Option<usize>, zero_width: usize) -> String {
+        format_bigint(
+            &BigInt::from(n),
+            conversion,
+            false,
+            false,
+            false,
+            precision,
+            zero_width,
+        )
+    }
+
+    #[test]
+    fn test_format_bigint() {
+        assert_eq!(fmt(1234, b'd', None, 0), "1234");
+        assert_eq!(fmt(-1234, b'd', None, 0), "-1234");
+        assert_eq!(fmt(255, b'x', None, 0), "ff");
+        assert_eq!(fmt(255, b'X', None, 0), "FF");
+        assert_eq!(fmt(-8, b'o', None, 0), "-10");
+        assert_eq!(fmt(42, b'd', Some(5), 0), "00042");
+        assert_eq!(fmt(-42, b'd', None, 6), "-00042");
+        // A precision disables zero padding, as in `printf'.
+        assert_eq!(fmt(42, b'd', Some(3), 6), "042");
+        let n = BigInt::from(255);
+        assert_eq!(
+            format_bigint(&n, b'x', false, false, true, None, 6),
+            "0x00ff"
+        );
+        assert_eq!(format_bigint(&n, b'd', true, false, false, None, 0), "+255");
+        assert_eq!(format_bigint(&n, b'd', false, true, false, None, 0), " 255");
+    }
+
+    #[test]
+    fn test_compare_integer_float() {
+        let n = BigInt::from(1) << 70;
+        let f = 2f64.powi(70);
+        assert_eq!(compare_integer_float(&n, f), Some(Ordering::Equal));
+        assert_eq!(compare_integer_float(&(&n + 1), f), Some(Ordering::Greater));
+        assert_eq!(compare_integer_float(&(&n - 1), f), Some(Ordering::Less));
+        assert_eq!(
+            compare_integer_float(&BigInt::from(3), 3.5),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare_integer_float(&BigInt::from(-3), -3.5),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare_integer_float(&n, EmacsDouble::INFINITY),
+            Some(Ordering::Less)
+        );
+        assert_eq!(compare_integer_float(&n, EmacsDouble::NAN), None);
+    }
+
+    #[test]
+    fn test_bigint_logcount() {
+        assert_eq!(bigint_logcount(&BigInt::from(0)), 0);
+        assert_eq!(bigint_logcount(&BigInt::from(255)), 8);
+        assert_eq!(bigint_logcount(&BigInt::from(-1)), 0);
+        assert_eq!(bigint_logcount(&BigInt::from(-256)), 8);
+        assert_eq!(bigint_logcount(&((BigInt::from(1) << 100) - 1)), 100);
+    }
+
+    #[test]
+    fn test_bigint_to_float() {
+        let huge = BigInt::from(1) << 2000;
+        assert_eq!(bigint_to_float(&huge), EmacsDouble::INFINITY);
+        assert_eq!(bigint_to_float(&-huge), EmacsDouble::NEG_INFINITY);
+        assert_eq!(bigint_to_float(&(BigInt::from(1) << 80)), 2f64.powi(80));
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/bignum_exports.rs"));
@@ -12,7 +12,7 @@ use crate::{
     remacs_sys::EmacsInt,
     remacs_sys::{case_action, casify_object, casify_region},
     remacs_sys::{control_x_map, initial_define_key, meta_map, scan_words, set_point},
-    remacs_sys::{Qdisabled, Qt},
+    remacs_sys::{Qdisabled, Qnil, Qt},
     symbols::symbol_value,
     threads::ThreadState,
 };
@@ -31,7 +31,7 @@ fn casify_word(flag: case_action, words: EmacsInt) {
         n => n,
     };
 
-    let new_pos = unsafe { casify_region(flag, buffer_ref.pt.into(), far_end.into()) };
+    let new_pos = unsafe { casify_region(flag, buffer_ref.pt.into(), far_end.into(), Qnil) };
 
     unsafe { set_point(new_pos) };
 }
@@ -43,19 +43,23 @@ fn casify_word(flag: case_action, words: EmacsInt) {
 /// The argument object is not altered--the value is a copy.  If argument
 /// is a character, characters which map to multiple code points when
 /// cased, e.g. ﬁ, are returned unchanged.
-#[lisp_fn]
-pub fn capitalize(object: LispObject) -> LispObject {
-    unsafe { casify_object(case_action::CASE_CAPITALIZE, object) }
+///
+/// Optional argument LANGUAGE selects language-specific casing rules, as
+/// in `upcase'.
+#[lisp_fn(min = "1")]
+pub fn capitalize(object: LispObject, language: LispObject) -> LispObject {
+    unsafe { casify_object(case_action::CASE_CAPITALIZE, object, language) }
 }
 
 /// Convert the region to capitalized form.
 /// This means that each word's first character is converted to either
 /// title case or upper case, and the rest to lower case.  In
 /// programs, give two arguments, the starting and ending character
-/// positions to operate on.
-#[lisp_fn(intspec = "r")]
-pub fn capitalize_region(beg: LispObject, end: LispObject) {
-    unsafe { casify_region(case_action::CASE_CAPITALIZE, beg, end) };
+/// positions to operate on.  Optional argument LANGUAGE selects
+/// language-specific casing rules, as in `upcase'.
+#[lisp_fn(min = "2", intspec = "r")]
+pub fn capitalize_region(beg: LispObject, end: LispObject, language: LispObject) {
+    unsafe { casify_region(case_action::CASE_CAPITALIZE, beg, end, language) };
 }
 
 /// Capitalize from point to the end of word, moving over.
@@ -75,21 +79,36 @@ pub fn capitalize_word(words: EmacsInt) {
 /// Convert argument to lower case and return that.
 /// The argument may be a character or string.  The result has the same type.
 /// The argument object is not altered--the value is a copy.
-#[lisp_fn]
-pub fn downcase(object: LispObject) -> LispObject {
-    unsafe { casify_object(case_action::CASE_DOWN, object) }
+///
+/// Optional argument LANGUAGE selects language-specific casing rules, as
+/// in `upcase'.
+#[lisp_fn(min = "1")]
+pub fn downcase(object: LispObject, language: LispObject) -> LispObject {
+    unsafe { casify_object(case_action::CASE_DOWN, object, language) }
 }
 
 /// Convert the region to lower case.  In programs, wants two arguments.
 /// These arguments specify the starting and ending character numbers
 /// of the region to operate on.  When used as a command, the text
-/// between point and the mark is operated on.
+/// between point and the mark is operated on.  Optional argument
+/// LANGUAGE selects language-specific casing rules, as in `upcase'.
 #[lisp_fn(
     min = "2",
     intspec = "(list (region-beginning) (region-end) (region-noncontiguous-p))"
 )]
-pub fn downcase_region(beg: LispObject, end: LispObject, region_noncontiguous_p: bool) {
-    casefiddle_region(beg, end, region_noncontiguous_p, case_action::CASE_DOWN);
+pub fn downcase_region(
+    beg: LispObject,
+    end: LispObject,
+    region_noncontiguous_p: bool,
+    language: LispObject,
+) {
+    casefiddle_region(
+        beg,
+        end,
+        region_noncontiguous_p,
+        case_action::CASE_DOWN,
+        language,
+    );
 }
 
 /// Convert to lower case from point to end of word, moving over.
@@ -108,10 +127,19 @@ pub fn downcase_word(words: EmacsInt) {
 /// The argument object is not altered--the value is a copy.  If argument
 /// is a character, characters which map to multiple code points when
 /// cased, e.g. ﬁ, are returned unchanged.
+///
+/// Characters whose case form is several characters, such as ß, are
+/// converted to all of them when the argument is a string, and
+/// `downcase' turns a capital sigma at the end of a word into final
+/// sigma.  Optional argument LANGUAGE, a string or symbol such as `tr'
+/// or `lt', selects the casing rules of that language: the dotted and
+/// dotless i of Turkish and Azerbaijani, and the dot above i and j that
+/// Lithuanian keeps under accents.  Other languages, and nil, use the
+/// default rules.
 /// See also `capitalize', `downcase' and `upcase-initials'.
-#[lisp_fn]
-pub fn upcase(object: LispObject) -> LispObject {
-    unsafe { casify_object(case_action::CASE_UP, object) }
+#[lisp_fn(min = "1")]
+pub fn upcase(object: LispObject, language: LispObject) -> LispObject {
+    unsafe { casify_object(case_action::CASE_UP, object, language) }
 }
 
 /* Like Fcapitalize but change only the initials.  */
@@ -123,9 +151,12 @@ pub fn upcase(object: LispObject) -> LispObject {
 /// type.  The argument object is not altered--the value is a copy.
 /// If argument is a character, characters which map to multiple code
 /// points when cased, e.g. ﬁ, are returned unchanged.
-#[lisp_fn]
-pub fn upcase_initials(obj: LispObject) -> LispObject {
-    unsafe { casify_object(case_action::CASE_CAPITALIZE_UP, obj) }
+///
+/// Optional argument LANGUAGE selects language-specific casing rules, as
+/// in `upcase'.
+#[lisp_fn(min = "1")]
+pub fn upcase_initials(obj: LispObject, language: LispObject) -> LispObject {
+    unsafe { casify_object(case_action::CASE_CAPITALIZE_UP, obj, language) }
 }
 
 // Like Fcapitalize_region but change only the initials.
@@ -134,23 +165,37 @@ pub fn upcase_initials(obj: LispObject) -> LispObject {
 /// This means that each word's first character is converted to either
 /// title case or upper case, and the rest are left unchanged.  In
 /// programs, give two arguments, the starting and ending character
-/// positions to operate on.
-#[lisp_fn(intspec = "r")]
-pub fn upcase_initials_region(beg: LispObject, end: LispObject) {
-    unsafe { casify_region(case_action::CASE_CAPITALIZE_UP, beg, end) };
+/// positions to operate on.  Optional argument LANGUAGE selects
+/// language-specific casing rules, as in `upcase'.
+#[lisp_fn(min = "2", intspec = "r")]
+pub fn upcase_initials_region(beg: LispObject, end: LispObject, language: LispObject) {
+    unsafe { casify_region(case_action::CASE_CAPITALIZE_UP, beg, end, language) };
 }
 
 /// Convert the region to upper case.  In programs, wants two arguments.
 /// These arguments specify the starting and ending character numbers
 /// of the region to operate on.  When used as a command, the text
 /// between point and the mark is operated on.
+/// Optional argument LANGUAGE selects language-specific casing rules, as
+/// in `upcase'.
 /// See also `capitalize-region'.
 #[lisp_fn(
     min = "2",
     intspec = "(list (region-beginning) (region-end) (region-noncontiguous-p))"
 )]
-pub fn upcase_region(beg: LispObject, end: LispObject, region_noncontiguous_p: bool) {
-    casefiddle_region(beg, end, region_noncontiguous_p, case_action::CASE_UP);
+pub fn upcase_region(
+    beg: LispObject,
+    end: LispObject,
+    region_noncontiguous_p: bool,
+    language: LispObject,
+) {
+    casefiddle_region(
+        beg,
+        end,
+        region_noncontiguous_p,
+        case_action::CASE_UP,
+        language,
+    );
 }
 
 /// Convert to upper case from point to end of word, moving over.
@@ -172,9 +217,10 @@ fn casefiddle_region(
     end: LispObject,
     region_noncontiguous_p: bool,
     action: case_action,
+    language: LispObject,
 ) {
     if !region_noncontiguous_p {
-        unsafe { casify_region(action, beg, end) };
+        unsafe { casify_region(action, beg, end, language) };
     } else {
         let bounds = call!(
             symbol_value(intern("region-extract-function")),
@@ -183,7 +229,7 @@ fn casefiddle_region(
 
         for elt in bounds.iter_cars(LispConsEndChecks::off, LispConsCircularChecks::off) {
             let (car, cdr) = elt.into();
-            unsafe { casify_region(action, car, cdr) };
+            unsafe { casify_region(action, car, cdr, language) };
         }
     }
 }
@@ -195,6 +241,7 @@ pub extern "C" fn syms_of_casefiddle() {
     def_lisp_sym!(Qspecial_uppercase, "special-uppercase");
     def_lisp_sym!(Qspecial_lowercase, "special-lowercase");
     def_lisp_sym!(Qspecial_titlecase, "special-titlecase");
+    def_lisp_sym!(Qcanonical_combining_class, "canonical-combining-class");
 }
 
 #[no_mangle]
@@ -1,6 +1,9 @@
 //! Operations on characters.
 
+use std::convert::TryFrom;
+
 use libc::{c_uchar, ptrdiff_t};
+use unicode_width::UnicodeWidthChar;
 
 use remacs_macros::lisp_fn;
 
@@ -8,6 +11,7 @@ use crate::{
     lisp::LispObject,
     multibyte::{char_resolve_modifier_mask, Codepoint, MAX_CHAR},
     remacs_sys::EmacsInt,
+    remacs_sys::{char_width, disp_char_vector, globals, Lisp_Char_Table, Qnil},
     threads::ThreadState,
 };
 
@@ -101,4 +105,97 @@ pub fn char_resolve_modifiers(character: LispObject) -> EmacsInt {
     char_resolve_modifier_mask(character.into())
 }
 
+/// Return true if C has the East Asian Width property Ambiguous: it is
+/// wide in East Asian contexts and narrow elsewhere.
+fn is_east_asian_ambiguous(c: Codepoint) -> bool {
+    char::try_from(u32::from(c)).map_or(false, |c| c.width() != c.width_cjk())
+}
+
+/// Return the width of C when displayed with the display table DP.
+/// This is `char_width', except that characters of ambiguous East Asian
+/// width take `east-asian-ambiguous-width' columns when it is set and DP
+/// does not say how to display them.
+pub fn char_display_width(c: Codepoint, dp: *mut Lisp_Char_Table) -> usize {
+    let ambiguous_width = unsafe { globals.Veast_asian_ambiguous_width }.as_natnum();
+    if let Some(width) = ambiguous_width {
+        if is_east_asian_ambiguous(c)
+            && (dp.is_null() || !unsafe { disp_char_vector(dp, c.val() as i32) }.is_vector())
+        {
+            return width as usize;
+        }
+    }
+    unsafe { char_width(c.val() as i32, dp) as usize }
+}
+
+const ZERO_WIDTH_JOINER: u32 = 0x200D;
+const TEXT_PRESENTATION_SELECTOR: u32 = 0xFE0E;
+const EMOJI_PRESENTATION_SELECTOR: u32 = 0xFE0F;
+
+fn is_regional_indicator(c: Codepoint) -> bool {
+    (0x1F1E6..=0x1F1FF).contains(&u32::from(c))
+}
+
+/// Return the width of the grapheme cluster CLUSTER when displayed with
+/// the display table DP.
+///
+/// This is the sum of the widths of its characters, except that a pair
+/// of regional indicators is a flag two columns wide, the characters an
+/// emoji is joined to with ZERO WIDTH JOINER are drawn over it, emoji
+/// modifiers and tags take no room of their own, and a variation
+/// selector makes the character before it one column wide for text
+/// presentation (VS15) or two for emoji presentation (VS16).
+pub fn grapheme_width(cluster: &[Codepoint], dp: *mut Lisp_Char_Table) -> usize {
+    if let [first, second] = *cluster {
+        if is_regional_indicator(first) && is_regional_indicator(second) {
+            return 2;
+        }
+    }
+
+    let mut width = 0;
+    // The width of the last character that took up columns.
+    let mut base: Option<usize> = None;
+    let mut joined = false;
+    for &c in cluster {
+        match u32::from(c) {
+            ZERO_WIDTH_JOINER => joined = true,
+            TEXT_PRESENTATION_SELECTOR | EMOJI_PRESENTATION_SELECTOR if base.is_some() => {
+                let presentation_width = if u32::from(c) == EMOJI_PRESENTATION_SELECTOR {
+                    2
+                } else {
+                    1
+                };
+                width = width - base.unwrap() + presentation_width;
+                base = Some(presentation_width);
+            }
+            // Emoji modifiers and tags.
+            0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F if base.is_some() => {}
+            _ if joined => joined = false,
+            _ => {
+                let w = char_display_width(c, dp);
+                width += w;
+                base = Some(w);
+            }
+        }
+    }
+    width
+}
+
 include!(concat!(env!("OUT_DIR"), "/character_exports.rs"));
+
+#[allow(unused_doc_comments)]
+#[no_mangle]
+pub extern "C" fn rust_syms_of_character() {
+    /// Width of characters whose East Asian Width is Ambiguous.
+    /// These characters, such as Greek and Cyrillic letters and many
+    /// symbols, are displayed one column wide by most terminals and two
+    /// columns wide by terminals set up for Chinese, Japanese or Korean.
+    /// If the value is a natural number, such characters are taken to
+    /// occupy that many columns by `string-width', `current-column' and
+    /// `move-to-column', unless the display table says how to display
+    /// them.  If nil, their width is taken from `char-width-table'.
+    defvar_lisp!(
+        Veast_asian_ambiguous_width,
+        "east-asian-ambiguous-width",
+        Qnil
+    );
+}
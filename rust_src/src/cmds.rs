@@ -25,9 +25,10 @@ use crate::{
     remacs_sys::{Fchar_width, Fmake_string, Fmove_to_column},
     remacs_sys::{
         Qbeginning_of_buffer, Qend_of_buffer, Qexpand_abbrev, Qinternal_auto_fill,
-        Qkill_forward_chars, Qnil, Qoverwrite_mode_binary, Qpost_self_insert_hook,
+        Qkill_forward_chars, Qkill_region, Qnil, Qoverwrite_mode_binary, Qpost_self_insert_hook,
         Qundo_auto__this_command_amalgamating, Qundo_auto_amalgamate,
     },
+    segmentation::forward_grapheme_position,
     threads::ThreadState,
 };
 
@@ -94,6 +95,43 @@ pub fn backward_char(n: LispObject) {
     move_point(n, false)
 }
 
+/// Move point N grapheme clusters forward, or backward if FORWARD is
+/// false.  N defaults to 1.
+fn move_point_by_graphemes(n: Option<EmacsInt>, forward: bool) {
+    let n = n.unwrap_or(1);
+    let pt = ThreadState::current_buffer_unchecked().pt;
+    let (new_point, shortage) = forward_grapheme_position(pt, if forward { n } else { -n });
+
+    unsafe { set_point(new_point) };
+    if shortage > 0 {
+        xsignal!(Qend_of_buffer);
+    } else if shortage < 0 {
+        xsignal!(Qbeginning_of_buffer);
+    }
+}
+
+/// Move point N grapheme clusters forward (backward if N is negative).
+/// A grapheme cluster is what is perceived as a single character, such
+/// as a base character with its combining marks, an emoji ZWJ sequence
+/// or a flag; see `string-grapheme-boundaries'.
+/// On reaching end or beginning of buffer, stop and signal error.
+/// Interactively, N is the numeric prefix argument.
+/// If N is omitted or nil, move point 1 grapheme cluster forward.
+#[lisp_fn(min = "0", intspec = "^p")]
+pub fn forward_grapheme(n: Option<EmacsInt>) {
+    move_point_by_graphemes(n, true)
+}
+
+/// Move point N grapheme clusters backward (forward if N is negative).
+/// On attempt to pass beginning or end of buffer, stop and signal error.
+/// Interactively, N is the numeric prefix argument.
+/// If N is omitted or nil, move point 1 grapheme cluster backward.
+/// See `forward-grapheme'.
+#[lisp_fn(min = "0", intspec = "^p")]
+pub fn backward_grapheme(n: Option<EmacsInt>) {
+    move_point_by_graphemes(n, false)
+}
+
 /// Return buffer position N characters after (before if N negative) point.
 #[lisp_fn]
 pub fn forward_point(n: EmacsInt) -> EmacsInt {
@@ -233,6 +271,35 @@ pub fn delete_char(n: EmacsInt, killflag: bool) {
     }
 }
 
+/// Delete the following N grapheme clusters (previous if N is negative).
+/// A grapheme cluster is what is perceived as a single character, such
+/// as a base character with its combining marks, an emoji ZWJ sequence
+/// or a flag; see `string-grapheme-boundaries'.
+/// Optional second arg KILLFLAG non-nil means kill instead (save in kill ring).
+/// Interactively, N is the prefix arg, and KILLFLAG is set if
+/// N was explicitly specified.
+#[lisp_fn(min = "1", intspec = "p\nP")]
+pub fn delete_grapheme(n: EmacsInt, killflag: bool) {
+    if n.abs() < 2 {
+        call!(Qundo_auto_amalgamate);
+    }
+
+    let pt = ThreadState::current_buffer_unchecked().pt;
+    let (pos, shortage) = forward_grapheme_position(pt, n);
+    if shortage > 0 {
+        xsignal!(Qend_of_buffer);
+    } else if shortage < 0 {
+        xsignal!(Qbeginning_of_buffer);
+    }
+
+    let (from, to) = if pos < pt { (pos, pt) } else { (pt, pos) };
+    if killflag {
+        call!(Qkill_region, from.into(), to.into());
+    } else {
+        unsafe { del_range(from, to) };
+    }
+}
+
 // Note that there's code in command_loop_1 which typically avoids
 // calling this.
 
@@ -511,6 +578,7 @@ pub extern "C" fn syms_of_cmds() {
     #[rustfmt::skip]
     def_lisp_sym!(Qundo_auto__this_command_amalgamating, "undo-auto--this-command-amalgamating");
     def_lisp_sym!(Qkill_forward_chars, "kill-forward-chars");
+    def_lisp_sym!(Qkill_region, "kill-region");
     // A possible value for a buffer's overwrite-mode variable.
     def_lisp_sym!(Qoverwrite_mode_binary, "overwrite-mode-binary");
     def_lisp_sym!(Qexpand_abbrev, "expand-abbrev");
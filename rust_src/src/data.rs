@@ -7,7 +7,9 @@ use remacs_macros::lisp_fn;
 
 use crate::{
     alloc::purecopy,
+    bignum,
     buffers::{per_buffer_idx, per_buffer_idx_from_field_offset},
+    floatfns::parse_hex_float,
     frame::selected_frame,
     keymap::get_keymap,
     lisp::is_autoload,
@@ -32,9 +34,10 @@ use crate::{
         Qarrayp, Qautoload, Qbool_vector, Qbuffer, Qchar_table, Qchoice, Qcompiled_function,
         Qcondition_variable, Qcons, Qcyclic_function_indirection, Qdefalias_fset_function, Qdefun,
         Qfinalizer, Qfloat, Qfont, Qfont_entity, Qfont_object, Qfont_spec, Qframe,
-        Qfunction_documentation, Qhash_table, Qinteger, Qmany, Qmarker, Qmodule_function, Qmutex,
-        Qnil, Qnone, Qoverlay, Qprocess, Qrange, Qstring, Qsubr, Qsymbol, Qterminal, Qthread,
-        Qunbound, Qunevalled, Quser_ptr, Qvector, Qwatchers, Qwindow, Qwindow_configuration,
+        Qfunction_documentation, Qhash_table, Qinteger, Qinvalid_read_syntax, Qmany, Qmarker,
+        Qmodule_function, Qmutex, Qnil, Qnone, Qoverlay, Qprocess, Qrange, Qstring, Qsubr, Qsymbol,
+        Qterminal, Qthread, Qunbound, Qunevalled, Quser_ptr, Qvector, Qwatchers, Qwindow,
+        Qwindow_configuration,
     },
     symbols::LispSymbolRef,
     threads::ThreadState,
@@ -143,6 +146,7 @@ pub fn type_of(object: LispObject) -> LispObject {
                 pvec_type::PVEC_CONDVAR => Qcondition_variable,
                 pvec_type::PVEC_TERMINAL => Qterminal,
                 pvec_type::PVEC_MODULE_FUNCTION => Qmodule_function,
+                pvec_type::PVEC_BIGNUM => Qinteger,
                 pvec_type::PVEC_FONT => {
                     if object.is_font_spec() {
                         Qfont_spec
@@ -781,9 +785,14 @@ pub fn get_variable_watchers(symbol: LispSymbolRef) -> LispObject {
 /// of VALUE.  If VALUE is negative, return the number of zero bits in the
 /// representation.
 #[lisp_fn]
-pub fn logcount(value: EmacsInt) -> i32 {
-    let value = if value < 0 { -1 - value } else { value };
-    unsafe { rust_count_one_bits(value as usize) }
+pub fn logcount(value: LispObject) -> i32 {
+    match value.as_fixnum() {
+        Some(value) => {
+            let value = if value < 0 { -1 - value } else { value };
+            unsafe { rust_count_one_bits(value as usize) }
+        }
+        None => bignum::bigint_logcount(&value.as_bigint_or_error()) as i32,
+    }
 }
 
 /// Set SYMBOL's function definition to DEFINITION, and return DEFINITION.
@@ -827,30 +836,119 @@ pub fn fset(mut symbol: LispSymbolRef, definition: LispObject) -> LispObject {
 ///
 /// If BASE, interpret STRING as a number in that base.  If BASE isn't
 /// present, base 10 is used.  BASE must be between 2 and 16 (inclusive).
-/// If the base used is not 10, STRING is always parsed as an integer.
+/// If the base used is not 10, STRING is parsed as an integer, except
+/// after a `#x' prefix, where it may be a float as described below.
+///
+/// If BASE isn't present, STRING may start with one of the radix
+/// prefixes `#x', `#o' and `#b' that `read' accepts, after an optional
+/// sign, to be read in base 16, 8 or 2.
+///
+/// Digits may be grouped with `_' characters, which are ignored when
+/// they are between two digits, as in "1_000_000".
+///
+/// In base 10, a number may have an exponent, as in "1e-3".  Unless
+/// BASE is given and is not 10, a hexadecimal float with a binary
+/// exponent as in C is also accepted: "0x1.8p3" or "#x1.8p3" is 12.0.
+/// Such a float is read exactly, so it can represent any float without
+/// rounding.
+///
+/// If STRICT is non-nil, the whole of STRING except for surrounding
+/// whitespace must be a number, and an `invalid-read-syntax' error is
+/// signaled if it is not, instead of returning 0 or a number read from
+/// the start of STRING.
 #[lisp_fn(min = "1", name = "string-to-number", c_name = "string_to_number")]
-pub fn string_to_number_lisp(mut string: LispStringRef, base: Option<EmacsInt>) -> LispObject {
-    let b = match base {
+pub fn string_to_number_lisp(
+    string: LispStringRef,
+    base: Option<EmacsInt>,
+    strict: bool,
+) -> LispObject {
+    let mut b = match base {
         None => 10,
         Some(n) => {
             if n < 2 || n > 16 {
                 args_out_of_range!(base, 2, 16)
             }
-            n
+            n as u32
         }
     };
 
-    let mut p = string.sdata_ptr();
-    unsafe {
-        while *p == ' ' as c_char || *p == '\t' as c_char {
-            p = p.offset(1);
+    let is_blank = |c: &u8| *c == b' ' || *c == b'\t' || (strict && (*c == b'\n' || *c == b'\r'));
+    let mut text = string.as_slice();
+    while text.first().map_or(false, is_blank) {
+        text = &text[1..];
+    }
+    while strict && text.last().map_or(false, is_blank) {
+        text = &text[..text.len() - 1];
+    }
+
+    // As in `read', the sign may come before or after a radix prefix.
+    let is_sign = |text: &[u8]| text.starts_with(b"-") || text.starts_with(b"+");
+    let mut sign: &[u8] = b"";
+    if is_sign(text) {
+        sign = &text[..1];
+        text = &text[1..];
+    }
+    if base.is_none() && text.first() == Some(&b'#') {
+        let prefix_base = match text.get(1) {
+            Some(b'x') | Some(b'X') => Some(16),
+            Some(b'o') | Some(b'O') => Some(8),
+            Some(b'b') | Some(b'B') => Some(2),
+            _ => None,
+        };
+        if let Some(prefix_base) = prefix_base {
+            b = prefix_base;
+            text = &text[2..];
+            if sign.is_empty() && is_sign(text) {
+                sign = &text[..1];
+                text = &text[1..];
+            }
         }
     }
+    let digits = strip_digit_separators(text, b);
+
+    let invalid = || -> ! {
+        xsignal!(
+            Qinvalid_read_syntax,
+            LispObject::from("Invalid number syntax"),
+            LispObject::from(string)
+        )
+    };
 
-    match unsafe { string_to_number(p, b as i32, true) } {
+    if b == 10 || (b == 16 && base.is_none()) {
+        // After "#x", the "0x" of a hexadecimal float is implied.
+        let mut hex = sign.to_vec();
+        if b == 16 && !(digits.starts_with(b"0x") || digits.starts_with(b"0X")) {
+            hex.extend_from_slice(b"0x");
+        }
+        hex.extend_from_slice(&digits);
+        if let Some((f, len)) = parse_hex_float(&hex) {
+            if strict && len < hex.len() {
+                invalid();
+            }
+            return LispObject::from_float(f);
+        }
+    }
+
+    let mut number = sign.to_vec();
+    number.extend(digits);
+    number.push(0);
+    match unsafe { string_to_number(number.as_ptr() as *const c_char, b as i32, !strict) } {
+        Qnil if strict => invalid(),
         Qnil => LispObject::from(0),
         n => n,
     }
 }
 
+/// Return S without the `_' characters between two digits in base BASE.
+fn strip_digit_separators(s: &[u8], base: u32) -> Vec<u8> {
+    let is_digit = |c: Option<&u8>| c.map_or(false, |&c| (c as char).is_digit(base));
+    s.iter()
+        .enumerate()
+        .filter(|&(i, &c)| {
+            !(c == b'_' && i > 0 && is_digit(s.get(i - 1)) && is_digit(s.get(i + 1)))
+        })
+        .map(|(_, &c)| c)
+        .collect()
+}
+
 include!(concat!(env!("OUT_DIR"), "/data_exports.rs"));
@@ -4,41 +4,74 @@ use remacs_macros::lisp_fn;
 
 #[cfg(unix)]
 use crate::dired_unix::{
-    directory_files_and_attributes_intro, directory_files_intro, file_attributes_intro, get_groups,
+    directory_disk_usage_intro, directory_files_and_attributes_intro, directory_files_intro,
+    directory_files_walk_intro, file_attributes_extended_intro, file_attributes_intro, get_groups,
     get_users,
 };
 #[cfg(windows)]
 use dired_windows::{file_attributes_intro, get_groups, get_users};
 
-use crate::{lisp::LispObject, lists::car, multibyte::LispStringRef, strings::string_lessp};
+use crate::{
+    lisp::LispObject,
+    lists::car,
+    multibyte::LispStringRef,
+    remacs_sys::{EmacsUint, Qnil},
+    strings::string_lessp,
+};
 
 /// Return a list of names of files in DIRECTORY.
-/// There are three optional arguments:
+/// There are four optional arguments:
 /// If FULL is non-nil, return absolute file names.  Otherwise return names
 ///  that are relative to the specified directory.
 /// If MATCH is non-nil, mention only file names that match the regexp MATCH.
-/// If NOSORT is non-nil, the list is not sorted--its order is unpredictable.
-///  Otherwise, the list returned is sorted with `string-lessp'.
-///  NOSORT is useful if you plan to sort the result yourself.
+///  MATCH may also be a cons (glob . PATTERN), where PATTERN is a shell
+///  glob such as "*.rs", "*.{c,h}" or "[a-z]*.el".  `*' and `?' do not
+///  match a slash, `[...]' and `[!...]' are character classes, `{a,b}' are
+///  alternatives, and a backslash quotes the next character.  The glob is
+///  matched against the file name alone, so a PATTERN containing a slash
+///  signals an error; use `directory-files-walk' to match subdirectories.
+/// If NOSORT is nil, the list is sorted with `string-lessp'.
+///  If NOSORT is one of the symbols below, the list is sorted by that key,
+///  in ascending order:
+///   `name'      -- the file name, as with `string-lessp';
+///   `natural'   -- the file name, comparing runs of digits numerically
+///                  as with `string-version-lessp';
+///   `extension' -- the file name extension, then the file name;
+///   `mtime'     -- the last modification time, oldest first;
+///   `size'      -- the size in bytes, smallest first.
+///  NOSORT may also be a cons (KEY . DESCENDING), where KEY is one of the
+///  above; if DESCENDING is non-nil, the order is reversed.
+///  Any other non-nil value means the list is not sorted--its order is
+///  unpredictable.  This is useful if you plan to sort the result yourself.
+/// If COUNT is a natural number, return at most COUNT file names.  The
+///  list is sorted before it is truncated, so for instance
+///  (directory-files DIR nil nil \\='(mtime . t) 10) returns the ten most
+///  recently modified files.
 #[lisp_fn(min = "1")]
 pub fn directory_files(
     directory: LispStringRef,
     full: LispObject,
     match_re: LispObject,
     nosort: LispObject,
+    count: Option<EmacsUint>,
 ) -> LispObject {
-    directory_files_intro(directory, full, match_re, nosort)
+    directory_files_intro(directory, full, match_re, nosort, count)
 }
 
 /// Return a list of names of files and their attributes in DIRECTORY.
-/// There are four optional arguments:
+/// There are five optional arguments:
 /// If FULL is non-nil, return absolute file names.  Otherwise return names
 ///  that are relative to the specified directory.
 /// If MATCH is non-nil, mention only file names that match the regexp MATCH.
-/// If NOSORT is non-nil, the list is not sorted--its order is unpredictable.
+///  MATCH may also be a shell glob, see `directory-files'.
+/// If NOSORT is nil, the list is sorted with `string-lessp'.  NOSORT may
+///  also be a sort key, see `directory-files'.  Any other non-nil value
+///  means the list is not sorted--its order is unpredictable.
 ///  NOSORT is useful if you plan to sort the result yourself.
 /// ID-FORMAT specifies the preferred format of attributes uid and gid, see
 /// `file-attributes' for further documentation.
+/// If COUNT is a natural number, return at most COUNT entries, after
+///  sorting.  Attributes are only collected for the entries returned.
 /// On MS-Windows, performance depends on `w32-get-true-file-attributes',
 /// which see.
 #[lisp_fn(min = "1")]
@@ -48,10 +81,80 @@ pub fn directory_files_and_attributes(
     match_re: LispObject,
     nosort: LispObject,
     id_format: LispObject,
+    count: Option<EmacsUint>,
+) -> LispObject {
+    directory_files_and_attributes_intro(directory, full, match_re, nosort, id_format, count)
+}
+
+/// Return a list of all files under DIRECTORY, walking it recursively.
+/// Files are returned in "depth first" order: the contents of each
+/// subdirectory come before the files of its parent, and the entries of
+/// each directory are visited in `string-lessp' order.  Each file name
+/// appears in the returned list in its absolute form.
+/// There are six optional arguments:
+/// If MATCH is non-nil, mention only files whose base names match the
+///  regexp MATCH.  MATCH may also be a shell glob (glob . PATTERN), see
+///  `directory-files'.  If PATTERN contains a slash, it is matched against
+///  the file name relative to DIRECTORY, and `**' matches any number of
+///  directories: (glob . "**/*.el") matches every Lisp file in the tree,
+///  and (glob . "lisp/*.el") only those directly in its lisp directory.
+/// If INCLUDE-DIRECTORIES is non-nil, also mention directories whose
+///  names match MATCH, after their contents.
+/// If MAX-DEPTH is non-nil, it is the number of directory levels below
+///  DIRECTORY to descend into; 0 means to list DIRECTORY only.
+/// If FOLLOW-SYMLINKS is non-nil, descend into symbolic links to
+///  directories.  Each directory is visited at most once, so cycles are
+///  harmless.  Otherwise such links are treated as leaf directories.
+/// If PREDICATE is non-nil, it is called with the absolute name of each
+///  directory before descending into it; a nil value prunes that
+///  directory.  The directory itself is still listed if it matches.
+/// If ATTRS is non-nil, each element is (FILE . ATTRIBUTES) as in
+///  `directory-files-and-attributes', collected during the walk.  ATTRS
+///  may be `string' or `integer' to choose the ID-FORMAT of the
+///  attributes, see `file-attributes'.
+/// Subdirectories that cannot be read are silently skipped.
+#[lisp_fn(min = "1")]
+pub fn directory_files_walk(
+    directory: LispStringRef,
+    match_re: LispObject,
+    include_directories: LispObject,
+    max_depth: Option<EmacsUint>,
+    follow_symlinks: LispObject,
+    predicate: LispObject,
+    attrs: LispObject,
 ) -> LispObject {
-    directory_files_and_attributes_intro(directory, full, match_re, nosort, id_format)
+    directory_files_walk_intro(
+        directory,
+        match_re,
+        include_directories,
+        max_depth,
+        follow_symlinks,
+        predicate,
+        attrs,
+    )
 }
 
+def_lisp_sym!(Qdirectory_files_walk, "directory-files-walk");
+def_lisp_sym!(Qglob, "glob");
+
+/// Return the disk usage of DIRECTORY and everything below it.
+/// The value is a list (APPARENT-SIZE ALLOCATED-SIZE FILES): the sum of
+/// the sizes in bytes, as in `file-attribute-size'; the number of bytes
+/// actually allocated on disk, which is smaller for sparse or compressed
+/// files and usually larger for small ones; and the number of files,
+/// including DIRECTORY and its subdirectories.
+/// Symbolic links are counted but not followed.  A file with several hard
+///  links is only counted once.
+/// If ONE-FILE-SYSTEM is non-nil, leave out directories on file systems
+///  other than the one DIRECTORY is on.
+/// Subdirectories that cannot be read are counted, but not their contents.
+#[lisp_fn(min = "1")]
+pub fn directory_disk_usage(directory: LispStringRef, one_file_system: LispObject) -> LispObject {
+    directory_disk_usage_intro(directory, one_file_system)
+}
+
+def_lisp_sym!(Qdirectory_disk_usage, "directory-disk-usage");
+
 /// Return a list of attributes of file FILENAME.
 /// Value is nil if specified file cannot be opened.
 ///
@@ -106,6 +209,41 @@ pub fn file_attributes(filename: LispStringRef, id_format: LispObject) -> LispOb
     file_attributes_intro(filename, id_format)
 }
 
+/// Return a plist of the attributes of FILENAME not covered by `file-attributes'.
+/// Value is nil if FILENAME cannot be accessed.  If FILENAME is a symbolic
+/// link, the attributes are those of the link itself.
+///
+/// The plist has the following properties:
+///  :birth-time  The creation time of the file, as in `current-time', or
+///               nil if the operating system or file system does not
+///               record it.
+///  :modes       The file modes, as a string of ten letters or dashes
+///               as in ls -l; the same as `file-attribute-modes'.
+///  :xattrs      An alist of extended attributes, (NAME . VALUE), with
+///               NAME and VALUE both unibyte strings.
+///  :acl         Non-nil if the file has an access control list beyond
+///               its mode bits.
+///  :filesystem-type  The type of the file system holding the file, such
+///               as "ext4" or "nfs4", or nil if unknown.
+///  :mount-point The directory on which that file system is mounted.
+///  :mount-source The device or other source of that file system.
+///
+/// Extended attributes and mount information are currently only available
+/// on GNU/Linux; elsewhere those properties are nil.
+#[lisp_fn]
+pub fn file_attributes_extended(filename: LispStringRef) -> LispObject {
+    file_attributes_extended_intro(filename)
+}
+
+def_lisp_sym!(Qfile_attributes_extended, "file-attributes-extended");
+def_lisp_sym!(QCbirth_time, ":birth-time");
+def_lisp_sym!(QCmodes, ":modes");
+def_lisp_sym!(QCxattrs, ":xattrs");
+def_lisp_sym!(QCacl, ":acl");
+def_lisp_sym!(QCfilesystem_type, ":filesystem-type");
+def_lisp_sym!(QCmount_point, ":mount-point");
+def_lisp_sym!(QCmount_source, ":mount-source");
+
 /// Return t if first arg file attributes list is less than second.
 /// Comparison is in lexicographic order and case is significant.
 #[lisp_fn]
@@ -128,4 +266,22 @@ pub fn system_groups() -> LispObject {
     get_groups()
 }
 
+#[allow(unused_doc_comments)]
+#[no_mangle]
+pub extern "C" fn rust_syms_of_dired() {
+    /// Non-nil means directory listings leave out files excluded by ignore files.
+    /// This applies to `directory-files', `directory-files-and-attributes'
+    /// and `directory-files-walk'.  Files are excluded by the patterns in
+    /// `.gitignore' and `.ignore' files in the listed directory and its
+    /// parents, in `.git/info/exclude', and in the global git excludes file
+    /// (`core.excludesFile', by default "~/.config/git/ignore"), following
+    /// the same rules as the `ignore' Rust crate and tools such as ripgrep.
+    /// Git's own ignore files only count inside a git working tree.
+    /// Hidden files are not excluded unless an ignore file says so.
+    /// Bind this around calls that list project files, rather than setting
+    /// it globally.
+    #[rustfmt::skip]
+    defvar_lisp!(Vdirectory_files_respect_ignore_files, "directory-files-respect-ignore-files", Qnil);
+}
+
 include!(concat!(env!("OUT_DIR"), "/dired_exports.rs"));
@@ -3,29 +3,45 @@ use libc::{
     size_t, ssize_t, timespec as c_timespec,
 };
 
-use std::ffi::{CStr, CString, OsStr};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString, OsStr, OsString};
 use std::fs;
 use std::io;
+use std::iter::Peekable;
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs::MetadataExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::ptr::null_mut;
 use std::slice;
+use std::str::Chars;
+use std::time::UNIX_EPOCH;
+
+use globset::{GlobBuilder, GlobMatcher};
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder, Glob},
+    Match,
+};
 
 use crate::{
-    coding::decode_system,
+    coding::{decode_system, encode_file_name},
     fileio::{expand_file_name, find_file_name_handler},
+    fns::nreverse,
     lisp::LispObject,
-    lists::list,
+    lists::{car, cdr, list, nth},
+    lists::{LispConsCircularChecks, LispConsEndChecks},
     multibyte::LispStringRef,
     remacs_sys::{
-        build_string, compile_pattern, decode_file_name, file_attributes_c_internal,
-        filemode_string, globals, re_pattern_buffer, re_search,
+        build_string, compile_pattern, decode_file_name, file_attributes_c_internal, globals,
+        make_unibyte_string, maybe_quit, re_pattern_buffer, re_search, EmacsDouble, EmacsUint,
     },
     remacs_sys::{
-        Qdirectory_files, Qdirectory_files_and_attributes, Qfile_attributes, Qfile_missing, Qnil,
-        Qt,
+        QCacl, QCbirth_time, QCfilesystem_type, QCmodes, QCmount_point, QCmount_source, QCxattrs,
+        Qdirectory_disk_usage, Qdirectory_files, Qdirectory_files_and_attributes,
+        Qdirectory_files_walk, Qfile_attributes, Qfile_attributes_extended, Qfile_missing, Qglob,
+        Qnil, Qt,
     },
-    time::make_lisp_time,
+    time::{float_time, make_lisp_time},
 };
 
 trait StringExt {
@@ -128,6 +144,7 @@ struct DirReq {
     match_re: Option<LispObject>, // filter regexp
     sortmemaybe: SortFNames,
     id_format: LispObject, // integer uid (default) or string username
+    count: Option<usize>,  // maximum number of names, applied after sorting
 }
 
 impl DirReq {
@@ -137,6 +154,7 @@ impl DirReq {
         match_re: Option<LispObject>,
         sortmemaybe: SortFNames,
         id_format: LispObject,
+        count: Option<usize>,
     ) -> Self {
         Self {
             dname,
@@ -144,13 +162,208 @@ impl DirReq {
             match_re,
             sortmemaybe,
             id_format,
+            count,
         }
     }
 }
 
+#[derive(Clone, Copy)]
+enum SortKey {
+    Name,
+    Natural,
+    Extension,
+    Mtime,
+    Size,
+}
+
 enum SortFNames {
     No,
-    Yes,
+    By { key: SortKey, descending: bool },
+}
+
+impl SortFNames {
+    // Interpret the NOSORT argument: nil sorts by name, a sort key
+    // symbol or (KEY . DESCENDING) sorts by that key, and any other
+    // non-nil value leaves the names unsorted.
+    fn from_nosort(nosort: LispObject) -> Self {
+        if nosort.is_nil() {
+            return SortFNames::By {
+                key: SortKey::Name,
+                descending: false,
+            };
+        }
+
+        let (key, descending) = match nosort.as_cons() {
+            Some(cons) => (cons.car(), cons.cdr().is_not_nil()),
+            None => (nosort, false),
+        };
+        if !key.is_symbol() {
+            return SortFNames::No;
+        }
+
+        let key = match key.to_idfstring().as_str() {
+            "name" => SortKey::Name,
+            "natural" => SortKey::Natural,
+            "extension" => SortKey::Extension,
+            "mtime" => SortKey::Mtime,
+            "size" => SortKey::Size,
+            _ => return SortFNames::No,
+        };
+
+        SortFNames::By { key, descending }
+    }
+
+    fn sort(&self, fnames: &mut Vec<String>, dname: &str) {
+        let (key, descending) = match *self {
+            SortFNames::No => return,
+            SortFNames::By { key, descending } => (key, descending),
+        };
+
+        match key {
+            SortKey::Name => fnames.sort(),
+            SortKey::Natural => fnames.sort_by(|a, b| natural_cmp(a, b).then_with(|| a.cmp(b))),
+            SortKey::Extension => fnames.sort_by(|a, b| {
+                let ext = |f: &str| Path::new(f).extension().map(OsStr::to_owned);
+                ext(a).cmp(&ext(b)).then_with(|| a.cmp(b))
+            }),
+            SortKey::Mtime | SortKey::Size => {
+                // Stat each file once rather than on every comparison.
+                // Files that vanished meanwhile sort as if empty and old.
+                let mut keyed: Vec<((i64, i64), String)> = fnames
+                    .drain(..)
+                    .map(|f| {
+                        let md = fs::symlink_metadata(f.to_full(dname.to_owned()));
+                        let k = match (key, md) {
+                            (SortKey::Size, Ok(md)) => (md.size() as i64, 0),
+                            (_, Ok(md)) => (md.mtime(), md.mtime_nsec()),
+                            (_, Err(_)) => (0, 0),
+                        };
+                        (k, f)
+                    })
+                    .collect();
+                keyed.sort();
+                fnames.extend(keyed.into_iter().map(|(_, f)| f));
+            }
+        }
+
+        if descending {
+            fnames.reverse();
+        }
+    }
+
+    // Whether NOSORT asked for the order file name handlers produce
+    // themselves, or none at all.
+    fn handler_sorts(&self) -> bool {
+        match *self {
+            SortFNames::No => true,
+            SortFNames::By { key, descending } => match key {
+                SortKey::Name => !descending,
+                _ => false,
+            },
+        }
+    }
+
+    // Sort ENTRIES, the result of a file name handler for DNAME: file
+    // names, or (NAME . ATTRIBUTES) conses when ATTRS.  Handlers are
+    // asked for unsorted lists, as they only know NOSORT as a flag.
+    fn sort_handler_entries(
+        &self,
+        entries: &mut Vec<LispObject>,
+        dname: LispStringRef,
+        attrs: bool,
+    ) {
+        let (key, descending) = match *self {
+            SortFNames::No => return,
+            SortFNames::By { key, descending } => (key, descending),
+        };
+
+        let name_of = |entry: LispObject| {
+            let name = if attrs { car(entry) } else { entry };
+            (name, name.to_stdstring())
+        };
+        let mut keyed: Vec<(EmacsDouble, String, LispObject)> = entries
+            .drain(..)
+            .map(|entry| {
+                let (name, name_s) = name_of(entry);
+                let k = match key {
+                    SortKey::Mtime | SortKey::Size => {
+                        let fattrs = if attrs {
+                            cdr(entry)
+                        } else {
+                            file_attributes_intro(expand_file_name(name.into(), Some(dname)), Qnil)
+                        };
+                        match (key, fattrs.is_nil()) {
+                            (_, true) => 0.0,
+                            (SortKey::Size, false) => nth(7, fattrs).any_to_float_or_error(),
+                            (_, false) => float_time(nth(5, fattrs)),
+                        }
+                    }
+                    _ => 0.0,
+                };
+                (k, name_s, entry)
+            })
+            .collect();
+
+        keyed.sort_by(|(ka, a, _), (kb, b, _)| match key {
+            SortKey::Name => a.cmp(b),
+            SortKey::Natural => natural_cmp(a, b).then_with(|| a.cmp(b)),
+            SortKey::Extension => {
+                let ext = |f: &str| Path::new(f).extension().map(OsStr::to_owned);
+                ext(a).cmp(&ext(b)).then_with(|| a.cmp(b))
+            }
+            SortKey::Mtime | SortKey::Size => ka
+                .partial_cmp(kb)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.cmp(b)),
+        });
+        entries.extend(keyed.into_iter().map(|(_, _, entry)| entry));
+
+        if descending {
+            entries.reverse();
+        }
+    }
+}
+
+// Compare A and B the way `string-version-lessp' does: runs of digits
+// are compared by their numeric value, everything else by character.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let digits = |it: &mut Peekable<Chars>| {
+                    let mut run = String::new();
+                    while let Some(c) = it.peek().copied().filter(char::is_ascii_digit) {
+                        run.push(c);
+                        it.next();
+                    }
+                    run
+                };
+                let (x_run, y_run) = (digits(&mut a), digits(&mut b));
+                let (x_num, y_num) = (x_run.trim_start_matches('0'), y_run.trim_start_matches('0'));
+                let ord = x_num
+                    .len()
+                    .cmp(&y_num.len())
+                    .then_with(|| x_num.cmp(y_num))
+                    .then_with(|| x_run.len().cmp(&y_run.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
 }
 
 enum FullPath {
@@ -174,8 +387,9 @@ impl DirData {
         match *self {
             DirData::Files { ref mut fnames } => {
                 fnames_from_os(fnames, &dr.dname, dr.match_re);
-                if let SortFNames::Yes = dr.sortmemaybe {
-                    fnames.sort();
+                dr.sortmemaybe.sort(fnames, &dr.dname);
+                if let Some(count) = dr.count {
+                    fnames.truncate(count);
                 }
             }
             DirData::FilesAttrs {
@@ -183,8 +397,9 @@ impl DirData {
                 ref mut fattrs,
             } => {
                 fnames_from_os(fnames, &dr.dname, dr.match_re);
-                if let SortFNames::Yes = dr.sortmemaybe {
-                    fnames.sort();
+                dr.sortmemaybe.sort(fnames, &dr.dname);
+                if let Some(count) = dr.count {
+                    fnames.truncate(count);
                 }
 
                 fattrs_from_os(fattrs, fnames, &dr.dname, dr.id_format);
@@ -228,13 +443,120 @@ fn fnames_from_os(fnames: &mut Vec<String>, dname: &str, match_re: Option<LispOb
     }
 }
 
+// Whether listings should leave out files excluded by ignore files.
+fn respect_ignore_files() -> bool {
+    unsafe { globals.Vdirectory_files_respect_ignore_files }.is_not_nil()
+}
+
+// Whether M ignores a file, whitelists it, or says nothing about it.
+fn decide(m: Match<&Glob>) -> Option<bool> {
+    if m.is_none() {
+        None
+    } else {
+        Some(m.is_ignore())
+    }
+}
+
+// The ignore files of one directory.
+struct DirIgnores {
+    ignore: Gitignore,    // .ignore
+    gitignore: Gitignore, // .gitignore
+    exclude: Gitignore,   // .git/info/exclude
+    has_git: bool,
+}
+
+// The rules of .ignore, .gitignore, .git/info/exclude and the global
+// git excludes file, as the ignore crate applies them: the innermost
+// directory with a matching rule decides, .ignore files take precedence
+// over git's, and git's only apply inside a repository, up to its top.
+// Each ignore file is read once however many directories a listing or
+// walk visits below it.
+struct IgnoreRules {
+    global: Gitignore,
+    dirs: HashMap<PathBuf, DirIgnores>,
+}
+
+impl IgnoreRules {
+    fn new() -> Self {
+        Self {
+            global: Gitignore::global().0,
+            dirs: HashMap::new(),
+        }
+    }
+
+    // The rules of the ignore file FILE, relative to ROOT.  A missing
+    // or broken file should not hide everything, so errors are dropped.
+    fn read(root: &Path, file: PathBuf) -> Gitignore {
+        let mut builder = GitignoreBuilder::new(root);
+        builder.add(file);
+        builder.build().unwrap_or_else(|_| Gitignore::empty())
+    }
+
+    fn load(&mut self, dir: &Path) {
+        if self.dirs.contains_key(dir) {
+            return;
+        }
+        let has_git = dir.join(".git").exists();
+        let ignores = DirIgnores {
+            ignore: Self::read(dir, dir.join(".ignore")),
+            gitignore: Self::read(dir, dir.join(".gitignore")),
+            exclude: if has_git {
+                Self::read(dir, dir.join(".git/info/exclude"))
+            } else {
+                Gitignore::empty()
+            },
+            has_git,
+        };
+        self.dirs.insert(dir.to_owned(), ignores);
+    }
+
+    // Whether the entry NAME of the directory DIR is excluded.
+    fn is_ignored(&mut self, dir: &Path, name: &OsStr, is_dir: bool) -> bool {
+        for ancestor in dir.ancestors() {
+            self.load(ancestor);
+        }
+        let path = dir.join(name);
+
+        let mut ignore = None;
+        let mut git = None;
+        let mut in_repo = false;
+        for ancestor in dir.ancestors() {
+            let ignores = &self.dirs[ancestor];
+            ignore = ignore.or_else(|| decide(ignores.ignore.matched(&path, is_dir)));
+            if !in_repo {
+                git = git
+                    .or_else(|| decide(ignores.gitignore.matched(&path, is_dir)))
+                    .or_else(|| decide(ignores.exclude.matched(&path, is_dir)));
+            }
+            in_repo = in_repo || ignores.has_git;
+        }
+        if !in_repo {
+            git = None;
+        }
+
+        ignore
+            .or(git)
+            .unwrap_or_else(|| in_repo && self.global.matched(&path, is_dir).is_ignore())
+    }
+}
+
+// The ignore rules for a listing or walk, if it should leave out files
+// excluded by ignore files.
+fn ignore_rules() -> Option<IgnoreRules> {
+    if respect_ignore_files() {
+        Some(IgnoreRules::new())
+    } else {
+        None
+    }
+}
+
 fn read_dir(dname: &str, fnames: &mut Vec<String>, match_re: Option<LispObject>) -> io::Result<()> {
     let dir_p = Path::new(dname);
-
     let re = match match_re {
-        Some(x) => Some(RegEx::new(x)),
+        Some(x) => Some(FileMatch::for_names(x)),
         None => None,
     };
+    let mut rules = ignore_rules();
 
     let dot = String::from(".");
     if match_re_maybe(dot.to_owned(), &re).is_some() {
@@ -247,6 +569,12 @@ fn read_dir(dname: &str, fnames: &mut Vec<String>, match_re: Option<LispObject>)
 
     for fname in fs::read_dir(dir_p)? {
         let fname = fname?;
+        if let Some(ref mut rules) = rules {
+            let is_dir = fname.file_type().map_or(false, |ft| ft.is_dir());
+            if rules.is_ignored(dir_p, &fname.file_name(), is_dir) {
+                continue;
+            }
+        }
         let f_enc = match fname.file_name().into_string() {
             Ok(file_name) => file_name,
             Err(err) => {
@@ -268,10 +596,10 @@ fn read_dir(dname: &str, fnames: &mut Vec<String>, match_re: Option<LispObject>)
     Ok(())
 }
 
-fn match_re_maybe(f: String, re: &Option<RegEx>) -> Option<String> {
+fn match_re_maybe(f: String, re: &Option<FileMatch>) -> Option<String> {
     match re {
         Some(re_value) => {
-            if re_value.is_match(f.as_str()) {
+            if re_value.is_match(f.as_str(), f.as_str()) {
                 Some(f)
             } else {
                 None
@@ -326,23 +654,95 @@ fn directory_files_core(dr: &DirReq, dd: &mut DirData) -> LispObject {
     dd.to_list(dr)
 }
 
+// Call HANDLER for OPERATION, `directory-files' or
+// `directory-files-and-attributes', with the arguments handlers have
+// always been given.  Globs, sort keys and COUNT are applied to the
+// result here; ID_FORMAT is Some for the attributes variant.
+fn handler_directory_files(
+    handler: LispObject,
+    operation: LispObject,
+    dnexp: LispStringRef,
+    full: LispObject,
+    match_re: LispObject,
+    nosort: LispObject,
+    id_format: Option<LispObject>,
+    count: Option<EmacsUint>,
+) -> LispObject {
+    let sort = SortFNames::from_nosort(nosort);
+    let handler_nosort = if sort.handler_sorts() { nosort } else { Qt };
+    // Handlers pass MATCH to `string-match', so they only get regexps.
+    let glob = match FileMatch::for_names(match_re) {
+        FileMatch::Regexp(_) => None,
+        glob => Some(glob),
+    };
+    let match_re = if glob.is_some() { Qnil } else { match_re };
+    let result = match id_format {
+        Some(id_format) => call!(
+            handler,
+            operation,
+            dnexp.into(),
+            full,
+            match_re,
+            handler_nosort,
+            id_format
+        ),
+        None => call!(
+            handler,
+            operation,
+            dnexp.into(),
+            full,
+            match_re,
+            handler_nosort
+        ),
+    };
+
+    if glob.is_none() && sort.handler_sorts() && count.is_none() {
+        return result;
+    }
+    let mut entries: Vec<LispObject> = result
+        .iter_cars(LispConsEndChecks::on, LispConsCircularChecks::on)
+        .collect();
+    if let Some(glob) = glob {
+        entries.retain(|&entry| {
+            let name = if id_format.is_some() {
+                car(entry)
+            } else {
+                entry
+            };
+            let name = name.to_stdstring();
+            let base = name.rsplit('/').next().unwrap_or(&name);
+            glob.is_match(base, base)
+        });
+    }
+    if !sort.handler_sorts() {
+        sort.sort_handler_entries(&mut entries, dnexp, id_format.is_some());
+    }
+    if let Some(count) = count {
+        entries.truncate(count as usize);
+    }
+    list(&entries)
+}
+
 pub fn directory_files_intro(
     directory: LispStringRef,
     full: LispObject,
     match_re: LispObject,
     nosort: LispObject,
+    count: Option<EmacsUint>,
 ) -> LispObject {
     let dnexp = expand_file_name(directory, None);
 
     let handler = find_file_name_handler(dnexp, Qdirectory_files);
     if handler.is_not_nil() {
-        return call!(
+        return handler_directory_files(
             handler,
             Qdirectory_files,
-            dnexp.into(),
+            dnexp,
             full,
             match_re,
-            nosort
+            nosort,
+            None,
+            count,
         );
     }
 
@@ -358,12 +758,9 @@ pub fn directory_files_intro(
         } else {
             Some(match_re)
         },
-        if nosort.is_nil() {
-            SortFNames::Yes
-        } else {
-            SortFNames::No
-        },
+        SortFNames::from_nosort(nosort),
         Qnil,
+        count.map(|c| c as usize),
     );
     let mut dd = DirData::Files { fnames: Vec::new() };
 
@@ -376,19 +773,21 @@ pub fn directory_files_and_attributes_intro(
     match_re: LispObject,
     nosort: LispObject,
     id_format: LispObject,
+    count: Option<EmacsUint>,
 ) -> LispObject {
     let dnexp = expand_file_name(directory, None);
 
     let handler = find_file_name_handler(dnexp, Qdirectory_files_and_attributes);
     if handler.is_not_nil() {
-        return call!(
+        return handler_directory_files(
             handler,
             Qdirectory_files_and_attributes,
-            dnexp.into(),
+            dnexp,
             full,
             match_re,
             nosort,
-            id_format
+            Some(id_format),
+            count,
         );
     }
 
@@ -404,12 +803,9 @@ pub fn directory_files_and_attributes_intro(
         } else {
             Some(match_re)
         },
-        if nosort.is_nil() {
-            SortFNames::Yes
-        } else {
-            SortFNames::No
-        },
+        SortFNames::from_nosort(nosort),
         id_format,
+        count.map(|c| c as usize),
     );
     let mut dd = DirData::FilesAttrs {
         fnames: Vec::new(),
@@ -441,12 +837,9 @@ pub extern "C" fn directory_files_internal(
         } else {
             Some(match_re)
         },
-        if nosort.is_nil() {
-            SortFNames::Yes
-        } else {
-            SortFNames::No
-        },
+        SortFNames::from_nosort(nosort),
         id_format,
+        None,
     );
 
     let mut dd = if attrs {
@@ -461,6 +854,372 @@ pub extern "C" fn directory_files_internal(
     directory_files_core(&dr, &mut dd)
 }
 
+// Recursive directory walk request input
+struct WalkReq {
+    match_re: Option<FileMatch>, // filter regexp or glob, see FileMatch
+    include_dirs: bool,
+    max_depth: Option<usize>, // None walks the whole tree
+    follow_symlinks: bool,
+    predicate: LispObject, // nil, or called with each directory before descending
+    id_format: Option<LispObject>, // Some if attributes are collected
+}
+
+// One entry of a directory being walked
+struct WalkEntry {
+    file_name: OsString, // base name as stored in the file system
+    name: String,        // decoded base name
+    is_dir: bool,        // also true for symlinks to directories
+    is_symlink: bool,
+}
+
+// Return the Lisp file name for PATH, decoded with the file name
+// coding system.
+fn path_to_lisp(path: &Path) -> LispObject {
+    unsafe { decode_file_name(bytes_to_unibyte(path.as_os_str().as_bytes())) }
+}
+
+// Read the entries of DIR, minus "." and "..", sorted by name.
+// Entries excluded by RULES, if any, are left out.
+fn walk_read_dir(dir: &Path, rules: &mut Option<IgnoreRules>) -> io::Result<Vec<WalkEntry>> {
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let ft = entry.file_type()?;
+        let file_name = entry.file_name();
+        if let Some(ref mut rules) = rules {
+            if rules.is_ignored(dir, &file_name, ft.is_dir()) {
+                continue;
+            }
+        }
+        // Names need not be valid UTF-8, so decode their bytes as
+        // `directory-files' does.
+        let name = path_to_lisp(Path::new(&file_name)).to_stdstring();
+
+        let is_symlink = ft.is_symlink();
+        // A dangling link is neither a directory nor an error.
+        let is_dir = if is_symlink {
+            fs::metadata(entry.path()).map_or(false, |md| md.is_dir())
+        } else {
+            ft.is_dir()
+        };
+
+        entries.push(WalkEntry {
+            file_name,
+            name,
+            is_dir,
+            is_symlink,
+        });
+    }
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+// Push the absolute file name of PATH (and its attributes, if
+// requested) onto the reversed RESULT list.
+fn walk_push(wr: &WalkReq, path: &Path, result: &mut LispObject) {
+    let fname = path_to_lisp(path);
+    let elt = match wr.id_format {
+        Some(id_format) => LispObject::cons(fname, file_attributes_core(fname, id_format)),
+        None => fname,
+    };
+    *result = LispObject::cons(elt, *result);
+}
+
+fn walk_dir(
+    wr: &WalkReq,
+    dir: &Path,
+    rel_dname: &str, // DIR relative to the top of the walk, decoded
+    depth: usize,
+    visited: &mut HashSet<(u64, u64)>,
+    rules: &mut Option<IgnoreRules>, // shared by the whole walk
+    result: &mut LispObject,
+) -> io::Result<()> {
+    let entries = walk_read_dir(dir, rules)?;
+    let mut files = Vec::new();
+
+    for entry in entries {
+        unsafe { maybe_quit() };
+        let full = dir.join(&entry.file_name);
+        let rel = if rel_dname.is_empty() {
+            entry.name.to_owned()
+        } else {
+            entry.name.to_full(rel_dname.to_owned())
+        };
+        let matches = match wr.match_re {
+            Some(ref re) => re.is_match(&entry.name, &rel),
+            None => true,
+        };
+
+        if !entry.is_dir {
+            if matches {
+                files.push(full);
+            }
+            continue;
+        }
+
+        let descend = wr.max_depth.map_or(true, |max| depth < max)
+            && (wr.follow_symlinks || !entry.is_symlink)
+            && (wr.predicate.is_nil() || call!(wr.predicate, path_to_lisp(&full)).is_not_nil());
+
+        if descend {
+            // Guard against cycles through symbolic links.
+            let is_new =
+                fs::metadata(&full).map_or(false, |md| visited.insert((md.dev(), md.ino())));
+            // Unreadable subdirectories are skipped rather than
+            // aborting the whole walk.
+            if is_new {
+                let _ = walk_dir(wr, &full, &rel, depth + 1, visited, rules, result);
+            }
+        }
+
+        if wr.include_dirs && matches {
+            walk_push(wr, &full, result);
+        }
+    }
+
+    for full in files {
+        walk_push(wr, &full, result);
+    }
+
+    Ok(())
+}
+
+pub fn directory_files_walk_intro(
+    directory: LispStringRef,
+    match_re: LispObject,
+    include_directories: LispObject,
+    max_depth: Option<EmacsUint>,
+    follow_symlinks: LispObject,
+    predicate: LispObject,
+    attrs: LispObject,
+) -> LispObject {
+    let dnexp = expand_file_name(directory, None);
+
+    let handler = find_file_name_handler(dnexp, Qdirectory_files_walk);
+    if handler.is_not_nil() {
+        return call!(
+            handler,
+            Qdirectory_files_walk,
+            dnexp.into(),
+            match_re,
+            include_directories,
+            max_depth.map_or(Qnil, LispObject::from_natnum),
+            follow_symlinks,
+            predicate,
+            attrs
+        );
+    }
+
+    let wr = WalkReq {
+        match_re: if match_re.is_nil() {
+            None
+        } else {
+            Some(FileMatch::new(match_re))
+        },
+        include_dirs: include_directories.is_not_nil(),
+        max_depth: max_depth.map(|d| d as usize),
+        follow_symlinks: follow_symlinks.is_not_nil(),
+        predicate,
+        id_format: if attrs.is_nil() {
+            None
+        } else if attrs.eq(Qt) {
+            Some(Qnil)
+        } else {
+            Some(attrs)
+        },
+    };
+
+    let dir = PathBuf::from(OsStr::from_bytes(encode_file_name(dnexp).as_slice()));
+    let mut visited = HashSet::new();
+    if let Ok(md) = fs::metadata(&dir) {
+        visited.insert((md.dev(), md.ino()));
+    }
+
+    let mut rules = ignore_rules();
+    let mut result = Qnil;
+    if let Err(err) = walk_dir(&wr, &dir, "", 0, &mut visited, &mut rules, &mut result) {
+        xsignal!(
+            Qfile_missing,
+            format!("Opening directory: {}", err).to_bstring(),
+            dnexp
+        );
+    }
+
+    nreverse(result)
+}
+
+// Totals gathered by disk_usage.
+#[derive(Default)]
+struct DiskUsage {
+    apparent: u64,
+    allocated: u64,
+    files: u64,
+}
+
+// Add the usage of the file PATH, with metadata MD, and of everything
+// below it to USAGE, the way du does: symbolic links are not followed,
+// a file with several hard links is only counted the first time one of
+// its (dev, inode) pairs is seen, and with ROOT_DEV directories on
+// another file system are left out entirely.
+fn disk_usage(
+    path: &Path,
+    md: &fs::Metadata,
+    root_dev: Option<u64>,
+    seen: &mut HashSet<(u64, u64)>,
+    usage: &mut DiskUsage,
+) {
+    if md.nlink() > 1 && !seen.insert((md.dev(), md.ino())) {
+        return;
+    }
+    usage.apparent += md.size();
+    // st_blocks is always in units of 512 bytes.
+    usage.allocated += md.blocks() * 512;
+    usage.files += 1;
+
+    if !md.is_dir() {
+        return;
+    }
+    // Unreadable directories still count themselves, but nothing below.
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(Result::ok) {
+        unsafe { maybe_quit() };
+        let child = entry.path();
+        let child_md = match fs::symlink_metadata(&child) {
+            Ok(md) => md,
+            Err(_) => continue,
+        };
+        if child_md.is_dir() && root_dev.map_or(false, |dev| dev != child_md.dev()) {
+            continue;
+        }
+        disk_usage(&child, &child_md, root_dev, seen, usage);
+    }
+}
+
+pub fn directory_disk_usage_intro(
+    directory: LispStringRef,
+    one_file_system: LispObject,
+) -> LispObject {
+    let dnexp = expand_file_name(directory, None);
+
+    let handler = find_file_name_handler(dnexp, Qdirectory_disk_usage);
+    if handler.is_not_nil() {
+        return call!(
+            handler,
+            Qdirectory_disk_usage,
+            dnexp.into(),
+            one_file_system
+        );
+    }
+
+    let dname = LispObject::from(dnexp).to_stdstring();
+    let path = Path::new(&dname);
+    let md = fs::metadata(path).and_then(|md| {
+        if md.is_dir() {
+            Ok(md)
+        } else {
+            Err(io::Error::from_raw_os_error(libc::ENOTDIR))
+        }
+    });
+    let md = match md {
+        Ok(md) => md,
+        Err(err) => xsignal!(
+            Qfile_missing,
+            format!("Opening directory: {}", err).to_bstring(),
+            dname.as_str()
+        ),
+    };
+
+    let root_dev = if one_file_system.is_nil() {
+        None
+    } else {
+        Some(md.dev())
+    };
+    let mut usage = DiskUsage::default();
+    disk_usage(path, &md, root_dev, &mut HashSet::new(), &mut usage);
+
+    list(&[
+        LispObject::from_natnum(usage.apparent),
+        LispObject::from_natnum(usage.allocated),
+        LispObject::from_natnum(usage.files),
+    ])
+}
+
+// A MATCH argument: an Emacs regexp matched against file names, or a
+// shell glob given as (glob . PATTERN).  A glob containing a slash is
+// matched against the file name relative to the directory being
+// walked, so that "src/*.rs" and "**/*.el" work; any other glob is
+// matched against the file name alone.  Flat listings only see bare
+// names, so they reject globs with a slash, see `FileMatch::for_names'.
+enum FileMatch {
+    Regexp(LispObject),
+    Glob {
+        matcher: GlobMatcher,
+        whole_path: bool,
+    },
+}
+
+impl FileMatch {
+    fn new(match_arg: LispObject) -> Self {
+        let cons = match match_arg.as_cons() {
+            Some(cons) if cons.car().eq(Qglob) => cons,
+            _ => return FileMatch::Regexp(match_arg),
+        };
+
+        let pattern: LispStringRef = cons.cdr().into();
+        let pattern = LispObject::from(pattern).to_stdstring();
+        let glob = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build();
+        match glob {
+            Ok(glob) => FileMatch::Glob {
+                matcher: glob.compile_matcher(),
+                whole_path: pattern.contains('/'),
+            },
+            Err(err) => error!("Invalid glob pattern: {}", err),
+        }
+    }
+
+    // Like `new', for the non-recursive listings, which match bare file
+    // names: a glob with a slash could never match one there, so signal
+    // rather than silently return nothing.
+    fn for_names(match_arg: LispObject) -> Self {
+        let file_match = Self::new(match_arg);
+        if let FileMatch::Glob {
+            whole_path: true, ..
+        } = file_match
+        {
+            // Free the matcher before signaling skips its destructor.
+            drop(file_match);
+            error!(
+                "Glob pattern contains a directory separator: {}; use `directory-files-walk'",
+                match_arg.as_cons().unwrap().cdr()
+            );
+        }
+        file_match
+    }
+
+    // Whether the file NAME, at REL_PATH relative to the listed
+    // directory, matches.
+    fn is_match(&self, name: &str, rel_path: &str) -> bool {
+        match *self {
+            // Compile the regexp anew each time, as Lisp code run in
+            // between (such as a walk's PREDICATE) may have evicted it
+            // from the pattern cache.
+            FileMatch::Regexp(re) => RegEx::new(re).is_match(name),
+            FileMatch::Glob {
+                ref matcher,
+                whole_path,
+            } => matcher.is_match(if whole_path { rel_path } else { name }),
+        }
+    }
+}
+
 struct RegEx {
     recomp: *mut re_pattern_buffer,
 }
@@ -511,7 +1270,7 @@ struct FileAttrs {
     ctime_s: i64,
     ctime_ns: c_long,
     size: u64,
-    //file_mode: String,
+    mode: u32,
     ino: u64,
     dev: u64,
 }
@@ -540,7 +1299,7 @@ impl FileAttrs {
             ctime_s: 0,
             ctime_ns: 0,
             size: 0,
-            //file_mode,
+            mode: 0,
             ino: 0,
             dev: 0,
         }
@@ -610,6 +1369,7 @@ impl FileAttrs {
         self.ctime_ns = md.ctime_nsec();
 
         self.size = md.size();
+        self.mode = md.mode();
 
         self.ino = md.ino();
         self.dev = md.dev();
@@ -685,8 +1445,7 @@ impl FileAttrs {
         let size = LispObject::from_natnum(self.size);
 
         //  8. File modes, as a string of ten letters or dashes as in ls -l.
-        //  Punt back to C until the filemode_string code is ported to Rust.
-        let modes = unsafe { filemode_string(self.fpath.as_str().into()) };
+        let modes = file_mode_string(self.mode).to_bstring();
 
         //  9. An unspecified value, present only for backward compatibility.
         let undef = Qt;
@@ -743,6 +1502,297 @@ fn file_attributes_core(fpath: LispObject, id_format: LispObject) -> LispObject
     }
 }
 
+// The ten-character mode string of `ls -l', as made by gnulib's
+// filemodestring: the file type followed by the permission bits.
+fn file_mode_string(mode: u32) -> String {
+    let ftype = match mode & libc::S_IFMT as u32 {
+        m if m == libc::S_IFREG as u32 => '-',
+        m if m == libc::S_IFDIR as u32 => 'd',
+        m if m == libc::S_IFLNK as u32 => 'l',
+        m if m == libc::S_IFBLK as u32 => 'b',
+        m if m == libc::S_IFCHR as u32 => 'c',
+        m if m == libc::S_IFIFO as u32 => 'p',
+        m if m == libc::S_IFSOCK as u32 => 's',
+        _ => '?',
+    };
+    let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+    // The execute position also shows the set-ID and sticky bits.
+    let exec = |x: u32, special: u32, set: char, unset: char| match (mode & x, mode & special) {
+        (0, 0) => '-',
+        (_, 0) => 'x',
+        (0, _) => unset,
+        (_, _) => set,
+    };
+
+    [
+        ftype,
+        bit(0o400, 'r'),
+        bit(0o200, 'w'),
+        exec(0o100, 0o4000, 's', 'S'),
+        bit(0o040, 'r'),
+        bit(0o020, 'w'),
+        exec(0o010, 0o2000, 's', 'S'),
+        bit(0o004, 'r'),
+        bit(0o002, 'w'),
+        exec(0o001, 0o1000, 't', 'T'),
+    ]
+    .iter()
+    .collect()
+}
+
+// A line of /proc/self/mountinfo, reduced to what file-attributes-extended
+// reports.
+#[derive(Debug, PartialEq)]
+struct MountInfo {
+    dev: (u64, u64), // major, minor
+    mount_point: String,
+    fs_type: String,
+    source: String,
+}
+
+impl MountInfo {
+    // Parse a mountinfo line, which looks like
+    // 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+    // where the optional fields before "-" vary in number.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(' ');
+        let dev = fields.nth(2)?;
+        let mount_point = fields.nth(1)?;
+        let mut fields = fields.skip_while(|&f| f != "-").skip(1);
+        let fs_type = fields.next()?;
+        let source = fields.next()?;
+
+        let (major, minor) = dev.split_at(dev.find(':')?);
+        let dev = (major.parse().ok()?, minor[1..].parse().ok()?);
+
+        Some(Self {
+            dev,
+            mount_point: unescape_mountinfo(mount_point),
+            fs_type: fs_type.to_string(),
+            source: unescape_mountinfo(source),
+        })
+    }
+
+    // Find the mount containing PATH, on the device DEV.  Bind mounts
+    // share a device, so the longest mount point that is a prefix of
+    // PATH wins; if no mount has a matching device (as may happen on
+    // btrfs), fall back to the longest prefix.
+    fn find(mountinfo: &str, dev: (u64, u64), path: &Path) -> Option<Self> {
+        let mounts: Vec<Self> = mountinfo
+            .lines()
+            .filter_map(Self::parse)
+            .filter(|m| path.starts_with(&m.mount_point))
+            .collect();
+        let on_dev = mounts.iter().any(|m| m.dev == dev);
+
+        mounts
+            .into_iter()
+            .filter(|m| !on_dev || m.dev == dev)
+            .max_by_key(|m| m.mount_point.len())
+    }
+}
+
+// The mount points listed in /proc/self/mountinfo, or none where that
+// file does not exist.
+pub(crate) fn mount_points() -> Vec<PathBuf> {
+    fs::read_to_string("/proc/self/mountinfo").map_or(Vec::new(), |mountinfo| {
+        mountinfo
+            .lines()
+            .filter_map(MountInfo::parse)
+            .map(|m| PathBuf::from(m.mount_point))
+            .collect()
+    })
+}
+
+// Undo the octal escapes (\040 for space and so on) of mountinfo fields.
+fn unescape_mountinfo(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|d| d.iter().all(|b| (b'0'..=b'7').contains(b)));
+        match (bytes[i], octal) {
+            (b'\\', Some(d)) => {
+                out.push(
+                    d.iter()
+                        .fold(0u8, |acc, b| acc.wrapping_mul(8).wrapping_add(b - b'0')),
+                );
+                i += 4;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// Split a device number into its major and minor parts, using the
+// encoding of glibc's gnu_dev_major and gnu_dev_minor.
+const fn dev_major_minor(dev: u64) -> (u64, u64) {
+    (
+        ((dev >> 8) & 0xfff) | ((dev >> 32) & 0xffff_f000),
+        (dev & 0xff) | ((dev >> 12) & 0xffff_ff00),
+    )
+}
+
+// Read the value of the extended attribute NAME of PATH, without
+// following symbolic links.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_xattr(path: &CStr, name: &CStr) -> Option<Vec<u8>> {
+    let size = unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), null_mut(), 0) };
+    if size < 0 {
+        return None;
+    }
+
+    let mut value = vec![0u8; size as usize];
+    let size = unsafe {
+        libc::lgetxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_mut_ptr() as *mut libc::c_void,
+            value.len(),
+        )
+    };
+    if size < 0 {
+        return None;
+    }
+    value.truncate(size as usize);
+    Some(value)
+}
+
+// The extended attributes of PATH as (NAME, VALUE) pairs, without
+// following symbolic links.  Attributes that vanish while being read
+// are left out.
+#[cfg(target_os = "linux")]
+fn list_xattrs(path: &CStr) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let size = unsafe { libc::llistxattr(path.as_ptr(), null_mut(), 0) };
+    if size <= 0 {
+        return Vec::new();
+    }
+
+    let mut names = vec![0u8; size as usize];
+    let size = unsafe {
+        libc::llistxattr(
+            path.as_ptr(),
+            names.as_mut_ptr() as *mut c_char,
+            names.len(),
+        )
+    };
+    if size < 0 {
+        return Vec::new();
+    }
+    names.truncate(size as usize);
+
+    names
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let value = get_xattr(path, &CString::new(name).ok()?)?;
+            Some((name.to_vec(), value))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn list_xattrs(_path: &CStr) -> Vec<(Vec<u8>, Vec<u8>)> {
+    Vec::new()
+}
+
+// Extended attribute names under which Linux file systems keep ACLs.
+pub(crate) const ACL_XATTRS: [&[u8]; 3] = [
+    b"system.posix_acl_access",
+    b"system.posix_acl_default",
+    b"system.nfs4_acl",
+];
+
+fn bytes_to_unibyte(bytes: &[u8]) -> LispObject {
+    unsafe { make_unibyte_string(bytes.as_ptr() as *const c_char, bytes.len() as isize) }
+}
+
+pub fn file_attributes_extended_intro(filename: LispStringRef) -> LispObject {
+    let fnexp = expand_file_name(filename, None);
+    let handler = find_file_name_handler(fnexp, Qfile_attributes_extended);
+    if handler.is_not_nil() {
+        return call!(handler, Qfile_attributes_extended, fnexp.into());
+    }
+
+    let fpath = LispObject::from(fnexp).to_stdstring();
+    let md = match fs::symlink_metadata(&fpath) {
+        Ok(md) => md,
+        Err(_) => return Qnil,
+    };
+
+    // std fetches the birth time with statx where the kernel and file
+    // system support it, and fails otherwise.
+    let birth_time = md
+        .created()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(Qnil, |d| {
+            make_lisp_time(c_timespec {
+                tv_sec: d.as_secs() as i64,
+                tv_nsec: c_long::from(d.subsec_nanos()),
+            })
+        });
+
+    let c_path = CString::new(fpath.as_str()).unwrap();
+    let xattrs = list_xattrs(&c_path);
+    let has_acl = xattrs
+        .iter()
+        .any(|(name, _)| ACL_XATTRS.contains(&name.as_slice()));
+    let xattrs = list(
+        &xattrs
+            .iter()
+            .map(|(name, value)| LispObject::cons(bytes_to_unibyte(name), bytes_to_unibyte(value)))
+            .collect::<Vec<_>>(),
+    );
+
+    // Find the mount through the canonical name of the parent, so that
+    // a symbolic link is placed where it lives rather than where it points.
+    let path = Path::new(&fpath);
+    let canonical = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent).map(|p| p.join(name)),
+        _ => fs::canonicalize(path),
+    };
+    let mount = match (canonical, fs::read_to_string("/proc/self/mountinfo")) {
+        (Ok(canonical), Ok(mountinfo)) => {
+            MountInfo::find(&mountinfo, dev_major_minor(md.dev()), &canonical)
+        }
+        _ => None,
+    };
+    let (fs_type, mount_point, mount_source) = match mount {
+        Some(m) => (
+            m.fs_type.to_bstring(),
+            m.mount_point.to_bstring(),
+            m.source.to_bstring(),
+        ),
+        None => (Qnil, Qnil, Qnil),
+    };
+
+    list(&[
+        QCbirth_time,
+        birth_time,
+        QCmodes,
+        file_mode_string(md.mode()).to_bstring(),
+        QCxattrs,
+        xattrs,
+        QCacl,
+        has_acl.into(),
+        QCfilesystem_type,
+        fs_type,
+        QCmount_point,
+        mount_point,
+        QCmount_source,
+        mount_source,
+    ])
+}
+
 fn get_user_real_login_name() -> LispObject {
     unsafe { globals.Vuser_real_login_name }
 }
@@ -781,3 +1831,72 @@ pub fn get_groups() -> LispObject {
     unsafe { endgrent() };
     list(&group_names)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_mode_string() {
+        assert_eq!(file_mode_string(0o100644), "-rw-r--r--");
+        assert_eq!(file_mode_string(0o040755), "drwxr-xr-x");
+        assert_eq!(file_mode_string(0o120777), "lrwxrwxrwx");
+        assert_eq!(file_mode_string(0o104755), "-rwsr-xr-x");
+        assert_eq!(file_mode_string(0o102644), "-rw-r-Sr--");
+        assert_eq!(file_mode_string(0o041777), "drwxrwxrwt");
+        assert_eq!(file_mode_string(0o041770), "drwxrwx--T");
+    }
+
+    const MOUNTINFO: &str = "\
+22 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 rw,errors=remount-ro
+25 22 0:22 / /tmp rw,nosuid shared:5 - tmpfs tmpfs rw
+30 22 8:3 / /home/user/My\\040Files rw master:1 shared:7 - xfs /dev/sda3 rw
+31 22 8:2 /srv /mnt/srv rw - ext4 /dev/sda2 rw";
+
+    #[test]
+    fn test_mount_info_parse() {
+        assert_eq!(
+            MountInfo::parse(MOUNTINFO.lines().nth(2).unwrap()),
+            Some(MountInfo {
+                dev: (8, 3),
+                mount_point: "/home/user/My Files".to_string(),
+                fs_type: "xfs".to_string(),
+                source: "/dev/sda3".to_string(),
+            })
+        );
+        assert_eq!(MountInfo::parse("garbage"), None);
+    }
+
+    #[test]
+    fn test_mount_info_find() {
+        let find =
+            |dev, path| MountInfo::find(MOUNTINFO, dev, Path::new(path)).map(|m| m.mount_point);
+
+        assert_eq!(find((8, 2), "/etc/passwd"), Some("/".to_string()));
+        assert_eq!(find((0, 22), "/tmp/x"), Some("/tmp".to_string()));
+        assert_eq!(
+            find((8, 3), "/home/user/My Files/a"),
+            Some("/home/user/My Files".to_string())
+        );
+        assert_eq!(find((8, 2), "/mnt/srv/data"), Some("/mnt/srv".to_string()));
+        // No mount on the device: fall back to the longest prefix.
+        assert_eq!(find((0, 99), "/tmp/x"), Some("/tmp".to_string()));
+    }
+
+    #[test]
+    fn test_dev_major_minor() {
+        assert_eq!(dev_major_minor(0x0802), (8, 2));
+        assert_eq!(dev_major_minor(0x0000_1000_0000_1234), (0x1012, 0x34));
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file2"), Ordering::Greater);
+        assert_eq!(natural_cmp("v1.9.0", "v1.10.0"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "b"), Ordering::Less);
+        assert_eq!(natural_cmp("a1", "a"), Ordering::Greater);
+        assert_eq!(natural_cmp("x007", "x7"), Ordering::Greater);
+        assert_eq!(natural_cmp("x7", "x7"), Ordering::Equal);
+    }
+}
@@ -1,12 +1,28 @@
 //! Functions to deal with files
 use errno::{set_errno, Errno};
 
+use std::fs;
+use std::io::{self, Read};
+use std::mem;
 use std::path;
+#[cfg(unix)]
+use std::{
+    ffi::{CString, OsStr},
+    os::unix::ffi::OsStrExt,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    os::unix::io::{AsRawFd, FromRawFd},
+    path::{Path, PathBuf},
+};
+
+#[cfg(unix)]
+use remacs_lib::make_temporary_file;
 
 use remacs_macros::lisp_fn;
 
 use crate::{
+    buffers::validate_region_rust,
     coding::encode_file_name,
+    editfns::buffer_substring_no_properties,
     errno::errno,
     lisp::LispObject,
     lists::LispCons,
@@ -16,13 +32,28 @@ use crate::{
         check_executable, check_existing, empty_unibyte_string, expand_and_dir_to_file,
         file_directory_p, file_name_absolute_p, file_name_case_insensitive_p, report_file_errno,
     },
-    remacs_sys::{Fexpand_file_name, Ffind_file_name_handler, Fmake_temp_file_internal},
     remacs_sys::{
-        Qfile_directory_p, Qfile_executable_p, Qfile_exists_p, Qfile_name_case_insensitive_p, Qnil,
+        Fexpand_file_name, Ffind_file_name_handler, Fmake_temp_file_internal, Fstring_as_unibyte,
+    },
+    remacs_sys::{
+        Qbinary, Qfile_content_type, Qfile_directory_p, Qfile_executable_p, Qfile_exists_p,
+        Qfile_name_case_insensitive_p, Qnil, Qtext,
     },
     threads::ThreadState,
 };
 
+#[cfg(target_os = "linux")]
+use crate::dired_unix::{get_xattr, ACL_XATTRS};
+#[cfg(unix)]
+use crate::{
+    eval::{record_unwind_protect, unbind_to},
+    remacs_sys::{
+        build_string, decode_file_name, write_region, Fdefault_file_modes, Fwrite_region,
+    },
+    remacs_sys::{Qnomessage, Qwrite_region},
+    threads::c_specpdl_index,
+};
+
 /// Signal a file-access failure that set errno.  STRING describes the
 /// failure, NAME the file involved.  When invoking this function, take
 /// care to not use arguments such as build_string ("foo") that involve
@@ -190,4 +221,482 @@ pub fn make_temp_name(prefix: LispStringRef) -> LispObject {
     }
 }
 
+// Return the encoded form of the file name NAME as a path.
+#[cfg(unix)]
+fn encoded_path(name: LispStringRef) -> path::PathBuf {
+    PathBuf::from(OsStr::from_bytes(encode_file_name(name).as_slice()))
+}
+
+#[cfg(not(unix))]
+fn encoded_path(name: LispStringRef) -> path::PathBuf {
+    let encoded = encode_file_name(name);
+    path::PathBuf::from(String::from_utf8_lossy(encoded.as_slice()).into_owned())
+}
+
+// Unwind function for `write-region-atomically': close the descriptor
+// in the cdr of CELL, unless a file has taken it over, and delete the
+// temporary file named by the car, unless it has been renamed into place.
+#[cfg(unix)]
+unsafe extern "C" fn delete_temp_file_unwind(cell: LispObject) {
+    if let Some(fd) = cell.as_cons().and_then(|c| c.cdr().as_fixnum()) {
+        libc::close(fd as libc::c_int);
+    }
+    if let Some(name) = cell.as_cons().and_then(|c| c.car().as_string()) {
+        let _ = fs::remove_file(encoded_path(name));
+    }
+}
+
+// Signal a file error for the failed operation OPERATION, a
+// NUL-terminated description, on FILE.
+pub(crate) fn report_io_error(operation: &[u8], file: LispStringRef, err: &io::Error) -> ! {
+    unsafe {
+        report_file_errno(
+            operation.as_ptr() as *const libc::c_char,
+            file.into(),
+            err.raw_os_error().unwrap_or(0),
+        )
+    };
+    unreachable!()
+}
+
+// Give the new file TMP the modes, owner and group and access control
+// list of TARGET, or the default modes if TARGET does not exist yet.
+// Only the group is changed if we may not give the file away.
+#[cfg(unix)]
+fn copy_file_permissions(tmp: &fs::File, target: &Path) -> io::Result<()> {
+    let fd = tmp.as_raw_fd();
+    let md = match fs::metadata(target) {
+        Ok(md) => md,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            let mode = unsafe { Fdefault_file_modes() }.as_natnum_or_error() as libc::mode_t;
+            return tmp.set_permissions(fs::Permissions::from_mode(u32::from(mode & 0o666)));
+        }
+        Err(err) => return Err(err),
+    };
+
+    unsafe {
+        if libc::fchown(fd, md.uid(), md.gid()) != 0 {
+            libc::fchown(fd, !0, md.gid());
+        }
+    }
+    tmp.set_permissions(fs::Permissions::from_mode(md.mode() & 0o7777))?;
+
+    #[cfg(target_os = "linux")]
+    {
+        let target = CString::new(target.as_os_str().as_bytes()).unwrap();
+        for name in ACL_XATTRS.iter() {
+            let name = CString::new(*name).unwrap();
+            if let Some(value) = get_xattr(&target, &name) {
+                let ret = unsafe {
+                    libc::fsetxattr(
+                        fd,
+                        name.as_ptr(),
+                        value.as_ptr() as *const libc::c_void,
+                        value.len(),
+                        0,
+                    )
+                };
+                // Not every file system supports access control lists.
+                if ret != 0 {
+                    let err = io::Error::last_os_error();
+                    match err.raw_os_error() {
+                        Some(libc::ENOTSUP) | Some(libc::EOPNOTSUPP) => {}
+                        _ => return Err(err),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write current region into FILENAME so that it is replaced atomically.
+/// START, END and FILENAME are as in `write-region', which is used to
+/// write the text, so that the usual coding system and annotation rules
+/// apply.
+///
+/// The text is first written to a new file in the same directory as
+/// FILENAME.  That file is flushed to disk, given the modes, owner and
+/// group and access control list of FILENAME if it exists, and then
+/// renamed over FILENAME.  Other programs therefore see either the old or
+/// the new contents, never a partial file, even if Emacs or the system
+/// crashes or the disk fills up in the middle.  If FILENAME is a symbolic
+/// link, the file it points to is replaced.  The owner is only copied if
+/// the user is allowed to give files away.
+///
+/// If SYNC-DIRECTORY is non-nil, flush the directory as well once the
+/// file has been renamed, so that the new contents survive a power loss
+/// right after this function returns.
+///
+/// If FILENAME has a file name handler, this simply calls `write-region'.
+#[cfg(unix)]
+#[lisp_fn(min = "3")]
+pub fn write_region_atomically(
+    start: LispObject,
+    end: LispObject,
+    filename: LispStringRef,
+    sync_directory: LispObject,
+) {
+    let absname = expand_file_name(filename, None);
+    if find_file_name_handler(absname, Qwrite_region).is_not_nil() {
+        unsafe { Fwrite_region(start, end, absname.into(), Qnil, Qnil, Qnil, Qnil) };
+        return;
+    }
+
+    // Replace the target of a symbolic link rather than the link.
+    let target = encoded_path(absname);
+    let target = fs::canonicalize(&target).unwrap_or(target);
+    let (dir, name) = match (target.parent(), target.file_name()) {
+        (Some(dir), Some(name)) => (dir.to_owned(), name.to_owned()),
+        _ => error!("Not a file name: {}", target.display()),
+    };
+
+    let template = dir.join(format!(".{}.tmpXXXXXX", name.to_string_lossy()));
+    let template = match template.to_str() {
+        Some(template) => template.to_owned(),
+        None => error!("Cannot make a temporary name for {}", target.display()),
+    };
+    let (fd, tmpname) = match make_temporary_file(template, 0) {
+        Ok(created) => created,
+        Err(errno) => {
+            let err = io::Error::from_raw_os_error(errno);
+            report_io_error(b"Creating temporary file\0", absname, &err)
+        }
+    };
+    let tmppath = PathBuf::from(&tmpname);
+    let tmplisp: LispStringRef =
+        unsafe { decode_file_name(build_string(CString::new(tmpname).unwrap().as_ptr())) }.into();
+
+    // Write through the descriptor the file was created with, as its
+    // name could be replaced in between.  Close the descriptor and
+    // remove the temporary file if writing fails part way.
+    let cell = LispObject::cons(tmplisp, LispObject::from(fd));
+    let count = c_specpdl_index();
+    unsafe {
+        record_unwind_protect(Some(delete_temp_file_unwind), cell);
+        write_region(
+            start,
+            end,
+            tmplisp.into(),
+            Qnil,
+            Qnomessage,
+            absname.into(),
+            Qnil,
+            fd,
+        );
+    }
+
+    // The unwind handler keeps owning the descriptor until the last
+    // call that can signal, so that a signal still closes it.
+    let tmp = mem::ManuallyDrop::new(unsafe { fs::File::from_raw_fd(fd) });
+    if let Err(err) = copy_file_permissions(&tmp, &target) {
+        report_io_error(b"Copying file permissions\0", tmplisp, &err);
+    }
+    if let Err(err) = tmp.sync_all() {
+        report_io_error(b"Synchronizing file\0", tmplisp, &err);
+    }
+    cell.as_cons().unwrap().set_cdr(Qnil);
+    drop(mem::ManuallyDrop::into_inner(tmp));
+
+    if let Err(err) = fs::rename(&tmppath, &target) {
+        report_io_error(b"Renaming\0", absname, &err);
+    }
+    cell.as_cons().unwrap().set_car(Qnil);
+    unbind_to(count, Qnil);
+
+    if sync_directory.is_not_nil() {
+        if let Err(err) = fs::File::open(&dir).and_then(|d| d.sync_all()) {
+            report_io_error(b"Synchronizing directory\0", absname, &err);
+        }
+    }
+}
+
+def_lisp_sym!(Qnomessage, "nomessage");
+
+/// The number of bytes that `file-content-type' looks at.
+const CONTENT_SNIFF_LEN: usize = 8192;
+
+/// Signatures recognized by `sniff_content_type`: the offset and bytes
+/// of the magic number, the MIME type and whether the content is binary.
+/// Longer signatures that share a prefix with shorter ones come first.
+const CONTENT_MAGIC: &[(usize, &[u8], &str, bool)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png", true),
+    (0, b"\xff\xd8\xff", "image/jpeg", true),
+    (0, b"GIF87a", "image/gif", true),
+    (0, b"GIF89a", "image/gif", true),
+    (8, b"WEBP", "image/webp", true),
+    (0, b"II*\0", "image/tiff", true),
+    (0, b"MM\0*", "image/tiff", true),
+    (0, b"%PDF-", "application/pdf", true),
+    (0, b"%!PS", "application/postscript", false),
+    (0, b"PK\x03\x04", "application/zip", true),
+    (0, b"PK\x05\x06", "application/zip", true),
+    (0, b"\x1f\x8b", "application/gzip", true),
+    (0, b"BZh", "application/x-bzip2", true),
+    (0, b"\xfd7zXZ\0", "application/x-xz", true),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd", true),
+    (
+        0,
+        b"7z\xbc\xaf\x27\x1c",
+        "application/x-7z-compressed",
+        true,
+    ),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar", true),
+    (257, b"ustar", "application/x-tar", true),
+    (0, b"\0asm", "application/wasm", true),
+    (0, b"\xef\xbb\xbf", "text/plain; charset=utf-8", false),
+    (0, b"\xff\xfe\0\0", "text/plain; charset=utf-32le", false),
+    (0, b"\0\0\xfe\xff", "text/plain; charset=utf-32be", false),
+    (0, b"\xff\xfe", "text/plain; charset=utf-16le", false),
+    (0, b"\xfe\xff", "text/plain; charset=utf-16be", false),
+];
+
+/// Return the little-endian 16-bit value at OFFSET in DATA.
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+/// Return the little-endian 32-bit value at OFFSET in DATA.
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Whether DATA starts with a BMP file header.  "BM" alone is common in
+/// text, so the size of the DIB header that follows must be one of the
+/// sizes of the known versions.
+fn is_bmp(data: &[u8]) -> bool {
+    data.starts_with(b"BM")
+        && le_u32(data, 14).map_or(false, |size| {
+            [12, 16, 40, 52, 56, 64, 108, 124].contains(&size)
+        })
+}
+
+/// Whether DATA is a PE executable: an MS-DOS stub starting with "MZ"
+/// whose e_lfanew field, at offset 60, points to a PE signature.
+fn is_pe(data: &[u8]) -> bool {
+    data.starts_with(b"MZ")
+        && le_u32(data, 60).map_or(false, |offset| {
+            let offset = offset as usize;
+            data.get(offset..offset + 4) == Some(b"PE\0\0")
+        })
+}
+
+/// Whether DATA is an icon file: a header with a non-zero image count,
+/// followed by directory entries with sane fields that point past the
+/// directory.  Only the entries within DATA are checked.
+fn is_ico(data: &[u8]) -> bool {
+    let count = match le_u16(data, 4) {
+        Some(count) if data.starts_with(b"\0\0\x01\0") && count > 0 => usize::from(count),
+        _ => return false,
+    };
+    let images_start = 6 + 16 * count as u64;
+    let entry_ok = |entry: &[u8]| {
+        entry[3] == 0
+            && le_u16(entry, 4).map_or(false, |planes| planes <= 1)
+            && le_u16(entry, 6).map_or(false, |bits| [0, 1, 4, 8, 16, 24, 32].contains(&bits))
+            && le_u32(entry, 8).map_or(false, |size| size > 0)
+            && le_u32(entry, 12).map_or(false, |offset| u64::from(offset) >= images_start)
+    };
+    let entries: Vec<&[u8]> = data[6..].chunks_exact(16).take(count).collect();
+    !entries.is_empty() && entries.into_iter().all(entry_ok)
+}
+
+/// Guess the MIME type of DATA, the start of a file, from its magic
+/// number.  Return the type and whether the content is binary.  Data
+/// without a known magic number is text unless it contains NUL bytes or
+/// many control characters.
+fn sniff_content_type(data: &[u8]) -> (&'static str, bool) {
+    // RIFF is a container; only WebP is of interest here.
+    let riff = data.starts_with(b"RIFF");
+    let magic = CONTENT_MAGIC.iter().find(|&&(offset, magic, mime, _)| {
+        data.get(offset..offset + magic.len()) == Some(magic) && (mime != "image/webp" || riff)
+    });
+    if let Some(&(_, _, mime, binary)) = magic {
+        return (mime, binary);
+    }
+    // These magic numbers are short enough to start ordinary text.
+    if is_bmp(data) {
+        return ("image/bmp", true);
+    }
+    if is_ico(data) {
+        return ("image/vnd.microsoft.icon", true);
+    }
+    if is_pe(data) {
+        return ("application/vnd.microsoft.portable-executable", true);
+    }
+
+    if data.starts_with(b"\x7fELF") {
+        // The object file type, e_type, is at offset 16 in the byte
+        // order given at offset 5.
+        let e_type = match (data.get(5), data.get(16..18)) {
+            (Some(1), Some(t)) => u16::from_le_bytes([t[0], t[1]]),
+            (Some(2), Some(t)) => u16::from_be_bytes([t[0], t[1]]),
+            _ => 0,
+        };
+        let mime = match e_type {
+            1 => "application/x-object",
+            3 => "application/x-sharedlib",
+            4 => "application/x-coredump",
+            _ => "application/x-executable",
+        };
+        return (mime, true);
+    }
+
+    let controls = data
+        .iter()
+        .filter(|&&b| b < 0x20 && !b"\t\n\r\x0b\x0c\x1b\x08".contains(&b))
+        .count();
+    if data.contains(&0) || controls * 10 > data.len() {
+        ("application/octet-stream", true)
+    } else if data.starts_with(b"<?xml") {
+        ("application/xml", false)
+    } else {
+        ("text/plain", false)
+    }
+}
+
+/// Guess the type of the contents of a file or of part of a buffer.
+/// If FILE-OR-START is a string, it names the file to examine.
+/// Otherwise FILE-OR-START and END are positions delimiting the region of
+/// the current buffer to examine; if FILE-OR-START is nil, the whole
+/// accessible portion of the buffer is used.
+///
+/// The type is guessed from the magic number at the start of the data,
+/// which is recognized for common image formats, archives and compressed
+/// files, PDF documents, ELF and PE executables, and text with a Unicode
+/// byte order mark.  Data without a magic number is considered binary if
+/// it contains NUL bytes or many control characters, and text otherwise.
+/// Only the first 8192 bytes are examined.
+///
+/// The value is a cons (MIME-TYPE . CLASS), where MIME-TYPE is a string
+/// such as "image/png" or "text/plain; charset=utf-16le", and CLASS is
+/// either `text' or `binary'.  An empty file or region is text.
+#[lisp_fn(min = "1")]
+pub fn file_content_type(file_or_start: LispObject, end: LispObject) -> LispObject {
+    let mut data = Vec::with_capacity(CONTENT_SNIFF_LEN);
+
+    if let Some(file) = file_or_start.as_string() {
+        let absname = expand_file_name(file, None);
+        let handler = find_file_name_handler(absname, Qfile_content_type);
+        if handler.is_not_nil() {
+            return call!(handler, Qfile_content_type, absname.into());
+        }
+
+        let read = fs::File::open(encoded_path(absname))
+            .and_then(|f| f.take(CONTENT_SNIFF_LEN as u64).read_to_end(&mut data));
+        if let Err(err) = read {
+            report_io_error(b"Reading file\0", absname, &err);
+        }
+    } else {
+        let (start, end) = if file_or_start.is_nil() {
+            let buf = ThreadState::current_buffer_unchecked();
+            (buf.begv, buf.zv)
+        } else {
+            validate_region_rust(file_or_start, end)
+        };
+        // A character takes at least one byte.
+        let end = end.min(start + CONTENT_SNIFF_LEN as isize);
+        let text = buffer_substring_no_properties(start.into(), end.into());
+        // This turns raw bytes back into themselves.
+        let text: LispStringRef = unsafe { Fstring_as_unibyte(text) }.into();
+        let bytes = text.as_slice();
+        data.extend_from_slice(&bytes[..bytes.len().min(CONTENT_SNIFF_LEN)]);
+    }
+
+    let (mime, binary) = sniff_content_type(&data);
+    LispObject::cons(mime, if binary { Qbinary } else { Qtext })
+}
+
+def_lisp_sym!(Qfile_content_type, "file-content-type");
+def_lisp_sym!(Qbinary, "binary");
+
+#[cfg(test)]
+mod tests {
+    use super::sniff_content_type;
+
+    #[test]
+    fn test_sniff_content_type() {
+        assert_eq!(sniff_content_type(b""), ("text/plain", false));
+        assert_eq!(sniff_content_type(b"GIF89a\x01\0"), ("image/gif", true));
+        assert_eq!(
+            sniff_content_type(b"RIFF\0\0\0\0WEBPVP8 "),
+            ("image/webp", true)
+        );
+        assert_eq!(
+            sniff_content_type(b"RIFF\0\0\0\0WAVEfmt "),
+            ("application/octet-stream", true)
+        );
+        assert_eq!(
+            sniff_content_type(b"\xff\xfe\0\0a\0\0\0"),
+            ("text/plain; charset=utf-32le", false)
+        );
+        assert_eq!(
+            sniff_content_type(b"\xff\xfea\0"),
+            ("text/plain; charset=utf-16le", false)
+        );
+
+        let mut elf = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x03\0".to_vec();
+        assert_eq!(sniff_content_type(&elf), ("application/x-sharedlib", true));
+        elf[5] = 2;
+        elf[16..18].copy_from_slice(&[0, 1]);
+        assert_eq!(sniff_content_type(&elf), ("application/x-object", true));
+
+        let mut tar = vec![b'a'; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff_content_type(&tar), ("application/x-tar", true));
+
+        assert_eq!(
+            sniff_content_type(b"<?xml version=\"1.0\"?>"),
+            ("application/xml", false)
+        );
+        assert_eq!(
+            sniff_content_type(b"caf\xc3\xa9\n\tfoo\x1b[0m\n"),
+            ("text/plain", false)
+        );
+        assert_eq!(
+            sniff_content_type(b"\x01\x02\x03abc"),
+            ("application/octet-stream", true)
+        );
+    }
+
+    #[test]
+    fn test_sniff_short_magic() {
+        let mut bmp = b"BM\x46\0\0\0\0\0\0\0\x36\0\0\0\x28\0\0\0".to_vec();
+        bmp.resize(64, 0);
+        assert_eq!(sniff_content_type(&bmp), ("image/bmp", true));
+        assert_eq!(
+            sniff_content_type(b"BMW drivers club: monthly meeting\n"),
+            ("text/plain", false)
+        );
+
+        let mut pe = vec![0; 0x84];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[60] = 0x80;
+        pe[0x80..].copy_from_slice(b"PE\0\0");
+        assert_eq!(
+            sniff_content_type(&pe),
+            ("application/vnd.microsoft.portable-executable", true)
+        );
+        pe[0x81] = b'X';
+        assert_eq!(sniff_content_type(&pe), ("application/octet-stream", true));
+        assert_eq!(
+            sniff_content_type(b"MZ is the postal code of the Mizoram region.\n"),
+            ("text/plain", false)
+        );
+
+        let mut ico = b"\0\0\x01\0\x01\0\x10\x10\0\0\x01\0\x20\0".to_vec();
+        ico.extend_from_slice(b"\x68\x04\0\0\x16\0\0\0");
+        ico.resize(64, 0);
+        assert_eq!(sniff_content_type(&ico), ("image/vnd.microsoft.icon", true));
+        ico[4] = 0;
+        assert_eq!(sniff_content_type(&ico), ("application/octet-stream", true));
+        ico[4] = 1;
+        ico[18] = 2;
+        assert_eq!(sniff_content_type(&ico), ("application/octet-stream", true));
+    }
+}
+
 include!(concat!(env!("OUT_DIR"), "/fileio_exports.rs"));
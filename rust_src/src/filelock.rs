@@ -2,18 +2,29 @@
 
 use remacs_macros::lisp_fn;
 
-use std::fs::{remove_file, File};
+use std::collections::HashMap;
+use std::fs::{remove_file, DirBuilder, File, OpenOptions};
 use std::io::prelude::Read;
 use std::io::Error;
 use std::io::ErrorKind::{InvalidData, InvalidInput, NotFound, PermissionDenied};
 use std::io::Result;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
 use systemstat::Platform;
 
 #[cfg(unix)]
-use libc::{ELOOP, O_NOFOLLOW, O_RDONLY};
+use libc::{c_char, ELOOP, O_NOFOLLOW, O_RDONLY};
 #[cfg(unix)]
-use std::{ffi::CString, ffi::OsStr, os::unix::ffi::OsStrExt, os::unix::io::FromRawFd};
+use std::{
+    ffi::CStr,
+    ffi::CString,
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    os::unix::io::{AsRawFd, FromRawFd},
+};
 
 use crate::{
     coding::encode_file_name,
@@ -21,7 +32,7 @@ use crate::{
     fileio::expand_file_name,
     lisp::LispObject,
     multibyte::LispStringRef,
-    remacs_sys::{emacs_open, lock_file, maybe_quit, unlock_file},
+    remacs_sys::{emacs_open, globals, lock_file, make_unibyte_string, maybe_quit, unlock_file},
     remacs_sys::{Qnil, Qstringp, Qt},
     threads::ThreadState,
 };
@@ -273,10 +284,34 @@ fn to_path_buf(path: LispStringRef) -> PathBuf {
 #[cfg(windows)]
 fn to_path_buf(path: LispStringRef) -> PathBuf {} // TODO (may have to return a Result)
 
-/// Generates a path to a lock file corresponding to the given
-/// file name in `path`.
-fn make_lock_name(path: LispStringRef) -> PathBuf {
-    let mut path = to_path_buf(path);
+/// Returns the directory named by `lock-file-directory`, or [`None`] if
+/// lock files should be created next to the files they lock.
+fn lock_file_directory() -> Option<PathBuf> {
+    let dir = unsafe { globals.Vlock_file_directory };
+    dir.as_string()
+        .map(|dir| to_path_buf(expand_file_name(dir, None)))
+}
+
+/// Generates the name of the lock entry for `path` inside `dir`.  The
+/// entry is keyed by a SHA-256 digest of the (encoded) file name, so
+/// every session computes the same name for the same file.
+#[cfg(unix)]
+fn hashed_lock_name(dir: &Path, path: &Path) -> PathBuf {
+    let digest = Sha256::digest(path.as_os_str().as_bytes());
+    let name: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+
+    dir.join(name)
+}
+
+#[cfg(windows)]
+fn hashed_lock_name(dir: &Path, path: &Path) -> PathBuf {} // TODO
+
+/// Generates a path to a lock file for the encoded, expanded file
+/// name `path`.
+fn lock_name_for(mut path: PathBuf) -> PathBuf {
+    if let Some(dir) = lock_file_directory() {
+        return hashed_lock_name(&dir, &path);
+    }
 
     let mut lock_file_name = std::ffi::OsString::from(".#");
     lock_file_name.push(path.file_name().unwrap_or_else(|| std::ffi::OsStr::new("")));
@@ -286,6 +321,162 @@ fn make_lock_name(path: LispStringRef) -> PathBuf {
     path
 }
 
+/// Generates a path to a lock file corresponding to the given
+/// file name in `path`.
+fn make_lock_name(path: LispStringRef) -> PathBuf {
+    lock_name_for(to_path_buf(path))
+}
+
+/// Returns the name of the lock file for the encoded, expanded file
+/// name `filename` as a unibyte string.  If lock files are kept in
+/// `lock-file-directory`, that directory is created when missing.
+/// Called by `lock_file` and `unlock_file` in filelock.c.
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn make_lock_file_name(filename: LispObject) -> LispObject {
+    let filename: LispStringRef = filename.into();
+    let path = PathBuf::from(OsStr::from_bytes(filename.as_slice()));
+
+    if let Some(dir) = lock_file_directory() {
+        // A failure here surfaces when the lock file itself cannot be
+        // created, which `lock_file` already reports.
+        let _ = DirBuilder::new().recursive(true).mode(0o700).create(&dir);
+    }
+
+    lock_path_to_lisp(&lock_name_for(path))
+}
+
+#[cfg(unix)]
+fn lock_path_to_lisp(lock_name: &Path) -> LispObject {
+    let bytes = lock_name.as_os_str().as_bytes();
+    unsafe { make_unibyte_string(bytes.as_ptr() as *const c_char, bytes.len() as isize) }
+}
+
+lazy_static! {
+    /// Lock files taken by this process, keyed by the encoded, expanded
+    /// name of the file they lock.  Unlocking removes the lock file
+    /// recorded here, wherever `lock-file-directory` points by then.
+    static ref HELD_LOCK_NAMES: Mutex<HashMap<PathBuf, PathBuf>> = Mutex::new(HashMap::new());
+}
+
+/// Records that the lock file `lfname` was taken for the encoded,
+/// expanded file name `filename`.
+/// Called by `lock_file` in filelock.c once it holds the lock.
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn record_lock_file_name(filename: LispObject, lfname: *const c_char) {
+    let filename: LispStringRef = filename.into();
+    let path = PathBuf::from(OsStr::from_bytes(filename.as_slice()));
+    let lock_name = PathBuf::from(OsStr::from_bytes(
+        unsafe { CStr::from_ptr(lfname) }.to_bytes(),
+    ));
+
+    HELD_LOCK_NAMES.lock().unwrap().insert(path, lock_name);
+}
+
+/// Returns the name of the lock file to remove when unlocking the
+/// encoded, expanded file name `filename`, and forgets it: the one
+/// recorded by [`record_lock_file_name`], or else the one
+/// [`make_lock_file_name`] computes now.
+/// Called by `unlock_file` in filelock.c.
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn take_lock_file_name(filename: LispObject) -> LispObject {
+    let name: LispStringRef = filename.into();
+    let path = PathBuf::from(OsStr::from_bytes(name.as_slice()));
+
+    match HELD_LOCK_NAMES.lock().unwrap().remove(&path) {
+        Some(lock_name) => lock_path_to_lisp(&lock_name),
+        None => make_lock_file_name(filename),
+    }
+}
+
+lazy_static! {
+    /// Advisory locks held by this process, keyed by lock file name.
+    /// Dropping the [`File`] closes the descriptor and releases the lock.
+    static ref ADVISORY_LOCKS: Mutex<HashMap<PathBuf, File>> = Mutex::new(HashMap::new());
+}
+
+/// Returns the path of the file carrying the `fcntl` advisory lock
+/// that accompanies the lock file `lock_name`.  Lock files themselves
+/// are symbolic links, which cannot be opened for locking.
+fn advisory_lock_name(lock_name: &Path) -> PathBuf {
+    let mut name = lock_name.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// Takes a write lock covering all of `file` without waiting.
+#[cfg(unix)]
+fn set_advisory_lock(file: &File) -> Result<()> {
+    let mut flock: libc::flock = unsafe { std::mem::zeroed() };
+    flock.l_type = libc::F_WRLCK as libc::c_short;
+    flock.l_whence = libc::SEEK_SET as libc::c_short;
+
+    match unsafe { libc::fcntl(file.as_raw_fd(), libc::F_SETLK, &flock) } {
+        -1 => Err(Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns [`true`] if `fcntl` advisory locks should accompany lock
+/// files.  This only applies to lock files in `lock-file-directory`.
+fn use_advisory_locks() -> bool {
+    unsafe { globals.Vlock_file_use_advisory_locks }.is_not_nil() && lock_file_directory().is_some()
+}
+
+/// Takes an `fcntl` advisory lock alongside the lock file `lfname`,
+/// if `lock-file-use-advisory-locks` asks for one.  Failing to get the
+/// advisory lock does not affect the lock file itself.
+/// Called by `lock_file_1` in filelock.c once the lock file exists.
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn acquire_advisory_lock(lfname: *const c_char) {
+    if !use_advisory_locks() {
+        return;
+    }
+
+    let lock_name = PathBuf::from(OsStr::from_bytes(
+        unsafe { CStr::from_ptr(lfname) }.to_bytes(),
+    ));
+    let mut locks = ADVISORY_LOCKS.lock().unwrap();
+    if locks.contains_key(&lock_name) {
+        return;
+    }
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .mode(0o600)
+        .custom_flags(O_NOFOLLOW)
+        .open(advisory_lock_name(&lock_name));
+
+    if let Ok(file) = file {
+        if set_advisory_lock(&file).is_ok() {
+            locks.insert(lock_name, file);
+        }
+    }
+}
+
+/// Releases the advisory lock taken by [`acquire_advisory_lock`] for
+/// the lock file `lfname`, if any.
+/// Called by `unlock_file` in filelock.c before the lock file is removed.
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn release_advisory_lock(lfname: *const c_char) {
+    let lock_name = PathBuf::from(OsStr::from_bytes(
+        unsafe { CStr::from_ptr(lfname) }.to_bytes(),
+    ));
+
+    // Remove the lock's file while still holding the lock, so nobody
+    // can lock the doomed file in between.
+    if let Some(file) = ADVISORY_LOCKS.lock().unwrap().remove(&lock_name) {
+        let _ = remove_file(advisory_lock_name(&lock_name));
+        drop(file);
+    }
+}
+
 /// Lock FILE, if current buffer is modified.
 /// FILE defaults to current buffer's visited file,
 /// or else nothing is done if current buffer isn't visiting a file.
@@ -334,6 +525,31 @@ pub fn file_locked_p(filename: LispStringRef) -> LispObject {
     }
 }
 
+#[allow(unused_doc_comments)]
+#[no_mangle]
+pub extern "C" fn rust_syms_of_filelock() {
+    /// If non-nil, a directory in which to keep lock files.
+    /// When nil, the lock file for FILE is a symbolic link named `.#FILE'
+    /// in the same directory as FILE.  Otherwise, lock files are created in
+    /// this directory, named after a SHA-256 hash of the file's truename, so
+    /// that they do not show up in project directories.  Every Emacs session
+    /// that should see the locks of the others must use the same directory;
+    /// a per-user cache directory such as "~/.cache/emacs/locks/" is a good
+    /// choice.  The directory is created on demand with mode 0700.
+    defvar_lisp!(Vlock_file_directory, "lock-file-directory", Qnil);
+
+    /// Non-nil means also take `fcntl' advisory locks on locked files.
+    /// This only has an effect when `lock-file-directory' is non-nil.  Each
+    /// lock file is then accompanied by a file with the same name plus
+    /// ".lock", which is write-locked for as long as the lock is held, so
+    /// that tools other than Emacs can detect it.
+    defvar_lisp!(
+        Vlock_file_use_advisory_locks,
+        "lock-file-use-advisory-locks",
+        Qnil
+    );
+}
+
 include!(concat!(env!("OUT_DIR"), "/filelock_exports.rs"));
 
 #[cfg(test)]
@@ -360,6 +576,32 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_hashed_lock_name() {
+        let dir = Path::new("/tmp/locks");
+        let name = hashed_lock_name(dir, Path::new("/home/user/project/src/main.rs"));
+
+        assert_eq!(name.parent(), Some(dir));
+        assert_eq!(name.file_name().unwrap().len(), 64);
+        assert_eq!(
+            name,
+            hashed_lock_name(dir, Path::new("/home/user/project/src/main.rs"))
+        );
+        assert_ne!(
+            name,
+            hashed_lock_name(dir, Path::new("/home/user/project/src/lib.rs"))
+        );
+    }
+
+    #[test]
+    fn test_advisory_lock_name() {
+        assert_eq!(
+            advisory_lock_name(Path::new("/tmp/locks/abc")),
+            PathBuf::from("/tmp/locks/abc.lock")
+        );
+    }
+
     fn example() -> LockInfo {
         LockInfo {
             user: "some-user.name".to_string(),
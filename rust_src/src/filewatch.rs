@@ -0,0 +1,316 @@
+//! Native file notification backend.
+//!
+//! Each watch owns a debounced `notify` watcher.  Events for the same
+//! file that arrive within the coalescing delay are merged into one, and
+//! a rename is reported as a single `renamed' event carrying both names
+//! instead of a `moved-from'/`moved-to' pair.  A thread per watch
+//! forwards the events to a queue and wakes up the command loop through
+//! a pipe, whose read end is polled like any other input descriptor.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::mpsc::channel;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use libc::{c_char, c_int, c_void, O_CLOEXEC, O_NONBLOCK};
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use remacs_macros::lisp_fn;
+
+use crate::{
+    coding::encode_file_name,
+    fileio::expand_file_name,
+    floatfns::extract_float,
+    fns::provide,
+    lisp::LispObject,
+    lists::{assq, delq, list, memq, nth},
+    multibyte::LispStringRef,
+    remacs_sys::{
+        add_read_fd, decode_file_name, event_kind, globals, input_event, kbd_buffer_store_event,
+        make_unibyte_string, staticpro, EmacsInt,
+    },
+    remacs_sys::{
+        Qattribute_change, Qattribute_changed, Qchange, Qchanged, Qcreated, Qdeleted,
+        Qfile_notify_error, Qfilewatch, Qnil, Qrecursive, Qrenamed, Qt,
+    },
+};
+
+/// The registered watches, as a list of `(DESCRIPTOR FILE FLAGS CALLBACK)'.
+/// The watchers themselves live in `WATCHERS`, under the same descriptor.
+static mut watch_list: LispObject = Qnil;
+
+lazy_static! {
+    static ref WATCHERS: Mutex<HashMap<EmacsInt, RecommendedWatcher>> = Mutex::new(HashMap::new());
+    static ref PENDING: Mutex<Vec<(EmacsInt, DebouncedEvent)>> = Mutex::new(Vec::new());
+    /// The read and write ends of the wakeup pipe, created on first use.
+    static ref WAKEUP: Mutex<Option<(c_int, c_int)>> = Mutex::new(None);
+    static ref NEXT_DESCRIPTOR: Mutex<EmacsInt> = Mutex::new(0);
+}
+
+/// Return the write end of the wakeup pipe, creating the pipe and
+/// registering its read end with the command loop if necessary.
+fn wakeup_fd() -> c_int {
+    let mut wakeup = WAKEUP.lock().unwrap();
+    if let Some((_, write_fd)) = *wakeup {
+        return write_fd;
+    }
+
+    let mut fds = [0 as c_int; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), O_CLOEXEC | O_NONBLOCK) } != 0 {
+        let err = std::io::Error::last_os_error().to_string();
+        drop(wakeup);
+        xsignal!(
+            Qfile_notify_error,
+            LispObject::from("Could not create wakeup pipe"),
+            LispObject::from(err.as_str())
+        );
+    }
+    unsafe { add_read_fd(fds[0], Some(filewatch_callback), ptr::null_mut()) };
+    *wakeup = Some((fds[0], fds[1]));
+    fds[1]
+}
+
+/// Forward the events of one watcher to `PENDING`, until the watcher is
+/// dropped and its channel disconnects.
+fn spawn_forwarder(descriptor: EmacsInt, rx: std::sync::mpsc::Receiver<DebouncedEvent>) {
+    let write_fd = wakeup_fd();
+    thread::spawn(move || {
+        for event in rx {
+            match event {
+                // These are sent ahead of the debounced event.
+                DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => continue,
+                _ => PENDING.lock().unwrap().push((descriptor, event)),
+            }
+            // A full pipe already guarantees a wakeup.
+            unsafe { libc::write(write_fd, b"\0".as_ptr() as *const c_void, 1) };
+        }
+    });
+}
+
+fn path_to_lisp(path: &Path) -> LispObject {
+    let bytes = path.as_os_str().as_bytes();
+    unsafe {
+        decode_file_name(make_unibyte_string(
+            bytes.as_ptr() as *const c_char,
+            bytes.len() as isize,
+        ))
+    }
+}
+
+/// Translate EVENT into `(DESCRIPTOR ACTION FILE [FILE1])', or nil if
+/// FLAGS do not ask for it.
+fn event_to_lisp(descriptor: EmacsInt, flags: LispObject, event: &DebouncedEvent) -> LispObject {
+    let change = memq(Qchange, flags).is_not_nil();
+    let attribute_change = memq(Qattribute_change, flags).is_not_nil();
+    let desc = LispObject::from(descriptor);
+
+    match event {
+        DebouncedEvent::Create(path) if change => list(&[desc, Qcreated, path_to_lisp(path)]),
+        DebouncedEvent::Write(path) if change => list(&[desc, Qchanged, path_to_lisp(path)]),
+        DebouncedEvent::Remove(path) if change => list(&[desc, Qdeleted, path_to_lisp(path)]),
+        DebouncedEvent::Rename(from, to) if change => {
+            list(&[desc, Qrenamed, path_to_lisp(from), path_to_lisp(to)])
+        }
+        DebouncedEvent::Chmod(path) if attribute_change => {
+            list(&[desc, Qattribute_changed, path_to_lisp(path)])
+        }
+        _ => Qnil,
+    }
+}
+
+/// Called by the command loop when the wakeup pipe is readable.  Store
+/// every pending event as a `file-notify' input event.
+#[no_mangle]
+pub unsafe extern "C" fn filewatch_callback(fd: c_int, _data: *mut c_void) {
+    let mut buf = [0u8; 256];
+    while libc::read(fd, buf.as_mut_ptr() as *mut c_void, buf.len()) > 0 {}
+
+    let pending = mem::replace(&mut *PENDING.lock().unwrap(), Vec::new());
+    for (descriptor, event) in pending {
+        // The watch may have been removed after the event was queued.
+        let watch = assq(LispObject::from(descriptor), watch_list);
+        if watch.is_nil() {
+            continue;
+        }
+        let arg = event_to_lisp(descriptor, nth(2, watch), &event);
+        if arg.is_nil() {
+            continue;
+        }
+
+        let mut ie: input_event = mem::zeroed();
+        ie.set_kind(event_kind::FILE_NOTIFY_EVENT);
+        ie.frame_or_window = Qnil;
+        ie.device = Qt;
+        ie.arg = list(&[arg, nth(3, watch)]);
+        kbd_buffer_store_event(&mut ie);
+    }
+}
+
+/// Add a watch for filesystem events pertaining to FILE.
+///
+/// This arranges for filesystem events pertaining to FILE to be reported
+/// to Emacs.  Use `filewatch-rm-watch' to cancel the watch.
+///
+/// Returned value is a descriptor for the added watch.  If the file cannot be
+/// watched for some reason, this function signals a `file-notify-error' error.
+///
+/// FLAGS is a list of conditions to set what will be watched for.  It can
+/// include the following symbols:
+///
+///   `change'           -- watch for file changes
+///   `attribute-change' -- watch for file attributes changes, like
+///                         permissions or modification time
+///   `recursive'        -- if FILE is a directory, also watch all of its
+///                         subdirectories, including those created later
+///
+/// Events for the same file that happen within DELAY seconds of each
+/// other are coalesced into one; if DELAY is nil, the value of
+/// `filewatch-coalesce-delay' is used.  A file that is renamed is
+/// reported once, as a `renamed' event with both names.
+///
+/// When any event happens, Emacs will call the CALLBACK function passing
+/// it a single argument EVENT, which is of the form
+///
+///   (DESCRIPTOR ACTION FILE [FILE1])
+///
+/// DESCRIPTOR is the same object as the one returned by this function.
+/// ACTION is the description of the event.  It could be any one of the
+/// following:
+///
+///   `created'           -- FILE was created
+///   `deleted'           -- FILE was deleted
+///   `changed'           -- FILE's contents has changed
+///   `renamed'           -- FILE has been renamed to FILE1
+///   `attribute-changed' -- a FILE attribute was changed
+///
+/// FILE and FILE1 are absolute file names.
+#[lisp_fn(min = "3")]
+pub fn filewatch_add_watch(
+    file: LispStringRef,
+    flags: LispObject,
+    callback: LispObject,
+    delay: LispObject,
+) -> LispObject {
+    let file = expand_file_name(file, None);
+    let encoded = encode_file_name(file);
+    let path = PathBuf::from(OsStr::from_bytes(encoded.as_slice()));
+
+    let delay = if delay.is_nil() {
+        unsafe { globals.Vfilewatch_coalesce_delay }
+    } else {
+        delay
+    };
+    let delay = extract_float(delay);
+    // `Duration::from_secs_f64' panics on values it cannot represent.
+    if !delay.is_finite() || delay < 0.0 || delay >= u64::MAX as f64 {
+        error!("Invalid coalescing delay: {}", delay);
+    }
+
+    let mode = if memq(Qrecursive, flags).is_not_nil() && path.is_dir() {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+
+    let (tx, rx) = channel();
+    let watcher = notify::watcher(tx, Duration::from_secs_f64(delay)).and_then(|mut w| {
+        w.watch(&path, mode)?;
+        Ok(w)
+    });
+    let watcher = match watcher {
+        Ok(w) => w,
+        Err(e) => xsignal!(
+            Qfile_notify_error,
+            LispObject::from("Could not add watch for file"),
+            LispObject::from(e.to_string().as_str()),
+            file.into()
+        ),
+    };
+
+    let descriptor = {
+        let mut next = NEXT_DESCRIPTOR.lock().unwrap();
+        *next += 1;
+        *next
+    };
+    WATCHERS.lock().unwrap().insert(descriptor, watcher);
+    spawn_forwarder(descriptor, rx);
+
+    let desc = LispObject::from(descriptor);
+    unsafe {
+        watch_list = LispObject::cons(list(&[desc, file.into(), flags, callback]), watch_list);
+    }
+    desc
+}
+
+/// Remove an existing WATCH-DESCRIPTOR.
+///
+/// WATCH-DESCRIPTOR should be an object returned by `filewatch-add-watch'.
+#[lisp_fn]
+pub fn filewatch_rm_watch(watch_descriptor: LispObject) -> bool {
+    let watch = unsafe { assq(watch_descriptor, watch_list) };
+    if watch.is_nil() {
+        xsignal!(
+            Qfile_notify_error,
+            LispObject::from("Not a watch descriptor"),
+            watch_descriptor
+        );
+    }
+    unsafe { watch_list = delq(watch, watch_list) };
+
+    // Dropping the watcher stops it and disconnects its forwarder.
+    if let Some(descriptor) = watch_descriptor.as_fixnum() {
+        WATCHERS.lock().unwrap().remove(&descriptor);
+    }
+    true
+}
+
+/// Check a watch specified by its WATCH-DESCRIPTOR.
+///
+/// WATCH-DESCRIPTOR should be an object returned by `filewatch-add-watch'.
+///
+/// A watch can become invalid if the file or directory it watches is
+/// deleted or if the watcher thread exits abnormally for any other
+/// reason.  Removing the watch by calling `filewatch-rm-watch' also
+/// makes it invalid.
+#[lisp_fn]
+pub fn filewatch_valid_p(watch_descriptor: LispObject) -> bool {
+    let watch = unsafe { assq(watch_descriptor, watch_list) };
+    if watch.is_nil() {
+        return false;
+    }
+    let encoded = encode_file_name(nth(1, watch).into());
+    Path::new(OsStr::from_bytes(encoded.as_slice())).exists()
+}
+
+#[no_mangle]
+pub extern "C" fn rust_syms_of_filewatch() {
+    unsafe { staticpro(&mut watch_list as *mut LispObject) };
+
+    def_lisp_sym!(Qfilewatch, "filewatch");
+    def_lisp_sym!(Qrecursive, "recursive");
+    def_lisp_sym!(Qchange, "change");
+    def_lisp_sym!(Qattribute_change, "attribute-change");
+    def_lisp_sym!(Qcreated, "created");
+    def_lisp_sym!(Qchanged, "changed");
+    def_lisp_sym!(Qdeleted, "deleted");
+    def_lisp_sym!(Qrenamed, "renamed");
+    def_lisp_sym!(Qattribute_changed, "attribute-changed");
+
+    /// Default number of seconds over which `filewatch-add-watch' coalesces
+    /// events for the same file.
+    /// Raising it reduces the number of events delivered while many files
+    /// change at once, such as during a version control checkout, at the
+    /// cost of reporting each change later.
+    #[rustfmt::skip]
+    defvar_lisp!(Vfilewatch_coalesce_delay, "filewatch-coalesce-delay", LispObject::from(0.1));
+
+    provide(Qfilewatch.into(), Qnil);
+}
+
+include!(concat!(env!("OUT_DIR"), "/filewatch_exports.rs"));
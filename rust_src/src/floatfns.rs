@@ -2,10 +2,16 @@
 #![allow(clippy::float_cmp)]
 
 use std::mem;
+use std::ptr;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
 
 use remacs_macros::lisp_fn;
 
 use crate::{
+    bignum::{check_bignum_bits, float_to_bigint},
     hashtable::LispHashTableRef,
     libm,
     lisp::defsubr,
@@ -102,8 +108,12 @@ impl LispObject {
 
     pub fn any_to_float_or_error(self) -> EmacsDouble {
         self.as_float().unwrap_or_else(|| {
-            self.as_fixnum()
-                .unwrap_or_else(|| wrong_type!(Qnumberp, self)) as EmacsDouble
+            if let Some(b) = self.as_bignum() {
+                b.to_float()
+            } else {
+                self.as_fixnum()
+                    .unwrap_or_else(|| wrong_type!(Qnumberp, self)) as EmacsDouble
+            }
         })
     }
 }
@@ -131,6 +141,199 @@ pub extern "C" fn extract_float(f: LispObject) -> EmacsDouble {
     f.any_to_float_or_error()
 }
 
+/// Return the shortest string that reads back as exactly F.
+///
+/// The digits are the fewest that identify F among all floats, and they
+/// are laid out the way `printf' lays out `%g' with just enough
+/// precision, but never fewer than `DBL_DIG' digits: exponential
+/// notation is used for exponents less than -4 or at least that
+/// precision.  A decimal point is added if needed to make the string
+/// read as a float.
+pub fn float_to_shortest_string(f: EmacsDouble) -> String {
+    const DBL_DIG: i32 = 15;
+
+    let sign = if libm::signbit(f) { "-" } else { "" };
+    if f.is_infinite() {
+        return format!("{}1.0e+INF", sign);
+    } else if f.is_nan() {
+        return format!("{}0.0e+NaN", sign);
+    }
+
+    // The `LowerExp' formatting of a float gives the shortest digits
+    // that round-trip, as D.DDDeX.
+    let sci = format!("{:e}", f.abs());
+    let (mantissa, exponent) = sci.split_at(sci.find('e').unwrap());
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let ndigits = digits.len() as i32;
+
+    let mut result = String::from(sign);
+    if exponent < -4 || exponent >= DBL_DIG.max(ndigits) {
+        result.push_str(&digits[..1]);
+        if ndigits > 1 {
+            result.push('.');
+            result.push_str(&digits[1..]);
+        }
+        let exp_sign = if exponent < 0 { '-' } else { '+' };
+        result.push_str(&format!("e{}{:02}", exp_sign, exponent.abs()));
+    } else if exponent < 0 {
+        result.push_str("0.");
+        for _ in 0..-exponent - 1 {
+            result.push('0');
+        }
+        result.push_str(&digits);
+    } else {
+        let int_len = (exponent + 1) as usize;
+        if digits.len() > int_len {
+            result.push_str(&digits[..int_len]);
+            result.push('.');
+            result.push_str(&digits[int_len..]);
+        } else {
+            result.push_str(&digits);
+            for _ in digits.len()..int_len {
+                result.push('0');
+            }
+            result.push_str(".0");
+        }
+    }
+    result
+}
+
+/// Write the shortest representation of DATA that reads back as the
+/// same float to BUF, which must have room for FLOAT_TO_STRING_BUFSIZE
+/// bytes, and return its length.  `float_to_string' uses this when
+/// `float-output-format' is nil.
+#[no_mangle]
+pub unsafe extern "C" fn float_to_shortest(
+    buf: *mut libc::c_char,
+    data: EmacsDouble,
+) -> libc::c_int {
+    let s = float_to_shortest_string(data);
+    ptr::copy_nonoverlapping(s.as_ptr(), buf as *mut u8, s.len());
+    *buf.add(s.len()) = 0;
+    s.len() as libc::c_int
+}
+
+/// Parse the hexadecimal float at the start of S, such as `0x1.8p3',
+/// with an optional sign, as C does.  The binary exponent is required.
+/// Return the value and the number of bytes it takes up, or `None' if S
+/// does not start with a hexadecimal float.
+///
+/// The value is rounded to the nearest float, so a float printed in
+/// hexadecimal with all its digits reads back exactly.
+pub fn parse_hex_float(s: &[u8]) -> Option<(EmacsDouble, usize)> {
+    let mut i = 0;
+    let negative = match s.first() {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    if !(s.get(i) == Some(&b'0') && (s.get(i + 1) == Some(&b'x') || s.get(i + 1) == Some(&b'X'))) {
+        return None;
+    }
+    i += 2;
+
+    // Accumulate the significant digits in MANTISSA, which keeps at
+    // least 60 bits; STICKY records whether any dropped digit was
+    // nonzero, which is all rounding needs to know about them.
+    let mut mantissa: u64 = 0;
+    let mut exponent: i64 = 0;
+    let mut sticky = false;
+    let mut any_digits = false;
+    let mut seen_point = false;
+    loop {
+        match s.get(i) {
+            Some(b'.') if !seen_point => seen_point = true,
+            Some(&c) if (c as char).is_ascii_hexdigit() => {
+                let d = u64::from((c as char).to_digit(16).unwrap());
+                any_digits = true;
+                if mantissa < 1 << 60 {
+                    mantissa = mantissa * 16 + d;
+                    if seen_point {
+                        exponent -= 4;
+                    }
+                } else {
+                    sticky |= d != 0;
+                    if !seen_point {
+                        exponent += 4;
+                    }
+                }
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+    if !any_digits || !(s.get(i) == Some(&b'p') || s.get(i) == Some(&b'P')) {
+        return None;
+    }
+    i += 1;
+
+    let exp_negative = match s.get(i) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    let exp_start = i;
+    let mut binary_exponent: i64 = 0;
+    while let Some(&c) = s.get(i) {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        // Anything this large overflows or underflows anyway.
+        binary_exponent = (binary_exponent * 10 + i64::from(c - b'0')).min(1 << 20);
+        i += 1;
+    }
+    if i == exp_start {
+        return None;
+    }
+    exponent += if exp_negative {
+        -binary_exponent
+    } else {
+        binary_exponent
+    };
+
+    let magnitude = if mantissa == 0 {
+        0.0
+    } else {
+        // Round MANTISSA to the precision of the result, which is less
+        // than 53 bits if it is subnormal, and scale it exactly.
+        let bits = 64 - i64::from(mantissa.leading_zeros());
+        let top = exponent + bits - 1;
+        let precision = 53 - (-1022 - top).max(0);
+        if precision < 0 {
+            0.0
+        } else {
+            let shift = bits - precision;
+            if shift > 0 {
+                let q = mantissa.checked_shr(shift as u32).unwrap_or(0);
+                let rem = mantissa & (u64::max_value() >> (64 - shift));
+                let half = 1u64 << (shift - 1);
+                if rem > half || (rem == half && (sticky || q & 1 == 1)) {
+                    mantissa = q + 1;
+                } else {
+                    mantissa = q;
+                }
+                exponent += shift;
+            }
+            let exponent = exponent.max(-10_000).min(10_000) as libc::c_int;
+            libm::ldexp(mantissa as EmacsDouble, exponent)
+        }
+    };
+    Some((if negative { -magnitude } else { magnitude }, i))
+}
+
 /// Calculate the modulus of two elisp floats.
 pub fn fmod_float(mut f1: f64, f2: f64) -> EmacsDouble {
     f1 %= f2;
@@ -162,6 +365,78 @@ simple_float_op!("tan", tan, "Return the tangent of ARG.");
 
 simple_float_op!("exp", exp, "Return the exponential base e of ARG.");
 simple_float_op!("sqrt", sqrt, "Return the square root of ARG.");
+simple_float_op!("cbrt", cbrt, "Return the cube root of ARG.");
+simple_float_op!("expm1", exp_m1, "Return (exp ARG) minus 1.");
+simple_float_op!("log1p", ln_1p, "Return (log (1+ ARG)).");
+
+simple_float_op!("sinh", sinh, "Return the hyperbolic sine of ARG.");
+simple_float_op!("cosh", cosh, "Return the hyperbolic cosine of ARG.");
+simple_float_op!("tanh", tanh, "Return the hyperbolic tangent of ARG.");
+
+/// Return the inverse hyperbolic sine of ARG.
+#[lisp_fn]
+pub fn asinh(arg: EmacsDouble) -> EmacsDouble {
+    libm::asinh(arg)
+}
+
+/// Return the inverse hyperbolic cosine of ARG.
+/// The result is a NaN if ARG is less than 1.
+#[lisp_fn]
+pub fn acosh(arg: EmacsDouble) -> EmacsDouble {
+    libm::acosh(arg)
+}
+
+/// Return the inverse hyperbolic tangent of ARG.
+/// The result is infinite if ARG is 1 or -1, and a NaN if its magnitude
+/// is greater than 1.
+#[lisp_fn]
+pub fn atanh(arg: EmacsDouble) -> EmacsDouble {
+    libm::atanh(arg)
+}
+
+/// Return the gamma function of ARG.
+/// The result is a NaN if ARG is a negative integer, and an infinity if
+/// it is a zero with the same sign.
+#[lisp_fn]
+pub fn gamma(arg: EmacsDouble) -> EmacsDouble {
+    libm::tgamma(arg)
+}
+
+/// Return the natural logarithm of the absolute value of the gamma
+/// function of ARG.
+/// Unlike `gamma', this does not overflow for large ARG.  The result is
+/// +inf if ARG is zero or a negative integer.
+#[lisp_fn]
+pub fn lgamma(arg: EmacsDouble) -> EmacsDouble {
+    libm::lgamma(arg).0
+}
+
+/// Return the error function of ARG.
+#[lisp_fn]
+pub fn erf(arg: EmacsDouble) -> EmacsDouble {
+    libm::erf(arg)
+}
+
+/// Return the complementary error function of ARG, that is 1 - (erf ARG).
+/// The result is accurate even when (erf ARG) is close to 1.
+#[lisp_fn]
+pub fn erfc(arg: EmacsDouble) -> EmacsDouble {
+    libm::erfc(arg)
+}
+
+/// Return the square root of X*X + Y*Y, without undue overflow or underflow.
+/// The result is +inf if either argument is infinite, even if the other
+/// is a NaN.
+#[lisp_fn]
+pub fn hypot(x: EmacsDouble, y: EmacsDouble) -> EmacsDouble {
+    x.hypot(y)
+}
+
+/// Return X * Y + Z, computed with a single rounding.
+#[lisp_fn]
+pub fn fma(x: EmacsDouble, y: EmacsDouble, z: EmacsDouble) -> EmacsDouble {
+    x.mul_add(y, z)
+}
 
 /// Driver for standard arithmetic operations on floats.
 pub fn float_arith_driver(
@@ -175,6 +450,7 @@ pub fn float_arith_driver(
         let next = match val.as_number_coerce_marker_or_error() {
             LispNumber::Float(f) => f,
             LispNumber::Fixnum(d) => d as f64,
+            LispNumber::Bignum(b) => b.to_float(),
         };
         match code {
             ArithOp::Add => accum += next,
@@ -279,6 +555,8 @@ pub fn float(arg: LispObject) -> LispObject {
         arg
     } else if let Some(n) = arg.as_fixnum() {
         LispObject::from_float(n as EmacsDouble)
+    } else if let Some(b) = arg.as_bignum() {
+        LispObject::from_float(b.to_float())
     } else {
         wrong_type!(Qnumberp, arg);
     }
@@ -320,9 +598,21 @@ pub fn ldexp(significand: EmacsDouble, exponent: EmacsInt) -> EmacsDouble {
 /// Return the exponential ARG1 ** ARG2.
 #[lisp_fn]
 pub fn expt(arg1: LispObject, arg2: LispObject) -> LispObject {
-    if let (Some(x), Some(y)) = (arg1.as_fixnum(), arg2.as_fixnum()) {
-        if y >= 0 && y <= EmacsInt::from(u32::max_value()) {
-            return x.pow(y as u32).into();
+    if let (Some(x), Some(y)) = (arg1.as_bigint(), arg2.as_fixnum()) {
+        if y >= 0 {
+            let y = y as usize;
+            // Only 0, 1 and -1 have powers that stay small.
+            if x.abs() <= BigInt::one() {
+                return if x.is_negative() && y.is_odd() {
+                    LispObject::from_fixnum(-1)
+                } else if x.is_zero() && y > 0 {
+                    LispObject::from_fixnum(0)
+                } else {
+                    LispObject::from_fixnum(1)
+                };
+            }
+            check_bignum_bits((x.bits() - 1).saturating_mul(y));
+            return num_traits::pow(x, y).into();
         }
     }
     let b = arg1.any_to_float_or_error();
@@ -341,6 +631,8 @@ pub fn logb(arg: LispObject) -> EmacsInt {
         } else {
             (mem::size_of::<EmacsUint>() * 8) as EmacsInt - 1 - EmacsInt::from(i.leading_zeros())
         }
+    } else if let Some(b) = arg.as_bignum() {
+        b.to_bigint().bits() as EmacsInt - 1
     } else if let Some(f) = arg.as_float() {
         if f == 0.0 {
             MOST_NEGATIVE_FIXNUM
@@ -366,16 +658,23 @@ pub fn fround(arg: LispObject) -> EmacsDouble {
 /// This rounds the value towards +inf.
 /// With optional DIVISOR, return the smallest integer no less than ARG/DIVISOR.
 #[lisp_fn(min = "1")]
-pub fn ceiling(arg: LispObject, divisor: LispObject) -> EmacsInt {
-    rounding_driver(arg, divisor, |x| x.ceil(), ceiling2, "ceiling")
+pub fn ceiling(arg: LispObject, divisor: LispObject) -> LispObject {
+    rounding_driver(
+        arg,
+        divisor,
+        |x| x.ceil(),
+        ceiling2,
+        bigint_ceiling2,
+        "ceiling",
+    )
 }
 
 /// Return the largest integer no greater than ARG.
 /// This rounds the value towards -inf.
 /// With optional DIVISOR, return the largest integer no greater than ARG/DIVISOR.
 #[lisp_fn(min = "1")]
-pub fn floor(arg: LispObject, divisor: LispObject) -> EmacsInt {
-    rounding_driver(arg, divisor, |x| x.floor(), floor2, "floor")
+pub fn floor(arg: LispObject, divisor: LispObject) -> LispObject {
+    rounding_driver(arg, divisor, |x| x.floor(), floor2, bigint_floor2, "floor")
 }
 
 /// Return the nearest integer to ARG.
@@ -386,16 +685,23 @@ pub fn floor(arg: LispObject, divisor: LispObject) -> EmacsInt {
 /// your machine.  For example, (round 2.5) can return 3 on some
 /// systems, but 2 on others.
 #[lisp_fn(min = "1")]
-pub fn round(arg: LispObject, divisor: LispObject) -> EmacsInt {
-    rounding_driver(arg, divisor, libm::rint, round2, "round")
+pub fn round(arg: LispObject, divisor: LispObject) -> LispObject {
+    rounding_driver(arg, divisor, libm::rint, round2, bigint_round2, "round")
 }
 
 /// Truncate a floating point number to an int.
 /// Rounds ARG toward zero.
 /// With optional DIVISOR, truncate ARG/DIVISOR.
 #[lisp_fn(min = "1")]
-pub fn truncate(arg: LispObject, divisor: LispObject) -> EmacsInt {
-    rounding_driver(arg, divisor, |x| x.trunc(), truncate2, "truncate")
+pub fn truncate(arg: LispObject, divisor: LispObject) -> LispObject {
+    rounding_driver(
+        arg,
+        divisor,
+        |x| x.trunc(),
+        truncate2,
+        bigint_truncate2,
+        "truncate",
+    )
 }
 
 fn rounding_driver(
@@ -403,12 +709,13 @@ fn rounding_driver(
     divisor: LispObject,
     double_round: impl Fn(f64) -> f64,
     int_round2: fn(EmacsInt, EmacsInt) -> EmacsInt,
+    bigint_round2: fn(&BigInt, &BigInt) -> BigInt,
     name: &str,
-) -> EmacsInt {
+) -> LispObject {
     let d;
     if divisor.is_nil() {
-        if arg.is_fixnum() {
-            return arg.as_fixnum().unwrap();
+        if arg.is_integer() {
+            return arg;
         } else if let Some(f) = arg.as_float() {
             d = f;
         } else {
@@ -419,7 +726,13 @@ fn rounding_driver(
             if div == 0 {
                 arith_error!();
             }
-            return int_round2(arg, div);
+            return LispObject::from_integer(int_round2(arg, div));
+        }
+        if let (Some(arg), Some(div)) = (arg.as_bigint(), divisor.as_bigint()) {
+            if div.is_zero() {
+                arith_error!();
+            }
+            return bigint_round2(&arg, &div).into();
         }
         let arg = arg.any_to_float_or_error();
         let div = divisor.any_to_float_or_error();
@@ -429,16 +742,19 @@ fn rounding_driver(
     // Round, coarsely test for fixnum overflow before converting to
     // EmacsInt (to avoid undefined behavior), and then exactly test
     // for overflow after converting (as FIXNUM_OVERFLOW_P is inaccurate
-    // on floats).
+    // on floats).  Larger results become bignums.
     let dr = double_round(d);
     if dr.abs() < (2 * (MOST_POSITIVE_FIXNUM + 1)) as f64 {
         let ir = dr as EmacsInt;
         if !LispObject::fixnum_overflow(ir) {
-            return ir;
+            return ir.into();
         }
     }
 
-    xsignal!(Qrange_error, name, arg)
+    match float_to_bigint(dr) {
+        Some(n) => n.into(),
+        None => xsignal!(Qrange_error, name, arg),
+    }
 }
 
 const fn ceiling2(i1: EmacsInt, i2: EmacsInt) -> EmacsInt {
@@ -471,6 +787,33 @@ fn round2(i1: EmacsInt, i2: EmacsInt) -> EmacsInt {
     }
 }
 
+fn bigint_ceiling2(n1: &BigInt, n2: &BigInt) -> BigInt {
+    -(-n1).div_floor(n2)
+}
+
+fn bigint_floor2(n1: &BigInt, n2: &BigInt) -> BigInt {
+    n1.div_floor(n2)
+}
+
+fn bigint_truncate2(n1: &BigInt, n2: &BigInt) -> BigInt {
+    n1 / n2
+}
+
+fn bigint_round2(n1: &BigInt, n2: &BigInt) -> BigInt {
+    // As in `round2'.
+    let (q, r) = n1.div_rem(n2);
+    let abs_r = r.abs();
+    let abs_r1 = n2.abs() - &abs_r;
+    let odd = if q.is_odd() { 1 } else { 0 };
+    if abs_r + odd <= abs_r1 {
+        q
+    } else if n2.is_negative() != r.is_negative() {
+        q - 1
+    } else {
+        q + 1
+    }
+}
+
 // Since these are generated via a macro the build cannot hook them into the
 // system automatically. Do not add more items here unless they are also generated
 // with something like simple_float_op.
@@ -483,6 +826,12 @@ pub fn rust_init_extra_syms() {
         defsubr(Stan.as_ptr());
         defsubr(Sexp.as_ptr());
         defsubr(Ssqrt.as_ptr());
+        defsubr(Scbrt.as_ptr());
+        defsubr(Sexpm1.as_ptr());
+        defsubr(Slog1p.as_ptr());
+        defsubr(Ssinh.as_ptr());
+        defsubr(Scosh.as_ptr());
+        defsubr(Stanh.as_ptr());
     }
 }
 
@@ -505,3 +854,53 @@ fn test_lisp_float_size() {
 
     assert!(mem::size_of::<Lisp_Float>() == max(double_size, ptr_size));
 }
+
+#[test]
+fn test_float_to_shortest_string() {
+    assert_eq!(float_to_shortest_string(0.0), "0.0");
+    assert_eq!(float_to_shortest_string(-0.0), "-0.0");
+    assert_eq!(float_to_shortest_string(100.0), "100.0");
+    assert_eq!(float_to_shortest_string(0.1), "0.1");
+    assert_eq!(float_to_shortest_string(1.0 / 3.0), "0.3333333333333333");
+    assert_eq!(float_to_shortest_string(1e14), "100000000000000.0");
+    assert_eq!(float_to_shortest_string(1e15), "1e+15");
+    assert_eq!(float_to_shortest_string(0.0001), "0.0001");
+    assert_eq!(float_to_shortest_string(-1.5e-5), "-1.5e-05");
+    assert_eq!(float_to_shortest_string(5e-324), "5e-324");
+    assert_eq!(float_to_shortest_string(EmacsDouble::INFINITY), "1.0e+INF");
+    assert_eq!(float_to_shortest_string(-EmacsDouble::NAN), "-0.0e+NaN");
+
+    // Every float reads back as itself.
+    let mut x: u64 = 0x1234_5678_9abc_def1;
+    for _ in 0..10_000 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        let f = EmacsDouble::from_bits(x);
+        if f.is_finite() {
+            let s = float_to_shortest_string(f);
+            assert_eq!(s.parse::<EmacsDouble>().unwrap().to_bits(), x, "{}", s);
+        }
+    }
+}
+
+#[test]
+fn test_parse_hex_float() {
+    let parse = |s: &str| parse_hex_float(s.as_bytes()).map(|(f, _)| f);
+    assert_eq!(parse("0x1.8p3"), Some(12.0));
+    assert_eq!(parse("-0X.8P1 and more"), Some(-1.0));
+    assert_eq!(parse("+0x10p-4"), Some(1.0));
+    assert_eq!(parse("0x1p1024"), Some(EmacsDouble::INFINITY));
+    assert_eq!(parse("0x1p-1074"), Some(5e-324));
+    // Halfway cases round to even, unless a later digit breaks the tie.
+    assert_eq!(parse("0x1p-1075"), Some(0.0));
+    assert_eq!(parse("0x1.0000000000001p-1075"), Some(5e-324));
+    assert_eq!(parse("0x1.00000000000008p0"), Some(1.0));
+    assert_eq!(parse("0x1.000000000000080001p0"), Some(1.0000000000000002));
+    assert_eq!(parse("0x1.fffffffffffff8p0"), Some(2.0));
+    assert_eq!(parse("0x1.8"), None);
+    assert_eq!(parse("0xp1"), None);
+    assert_eq!(parse("0x1p"), None);
+    assert_eq!(parse("1.5"), None);
+    assert_eq!(parse_hex_float(b"-0x1p4 and more"), Some((-16.0, 6)));
+}
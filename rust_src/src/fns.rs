@@ -500,15 +500,10 @@ pub fn yes_or_no_p(prompt: LispStringRef) -> bool {
     let prompt = lisp_concat!(prompt, yes_or_no).into();
 
     loop {
-        let ans: LispStringRef = downcase(read_from_minibuffer(
-            prompt,
+        let ans: LispStringRef = downcase(
+            read_from_minibuffer(prompt, Qnil, Qnil, false, Qyes_or_no_p_history, Qnil, false),
             Qnil,
-            Qnil,
-            false,
-            Qyes_or_no_p_history,
-            Qnil,
-            false,
-        ))
+        )
         .into();
 
         match ans.as_slice() {
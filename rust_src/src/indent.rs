@@ -6,6 +6,7 @@ use remacs_macros::lisp_fn;
 
 use crate::{
     buffers::{point_byte, point_min_byte},
+    character::grapheme_width,
     editfns::{insert_char, point, point_min},
     lisp::LispObject,
     lists::LispCons,
@@ -18,7 +19,8 @@ use crate::{
         set_point, set_point_both,
     },
     remacs_sys::{globals, last_known_column, last_known_column_modified, last_known_column_point},
-    remacs_sys::{EmacsInt, EmacsUint},
+    remacs_sys::{EmacsInt, EmacsUint, Lisp_Char_Table},
+    segmentation::{first_grapheme_len, segmentation_char},
     threads::ThreadState,
     windows::{LispWindowLiveOrSelected, LispWindowRef},
 };
@@ -147,6 +149,46 @@ pub extern "C" fn invalidate_current_column() {
     unsafe { last_known_column_point = 0 };
 }
 
+/// Return the width of the grapheme cluster at POS in the current
+/// buffer, which must be multibyte, when displayed with the display
+/// table DP.  The cluster is cut short at LIMIT.  Store the number of
+/// characters and bytes it has in *NCHARS and *NBYTES.
+#[no_mangle]
+pub extern "C" fn grapheme_cluster_width(
+    pos: isize,
+    pos_byte: isize,
+    limit: isize,
+    dp: *mut Lisp_Char_Table,
+    nchars: *mut isize,
+    nbytes: *mut isize,
+) -> isize {
+    let buffer = ThreadState::current_buffer_unchecked();
+    // Each character, with the byte position after it.
+    let chars = || {
+        let mut byte = pos_byte;
+        (pos..max(limit, pos + 1)).map(move |_| {
+            let c = buffer.fetch_char(byte);
+            byte = buffer.inc_pos(byte);
+            (c, byte)
+        })
+    };
+
+    let len = first_grapheme_len(chars().map(|(c, _)| segmentation_char(c)));
+    let mut end_byte = pos_byte;
+    let cluster: Vec<Codepoint> = chars()
+        .take(len)
+        .map(|(c, byte)| {
+            end_byte = byte;
+            c
+        })
+        .collect();
+    unsafe {
+        *nchars = len as isize;
+        *nbytes = end_byte - pos_byte;
+    }
+    grapheme_width(&cluster, dp) as isize
+}
+
 /// Indent from point with tabs and spaces until COLUMN is reached.
 /// Optional second argument MINIMUM says always do at least MINIMUM
 /// spaces even if that goes past COLUMN; by default, MINIMUM is zero.
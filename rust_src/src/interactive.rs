@@ -37,7 +37,7 @@ impl InteractiveNumericPrefix {
                     1
                 } else if raw.eq(Qminus) {
                     -1
-                } else if raw.is_integer() {
+                } else if raw.is_fixnum() {
                     raw.into()
                 } else if let Some(number) = raw.as_cons().and_then(|v| v.car().as_fixnum()) {
                     number
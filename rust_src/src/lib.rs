@@ -29,9 +29,15 @@ extern crate lazy_static;
 extern crate base64 as base64_crate;
 extern crate libc;
 extern crate md5;
+extern crate num_bigint;
+extern crate num_integer;
+extern crate num_traits;
 extern crate rand;
 extern crate sha1;
 extern crate sha2;
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
+extern crate unicode_width;
 
 extern crate field_offset;
 extern crate flate2;
@@ -64,6 +70,7 @@ mod str2sig;
 
 mod alloc;
 mod base64;
+mod bignum;
 mod buffers;
 mod bytecode;
 mod callint;
@@ -92,6 +99,8 @@ mod eval;
 mod ffi;
 mod fileio;
 mod filelock;
+#[cfg(feature = "file-notify")]
+mod filewatch;
 mod floatfns;
 mod fns;
 mod fonts;
@@ -115,15 +124,21 @@ mod objects;
 mod print;
 mod process;
 mod profiler;
+mod random;
 #[allow(clippy::all)]
 mod remacs_sys;
 mod search;
+mod segmentation;
 mod symbols;
 mod syntax;
 mod terminal;
 mod textprop;
 mod threads;
 mod time;
+#[cfg(unix)]
+mod timezone;
+#[cfg(unix)]
+mod trash;
 mod util;
 mod vectors;
 mod window_configuration;
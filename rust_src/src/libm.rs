@@ -8,6 +8,13 @@ mod sys {
         pub fn frexp(n: c_double, value: &mut c_int) -> c_double;
         pub fn ldexp(x: c_double, n: c_int) -> c_double;
         pub fn rint(x: c_double) -> c_double;
+        pub fn asinh(x: c_double) -> c_double;
+        pub fn acosh(x: c_double) -> c_double;
+        pub fn atanh(x: c_double) -> c_double;
+        pub fn tgamma(x: c_double) -> c_double;
+        pub fn lgamma_r(x: c_double, sign: &mut c_int) -> c_double;
+        pub fn erf(x: c_double) -> c_double;
+        pub fn erfc(x: c_double) -> c_double;
     }
 }
 
@@ -34,6 +41,44 @@ pub fn rint(x: f64) -> f64 {
     unsafe { sys::rint(x) }
 }
 
+/// Return the inverse hyperbolic sine of `x`.
+pub fn asinh(x: f64) -> f64 {
+    unsafe { sys::asinh(x) }
+}
+
+/// Return the inverse hyperbolic cosine of `x`.
+pub fn acosh(x: f64) -> f64 {
+    unsafe { sys::acosh(x) }
+}
+
+/// Return the inverse hyperbolic tangent of `x`.
+pub fn atanh(x: f64) -> f64 {
+    unsafe { sys::atanh(x) }
+}
+
+/// Return the gamma function of `x`.
+pub fn tgamma(x: f64) -> f64 {
+    unsafe { sys::tgamma(x) }
+}
+
+/// Return the natural logarithm of the absolute value of the gamma
+/// function of `x`, and the sign of the gamma function.
+pub fn lgamma(x: f64) -> (f64, c_int) {
+    let mut sign: c_int = 0;
+    let l = unsafe { sys::lgamma_r(x, &mut sign) };
+    (l, sign)
+}
+
+/// Return the error function of `x`.
+pub fn erf(x: f64) -> f64 {
+    unsafe { sys::erf(x) }
+}
+
+/// Return the complementary error function of `x`, 1 - erf(`x`).
+pub fn erfc(x: f64) -> f64 {
+    unsafe { sys::erfc(x) }
+}
+
 #[cfg(test)]
 mod tests {
     use super::signbit;
@@ -617,7 +617,7 @@ impl LispObject {
     }
 
     pub fn eql(self, other: impl Into<Self>) -> bool {
-        if self.is_float() {
+        if self.is_float() || self.is_bignum() {
             self.equal_no_quit(other)
         } else {
             self.eq(other)
@@ -1,10 +1,22 @@
 //! Functions doing math on numbers.
 #![allow(clippy::float_cmp)]
 
+use std::cmp::{self, Ordering};
+use std::mem;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, ToPrimitive, Zero};
+
 use crate::remacs_sys::{EmacsInt, Qnumberp};
 use remacs_macros::lisp_fn;
 
-use crate::{floatfns, lisp::LispObject, numbers::LispNumber};
+use crate::{
+    bignum::{self, check_bignum_bits},
+    floatfns,
+    lisp::LispObject,
+    numbers::{LispNumber, MOST_POSITIVE_FIXNUM},
+};
 
 /// Return X modulo Y.
 /// The result falls between zero (inclusive) and Y (exclusive).
@@ -27,13 +39,15 @@ pub fn lisp_mod(x: LispNumber, y: LispNumber) -> LispObject {
 
             i1.into()
         }
-        (LispNumber::Fixnum(i1), LispNumber::Float(f2)) => {
-            floatfns::fmod_float(i1 as f64, f2).into()
-        }
-        (LispNumber::Float(f1), LispNumber::Fixnum(i2)) => {
-            floatfns::fmod_float(f1, i2 as f64).into()
+        (LispNumber::Float(f1), y) => floatfns::fmod_float(f1, y.to_float()).into(),
+        (x, LispNumber::Float(f2)) => floatfns::fmod_float(x.to_float(), f2).into(),
+        (x, y) => {
+            let y = y.to_bigint();
+            if y.is_zero() {
+                arith_error!();
+            }
+            x.to_bigint().mod_floor(&y).into()
         }
-        (LispNumber::Float(f1), LispNumber::Float(f2)) => floatfns::fmod_float(f1, f2).into(),
     }
 }
 
@@ -55,7 +69,9 @@ pub enum ArithOp {
 /// Given an array of `LispObject`, reduce over them according to the
 /// arithmetic operation specified.
 ///
-/// Modifies the array in place.
+/// The reduction is done on `EmacsInt`s for as long as the result fits,
+/// and continues on bignums or floats from the first argument that
+/// needs them.
 fn arith_driver(code: ArithOp, args: &[LispObject]) -> LispObject {
     let mut accum: EmacsInt = match code {
         ArithOp::Add | ArithOp::Sub | ArithOp::Logior | ArithOp::Logxor => 0,
@@ -63,81 +79,56 @@ fn arith_driver(code: ArithOp, args: &[LispObject]) -> LispObject {
         _ => 1,
     };
 
-    // TODO: use better variable names rather than just copying the C.
-    let mut overflow = false;
-    let mut ok_accum = accum;
-    let mut ok_args = 0;
-
     for (argnum, &val) in args.iter().enumerate() {
-        if !overflow {
-            ok_args = argnum;
-            ok_accum = accum;
-        }
-
-        match val.as_number_coerce_marker_or_error() {
+        let next = match val.as_number_coerce_marker_or_error() {
             LispNumber::Float(_) => {
-                return floatfns::float_arith_driver(ok_accum as f64, ok_args, code, args).into();
+                return floatfns::float_arith_driver(accum as f64, argnum, code, args).into();
             }
-            LispNumber::Fixnum(next) => {
-                match code {
-                    ArithOp::Add => {
-                        if accum.checked_add(next).is_none() {
-                            overflow = true;
-                        }
-                        accum = accum.wrapping_add(next);
-                    }
-                    ArithOp::Sub => {
-                        if argnum == 0 {
-                            if args.len() == 1 {
-                                // Calling - with one argument negates it.
-                                accum = -next;
-                            } else {
-                                accum = next;
-                            }
-                        } else {
-                            if accum.checked_sub(next).is_none() {
-                                overflow = true;
-                            }
-                            accum = accum.wrapping_sub(next);
-                        }
-                    }
-                    ArithOp::Mult => {
-                        if accum.checked_mul(next).is_none() {
-                            overflow = true;
-                        }
-                        accum = accum.wrapping_mul(next);
-                    }
-                    ArithOp::Div => {
-                        // If we have multiple arguments, we divide the first
-                        // argument by all the others.
-                        if args.len() > 1 && argnum == 0 {
-                            accum = next;
-                        } else {
-                            if next == 0 {
-                                arith_error!();
-                            }
-                            if accum.checked_div(next).is_none() {
-                                overflow = true;
-                            } else {
-                                accum = accum.wrapping_div(next);
-                            }
-                        }
-                    }
-                    ArithOp::Logand => {
-                        accum &= next;
-                    }
-                    ArithOp::Logior => {
-                        accum |= next;
+            LispNumber::Bignum(_) => {
+                return bignum::bignum_arith_driver(BigInt::from(accum), argnum, code, args);
+            }
+            LispNumber::Fixnum(next) => next,
+        };
+
+        let result = match code {
+            ArithOp::Add => accum.checked_add(next),
+            ArithOp::Sub => {
+                if argnum == 0 {
+                    if args.len() == 1 {
+                        // Calling - with one argument negates it.
+                        next.checked_neg()
+                    } else {
+                        Some(next)
                     }
-                    ArithOp::Logxor => {
-                        accum ^= next;
+                } else {
+                    accum.checked_sub(next)
+                }
+            }
+            ArithOp::Mult => accum.checked_mul(next),
+            ArithOp::Div => {
+                // If we have multiple arguments, we divide the first
+                // argument by all the others.
+                if args.len() > 1 && argnum == 0 {
+                    Some(next)
+                } else {
+                    if next == 0 {
+                        arith_error!();
                     }
+                    accum.checked_div(next)
                 }
             }
+            ArithOp::Logand => Some(accum & next),
+            ArithOp::Logior => Some(accum | next),
+            ArithOp::Logxor => Some(accum ^ next),
+        };
+
+        match result {
+            Some(result) => accum = result,
+            None => return bignum::bignum_arith_driver(BigInt::from(accum), argnum, code, args),
         }
     }
 
-    LispObject::from_fixnum_truncated(accum)
+    LispObject::from_integer(accum)
 }
 
 /// Return sum of any number of arguments, which are numbers or markers.
@@ -243,7 +234,13 @@ pub fn abs(arg: LispObject) -> LispObject {
     if let Some(f) = arg.as_float() {
         LispObject::from_float(f.abs())
     } else if let Some(n) = arg.as_fixnum() {
-        LispObject::from(n.abs())
+        LispObject::from_integer(n.abs())
+    } else if let Some(b) = arg.as_bignum() {
+        if b.is_negative() {
+            (-b.to_bigint()).into()
+        } else {
+            arg
+        }
     } else {
         wrong_type!(Qnumberp, arg);
     }
@@ -268,10 +265,9 @@ pub fn arithcompare(obj1: LispObject, obj2: LispObject, comparison: ArithCompari
     // ties if the floating-point comparison is either not done or reports
     // equality.
 
-    let (i1, i2, f1, f2) = match (
-        obj1.as_number_coerce_marker_or_error(),
-        obj2.as_number_coerce_marker_or_error(),
-    ) {
+    let num1 = obj1.as_number_coerce_marker_or_error();
+    let num2 = obj2.as_number_coerce_marker_or_error();
+    let (i1, i2, f1, f2) = match (num1, num2) {
         (LispNumber::Fixnum(n1), LispNumber::Fixnum(n2)) => (n1, n2, 0., 0.),
         (LispNumber::Fixnum(n1), LispNumber::Float(n2)) => {
             // Compare an integer NUM1 to a float NUM2.  This is the
@@ -290,6 +286,7 @@ pub fn arithcompare(obj1: LispObject, obj2: LispObject, comparison: ArithCompari
             (n2 as f64 as EmacsInt, n2, n1, n2 as f64)
         }
         (LispNumber::Float(n1), LispNumber::Float(n2)) => (0, 0, n1, n2),
+        _ => return ordering_matches(compare_bignums(num1, num2), comparison),
     };
     let fneq = f1 != f2;
 
@@ -327,6 +324,29 @@ pub fn arithcompare(obj1: LispObject, obj2: LispObject, comparison: ArithCompari
     }
 }
 
+/// Compare two numbers, at least one of which is a bignum, exactly.
+/// Return `None' if they are unordered because one is a NaN.
+fn compare_bignums(num1: LispNumber, num2: LispNumber) -> Option<Ordering> {
+    match (num1, num2) {
+        (LispNumber::Float(f1), n2) => {
+            bignum::compare_integer_float(&n2.to_bigint(), f1).map(Ordering::reverse)
+        }
+        (n1, LispNumber::Float(f2)) => bignum::compare_integer_float(&n1.to_bigint(), f2),
+        (n1, n2) => Some(n1.to_bigint().cmp(&n2.to_bigint())),
+    }
+}
+
+fn ordering_matches(ordering: Option<Ordering>, comparison: ArithComparison) -> bool {
+    match comparison {
+        ArithComparison::Equal => ordering == Some(Ordering::Equal),
+        ArithComparison::Notequal => ordering != Some(Ordering::Equal),
+        ArithComparison::Less => ordering == Some(Ordering::Less),
+        ArithComparison::Grtr => ordering == Some(Ordering::Greater),
+        ArithComparison::LessOrEqual => ordering.map_or(false, |o| o != Ordering::Greater),
+        ArithComparison::GrtrOrEqual => ordering.map_or(false, |o| o != Ordering::Less),
+    }
+}
+
 fn arithcompare_driver(args: &[LispObject], comparison: ArithComparison) -> bool {
     args.windows(2)
         .all(|i| arithcompare(i[0], i[1], comparison))
@@ -376,41 +396,109 @@ pub fn neq(num1: LispObject, num2: LispObject) -> bool {
 /// Return remainder of X divided by Y.
 /// Both must be integers or markers.
 #[lisp_fn(name = "%")]
-pub fn rem(x: LispNumber, y: LispNumber) -> EmacsInt {
-    let x = x.to_fixnum();
-    let y = y.to_fixnum();
+pub fn rem(x: LispNumber, y: LispNumber) -> LispObject {
+    match (x, y) {
+        (LispNumber::Bignum(_), _) | (_, LispNumber::Bignum(_)) => {
+            let y = y.to_bigint();
+            if y.is_zero() {
+                arith_error!();
+            }
+            (x.to_bigint() % y).into()
+        }
+        _ => {
+            let x = x.to_fixnum();
+            let y = y.to_fixnum();
 
-    if y == 0 {
-        arith_error!();
-    }
+            if y == 0 {
+                arith_error!();
+            }
 
-    x % y
+            LispObject::from_integer(x.wrapping_rem(y))
+        }
+    }
 }
 
 /// Return NUMBER plus one.  NUMBER may be a number or a marker.
 /// Markers are converted to integers.
 #[lisp_fn(name = "1+")]
-pub fn add1(number: LispNumber) -> LispNumber {
+pub fn add1(number: LispNumber) -> LispObject {
     match number {
-        LispNumber::Fixnum(num) => LispNumber::Fixnum(num + 1),
-        LispNumber::Float(num) => LispNumber::Float(num + 1.0),
+        LispNumber::Fixnum(num) => LispObject::from_integer(num + 1),
+        LispNumber::Bignum(num) => (num.to_bigint() + 1).into(),
+        LispNumber::Float(num) => LispObject::from_float(num + 1.0),
     }
 }
 
 /// Return NUMBER minus one.  NUMBER may be a number or a marker.
 /// Markers are converted to integers.
 #[lisp_fn(name = "1-")]
-pub fn sub1(number: LispNumber) -> LispNumber {
+pub fn sub1(number: LispNumber) -> LispObject {
     match number {
-        LispNumber::Fixnum(num) => LispNumber::Fixnum(num - 1),
-        LispNumber::Float(num) => LispNumber::Float(num - 1.0),
+        LispNumber::Fixnum(num) => LispObject::from_integer(num - 1),
+        LispNumber::Bignum(num) => (num.to_bigint() - 1).into(),
+        LispNumber::Float(num) => LispObject::from_float(num - 1.0),
     }
 }
 
 /// Return the bitwise complement of NUMBER.  NUMBER must be an integer.
 #[lisp_fn]
-pub fn lognot(number: EmacsInt) -> EmacsInt {
-    !number
+pub fn lognot(number: LispObject) -> LispObject {
+    match number.as_fixnum() {
+        Some(n) => LispObject::from_fixnum(!n),
+        None => (!number.as_bigint_or_error()).into(),
+    }
+}
+
+/// Return VALUE with its bits shifted left by COUNT.
+/// If COUNT is negative, shifting is actually to the right.
+/// In this case, the sign bit is duplicated.
+#[lisp_fn]
+pub fn ash(value: LispObject, count: LispObject) -> LispObject {
+    let width = (mem::size_of::<EmacsInt>() * 8) as EmacsInt;
+    if let (Some(v), Some(c)) = (value.as_fixnum(), count.as_fixnum()) {
+        if c <= 0 {
+            return LispObject::from_fixnum(v >> cmp::min(-c, width - 1));
+        } else if c < width && (v << c) >> c == v {
+            return LispObject::from_integer(v << c);
+        }
+    }
+
+    let value = value.as_bigint_or_error();
+    let count = count.as_bigint_or_error();
+    if value.is_zero() {
+        return LispObject::from_fixnum(0);
+    }
+    if count.is_negative() {
+        // Shifting a negative number right rounds toward -inf.
+        match (-count).to_usize() {
+            Some(c) => (value >> c).into(),
+            None => LispObject::from_fixnum(if value.is_negative() { -1 } else { 0 }),
+        }
+    } else {
+        let count = count.to_usize().unwrap_or_else(usize::max_value);
+        check_bignum_bits(value.bits().saturating_add(count));
+        (value << count).into()
+    }
+}
+
+/// Return VALUE with its bits shifted left by COUNT.
+/// If COUNT is negative, shifting is actually to the right.
+/// In this case, zeros are shifted in on the left, as if VALUE were an
+/// unsigned number as wide as a fixnum; VALUE must then not be a bignum.
+#[lisp_fn]
+pub fn lsh(value: LispObject, count: LispObject) -> LispObject {
+    let negative = |n: LispObject| n.as_bigint_or_error().is_negative();
+    if negative(value) && negative(count) {
+        let v = value
+            .as_fixnum()
+            .unwrap_or_else(|| args_out_of_range!(value, count));
+        let count = count.as_bigint_or_error() + 1;
+        return ash(
+            LispObject::from_fixnum((v >> 1) & MOST_POSITIVE_FIXNUM),
+            count.into(),
+        );
+    }
+    ash(value, count)
 }
 
 include!(concat!(env!("OUT_DIR"), "/math_exports.rs"));
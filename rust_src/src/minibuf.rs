@@ -1,5 +1,8 @@
 //! Minibuffer input and completion.
 
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
 use remacs_macros::lisp_fn;
 
 use crate::{
@@ -9,8 +12,9 @@ use crate::{
     eval, fns,
     keymap::get_keymap,
     lisp::LispObject,
-    lists::{car_safe, cdr_safe, memq, LispCons},
-    multibyte::LispStringRef,
+    lists::{car_safe, cdr_safe, list, memq, LispCons},
+    lists::{LispConsCircularChecks, LispConsEndChecks},
+    multibyte::{Codepoint, LispStringRef},
     obarray::{intern, intern_lisp},
     remacs_sys::{
         globals, Qbuffer_name_history, Qcommandp, Qcompletion_ignore_case, Qcustom_variable_p,
@@ -522,4 +526,331 @@ pub fn read_buffer(
     eval::unbind_to(count, result)
 }
 
+// Scores used by `completion-fuzzy-match'.  A match is worth more the
+// closer it follows the previous one and the more it looks like the
+// start of a word; every character skipped costs a little.
+const FUZZY_GAP_LEADING: f64 = -0.005;
+const FUZZY_GAP_TRAILING: f64 = -0.005;
+const FUZZY_GAP_INNER: f64 = -0.01;
+const FUZZY_MATCH_CONSECUTIVE: f64 = 1.0;
+const FUZZY_MATCH_SLASH: f64 = 0.9;
+const FUZZY_MATCH_WORD: f64 = 0.8;
+const FUZZY_MATCH_CAPITAL: f64 = 0.7;
+const FUZZY_MATCH_DOT: f64 = 0.6;
+
+// The most entries the tables of `fuzzy_score' may have, one per pair
+// of a pattern character and a candidate character.
+const FUZZY_MAX_CELLS: usize = 64 * 1024;
+
+/// Return the bonus for matching the character C when it follows PREV.
+fn fuzzy_match_bonus(prev: Codepoint, c: Codepoint) -> f64 {
+    let prev = char::try_from(u32::from(prev)).unwrap_or('\0');
+    let c = char::try_from(u32::from(c)).unwrap_or('\0');
+    if !c.is_alphanumeric() {
+        return 0.0;
+    }
+    match prev {
+        '/' => FUZZY_MATCH_SLASH,
+        '-' | '_' | ' ' => FUZZY_MATCH_WORD,
+        '.' => FUZZY_MATCH_DOT,
+        _ if c.is_uppercase() && prev.is_lowercase() => FUZZY_MATCH_CAPITAL,
+        _ => 0.0,
+    }
+}
+
+/// Score the fuzzy match of PATTERN in CANDIDATE, comparing the
+/// characters of PATTERN with those of FOLDED, which is CANDIDATE with
+/// the same case folding as PATTERN.  Return the score and the indices
+/// of the characters matched, in the alignment that scores best, or
+/// None if PATTERN is not a subsequence of FOLDED.
+///
+/// If the tables needed to find the best alignment would have more than
+/// `FUZZY_MAX_CELLS` entries, match each character of PATTERN at its
+/// first possible position instead, and score the match as if every
+/// character of CANDIDATE were skipped.
+pub fn fuzzy_score(
+    pattern: &[Codepoint],
+    candidate: &[Codepoint],
+    folded: &[Codepoint],
+) -> Option<(f64, Vec<usize>)> {
+    let n = pattern.len();
+    let m = folded.len();
+    if n == 0 {
+        return Some((0.0, Vec::new()));
+    }
+    if n > m {
+        return None;
+    }
+    if n * m > FUZZY_MAX_CELLS {
+        let mut positions = Vec::with_capacity(n);
+        let mut j = 0;
+        for &c in pattern {
+            j += folded[j..].iter().position(|&d| d == c)?;
+            positions.push(j);
+            j += 1;
+        }
+        return Some((m as f64 * FUZZY_GAP_INNER, positions));
+    }
+
+    let bonus: Vec<f64> = (0..m)
+        .map(|j| {
+            let prev = if j == 0 {
+                Codepoint::from('/')
+            } else {
+                candidate[j - 1]
+            };
+            fuzzy_match_bonus(prev, candidate[j])
+        })
+        .collect();
+
+    // best[i * m + j] is the best score of PATTERN[..=i] in FOLDED[..=j],
+    // and ending[i * m + j] the best one with PATTERN[i] matched at j.
+    let mut best = vec![f64::NEG_INFINITY; n * m];
+    let mut ending = vec![f64::NEG_INFINITY; n * m];
+    for i in 0..n {
+        let gap = if i == n - 1 {
+            FUZZY_GAP_TRAILING
+        } else {
+            FUZZY_GAP_INNER
+        };
+        let mut prev_score = f64::NEG_INFINITY;
+        for j in 0..m {
+            if pattern[i] == folded[j] {
+                let score = if i == 0 {
+                    j as f64 * FUZZY_GAP_LEADING + bonus[j]
+                } else if j > 0 {
+                    let k = (i - 1) * m + j - 1;
+                    (best[k] + bonus[j]).max(ending[k] + FUZZY_MATCH_CONSECUTIVE)
+                } else {
+                    f64::NEG_INFINITY
+                };
+                ending[i * m + j] = score;
+                prev_score = score.max(prev_score + gap);
+            } else {
+                prev_score += gap;
+            }
+            best[i * m + j] = prev_score;
+        }
+    }
+
+    let score = best[n * m - 1];
+    if score == f64::NEG_INFINITY {
+        return None;
+    }
+
+    // Walk back through the table to recover the positions.
+    let mut positions = vec![0; n];
+    let mut must_match = false;
+    let mut j = m;
+    for i in (0..n).rev() {
+        while j > 0 {
+            j -= 1;
+            let k = i * m + j;
+            if ending[k] != f64::NEG_INFINITY && (must_match || ending[k] == best[k]) {
+                must_match = i > 0
+                    && j > 0
+                    && ending[k] == ending[(i - 1) * m + j - 1] + FUZZY_MATCH_CONSECUTIVE;
+                positions[i] = j;
+                break;
+            }
+        }
+    }
+    Some((score, positions))
+}
+
+/// Return the fuzzy matches of PATTERN among COLLECTION, with scores.
+/// COLLECTION is a list of strings or symbols, or an alist whose keys are
+/// strings or symbols.  A candidate matches if the characters of PATTERN
+/// appear in it in the same order, not necessarily next to each other.
+/// Case is ignored if `completion-ignore-case' is non-nil.
+///
+/// The value has an element (CANDIDATE SCORE POSITIONS) for each matching
+/// CANDIDATE, a string, in the order of COLLECTION.  SCORE is a float that
+/// is higher for better matches: matches in a row, and matches at the
+/// start of the candidate or of a word in it, after a slash, a dot, a
+/// space, `-' or `_' or at a capital letter following a lower case one,
+/// are worth more, and every character skipped costs a little.
+/// POSITIONS is a list of the indices of the characters of CANDIDATE that
+/// matched PATTERN, in the alignment that gives SCORE, suitable for
+/// highlighting them.
+///
+/// Very long candidates are not scored: each character of PATTERN
+/// matches the first possible character of the candidate, and SCORE is
+/// lower than that of any match in a candidate of the same length.
+///
+/// If SORT is non-nil, sort the value by decreasing score, putting
+/// shorter candidates first among equal scores.
+#[lisp_fn(min = "2")]
+pub fn completion_fuzzy_match(
+    pattern: LispStringRef,
+    collection: LispObject,
+    sort: bool,
+) -> LispObject {
+    let ignore_case = unsafe { globals.completion_ignore_case };
+    let downcase_table = ThreadState::current_buffer_unchecked()
+        .downcase_table_
+        .force_char_table();
+    let fold = |c: Codepoint| {
+        if ignore_case {
+            downcase_table
+                .get(c.val() as isize)
+                .as_natnum()
+                .map_or(c, |d| Codepoint::from(d as u32))
+        } else {
+            c
+        }
+    };
+    let pattern: Vec<Codepoint> = pattern.chars().map(fold).collect();
+
+    let mut matches = Vec::new();
+    for elt in collection.iter_cars(LispConsEndChecks::on, LispConsCircularChecks::on) {
+        let key = elt.as_cons().map_or(elt, LispCons::car);
+        let string = key
+            .as_symbol()
+            .map_or(key, |sym| sym.symbol_name())
+            .force_string();
+
+        // Most candidates fail here, before anything is allocated.
+        let mut remaining = pattern.iter().peekable();
+        for c in string.chars() {
+            if remaining.peek() == Some(&&fold(c)) {
+                remaining.next();
+            }
+        }
+        if remaining.peek().is_some() {
+            continue;
+        }
+
+        let candidate: Vec<Codepoint> = string.chars().collect();
+        let folded: Vec<Codepoint> = candidate.iter().map(|&c| fold(c)).collect();
+        if let Some((score, positions)) = fuzzy_score(&pattern, &candidate, &folded) {
+            matches.push((string, candidate.len(), score, positions));
+        }
+    }
+
+    if sort {
+        matches.sort_by(|(_, len1, score1, _), (_, len2, score2, _)| {
+            score2
+                .partial_cmp(score1)
+                .unwrap_or(Ordering::Equal)
+                .then(len1.cmp(len2))
+        });
+    }
+
+    let elements: Vec<LispObject> = matches
+        .into_iter()
+        .map(|(string, _, score, positions)| {
+            let positions: Vec<LispObject> = positions.into_iter().map(LispObject::from).collect();
+            list(&[
+                string.into(),
+                LispObject::from_float(score),
+                list(&positions),
+            ])
+        })
+        .collect();
+    list(&elements)
+}
+
 include!(concat!(env!("OUT_DIR"), "/minibuf_exports.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, candidate: &str) -> Option<(f64, Vec<usize>)> {
+        let pattern: Vec<Codepoint> = pattern.chars().map(Codepoint::from).collect();
+        let folded: Vec<Codepoint> = candidate
+            .chars()
+            .map(|c| Codepoint::from(c.to_ascii_lowercase()))
+            .collect();
+        let candidate: Vec<Codepoint> = candidate.chars().map(Codepoint::from).collect();
+        fuzzy_score(&pattern, &candidate, &folded)
+    }
+
+    #[test]
+    fn test_fuzzy_score_positions() {
+        assert_eq!(score("", "abc"), Some((0.0, vec![])));
+        assert_eq!(score("abd", "abc"), None);
+        assert_eq!(score("abcd", "abc"), None);
+        assert_eq!(score("abc", "abc").unwrap().1, vec![0, 1, 2]);
+        // Consecutive matches win over scattered ones.
+        assert_eq!(score("ab", "a_xab").unwrap().1, vec![3, 4]);
+        // Word starts win over the middle of words.
+        assert_eq!(score("fb", "xfoo-bar").unwrap().1, vec![1, 5]);
+        assert_eq!(score("fb", "fooBar").unwrap().1, vec![0, 3]);
+        assert_eq!(score("ml", "mini/lib").unwrap().1, vec![0, 5]);
+    }
+
+    /// Recompute the score of matching PATTERN at POSITIONS in CANDIDATE,
+    /// adding up the terms in the same order as `fuzzy_score`.
+    fn rescore(candidate: &str, positions: &[usize]) -> f64 {
+        let chars: Vec<Codepoint> = candidate.chars().map(Codepoint::from).collect();
+        let bonus = |j: usize| {
+            let prev = if j == 0 {
+                Codepoint::from('/')
+            } else {
+                chars[j - 1]
+            };
+            fuzzy_match_bonus(prev, chars[j])
+        };
+        let mut score = positions[0] as f64 * FUZZY_GAP_LEADING + bonus(positions[0]);
+        for w in positions.windows(2) {
+            if w[1] == w[0] + 1 {
+                score += FUZZY_MATCH_CONSECUTIVE;
+            } else {
+                for _ in w[0] + 1..w[1] {
+                    score += FUZZY_GAP_INNER;
+                }
+                score += bonus(w[1]);
+            }
+        }
+        for _ in positions[positions.len() - 1] + 1..chars.len() {
+            score += FUZZY_GAP_TRAILING;
+        }
+        score
+    }
+
+    #[test]
+    fn test_fuzzy_score_consistent() {
+        assert_eq!(score("aab", "aaab").unwrap().1, vec![1, 2, 3]);
+        for &(pattern, candidate) in &[
+            ("aab", "aaab"),
+            ("ab", "abab"),
+            ("abc", "aabcabc"),
+            ("fb", "foo-bar/fb"),
+            ("aa", "a_aa"),
+            ("ml", "mini/lib"),
+            ("xyz", "x-y-xyz"),
+        ] {
+            let (total, positions) = score(pattern, candidate).unwrap();
+            assert_eq!(
+                total,
+                rescore(candidate, &positions),
+                "{} in {}: {:?}",
+                pattern,
+                candidate,
+                positions
+            );
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score_order() {
+        let s = |p, c| score(p, c).unwrap().0;
+        assert!(s("abc", "abc") > s("abc", "axbxc"));
+        assert!(s("fb", "foo-bar") > s("fb", "afoobar"));
+        assert!(s("fb", "fooBar") > s("fb", "foobar"));
+        assert!(s("ab", "ab") > s("ab", "xab"));
+        assert!(s("ab", "abx") > s("ab", "axxb"));
+    }
+
+    #[test]
+    fn test_fuzzy_score_long_candidate() {
+        let long = "x".repeat(FUZZY_MAX_CELLS) + "a-b-ab";
+        let (total, positions) = score("ab", &long).unwrap();
+        let m = long.len();
+        assert_eq!(positions, vec![m - 6, m - 4]);
+        assert!(total < rescore(&long, &positions));
+        assert_eq!(score("abc", &long), None);
+    }
+}
@@ -38,20 +38,31 @@ use std::ptr;
 use std::slice;
 
 use libc::{c_char, c_int, c_uchar, c_uint, c_void, memset, ptrdiff_t, size_t};
+use unicode_normalization::{
+    is_nfc_quick, is_nfd_quick, is_nfkc_quick, is_nfkd_quick, IsNormalized, UnicodeNormalization,
+};
+
+use remacs_macros::lisp_fn;
 
 use crate::{
+    buffers::validate_region_rust,
+    character::grapheme_width,
+    editfns::buffer_substring_no_properties,
     hashtable::LispHashTableRef,
     lisp::{ExternalPtr, LispObject, LispStructuralEqual},
+    lists::list,
     obarray::LispObarrayRef,
     remacs_sys::{
-        buffer_display_table, char_width, compare_string_intervals, empty_unibyte_string,
-        find_composition as c_find_composition, get_composition_id, string_char_to_byte,
+        buffer_display_table, compare_string_intervals, empty_unibyte_string,
+        find_composition as c_find_composition, get_composition_id, make_multibyte_string,
+        replace_range, string_char_to_byte, wrong_choice,
     },
     remacs_sys::{
         char_bits, composition_table, equal_kind, EmacsDouble, EmacsInt, Lisp_Interval,
         Lisp_String, Lisp_Type,
     },
-    remacs_sys::{Qcharacterp, Qnil, Qstringp},
+    remacs_sys::{Qcharacterp, Qmaybe, Qnfc, Qnfd, Qnfkc, Qnfkd, Qnil, Qstringp, Qt},
+    segmentation::{segment_boundaries, segmentation_text, Segment},
     symbols::LispSymbolRef,
 };
 
@@ -366,10 +377,10 @@ impl LispStringRef {
 
     /// Return width of the string when displayed in the current buffer. The
     /// width is measured by how many columns it occupies on the screen while
-    /// paying attention to compositions.
+    /// paying attention to compositions and grapheme clusters.
     ///
     /// With `precision` argument, return the width of longest substring that
-    /// doesn't exceed `precision`, and the number of characters and bytes it
+    /// doesn't exceed `precision` and doesn't split a grapheme cluster, and the number of characters and bytes it
     /// contains in the returned tuple.
     pub fn display_width(self, precision: Option<usize>) -> (usize, Option<(usize, usize)>) {
         // Manually determine if string is unibyte (lets us ignore multibyte
@@ -384,6 +395,9 @@ impl LispStringRef {
         let mut i = 0;
         // Byte index
         let mut b = 0;
+        // Grapheme cluster boundaries, and the index of the next one
+        let boundaries = segment_boundaries(&segmentation_text(self), Segment::Grapheme);
+        let mut k = 0;
 
         while i < len {
             // If there is a composition, get its id and end position.
@@ -409,15 +423,24 @@ impl LispStringRef {
                 let thiswidth = unsafe { (*(*composition_table.offset(cmp_id))).width } as usize;
                 (chars, bytes as usize, thiswidth)
             } else {
-                // Character is a single codepoint, calculate it if multibyte, otherwise get
-                // raw byte at b.
-                let (ch, bytes) = if multibyte {
-                    unsafe { string_char_and_length(self.const_data_ptr().add(b)) }
-                } else {
-                    (self.as_slice()[b].into(), 1)
-                };
-                let chars = unsafe { char_width(ch.val() as i32, distab) } as usize;
-                (1, bytes, chars)
+                // Measure the grapheme cluster at i.  Decode its characters if
+                // multibyte, otherwise take the raw bytes from b.
+                while boundaries[k] <= i {
+                    k += 1;
+                }
+                let chars = boundaries[k] - i;
+                let mut cluster = Vec::with_capacity(chars);
+                let mut bytes = 0;
+                for _ in 0..chars {
+                    let (ch, len) = if multibyte {
+                        unsafe { string_char_and_length(self.const_data_ptr().add(b + bytes)) }
+                    } else {
+                        (self.as_slice()[b + bytes].into(), 1)
+                    };
+                    cluster.push(ch);
+                    bytes += len;
+                }
+                (chars, bytes, grapheme_width(&cluster, distab))
             };
 
             // Return if adding character exceeds precision
@@ -1205,3 +1228,204 @@ pub extern "C" fn lisp_string_width(
     };
     width as isize
 }
+
+/// A Unicode normalization form, as defined by Unicode Standard Annex #15.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalizationForm {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+impl From<LispObject> for NormalizationForm {
+    fn from(form: LispObject) -> Self {
+        match form {
+            Qnfc => NormalizationForm::Nfc,
+            Qnfd => NormalizationForm::Nfd,
+            Qnfkc => NormalizationForm::Nfkc,
+            Qnfkd => NormalizationForm::Nfkd,
+            _ => unsafe { wrong_choice(list(&[Qnfc, Qnfd, Qnfkc, Qnfkd]), form) },
+        }
+    }
+}
+
+/// Return true if CP is a Unicode character.  Normalization leaves the
+/// other codepoints, such as raw bytes, alone, and never looks across
+/// them, so they split the text into independent runs.
+fn is_unicode(cp: &Codepoint) -> bool {
+    char::try_from(cp.0).is_ok()
+}
+
+fn unicode_chars(run: &[Codepoint]) -> impl Iterator<Item = char> + '_ {
+    run.iter().map(|cp| char::try_from(cp.0).unwrap())
+}
+
+/// Return CPS normalized to FORM.
+pub fn normalize_codepoints(cps: &[Codepoint], form: NormalizationForm) -> Vec<Codepoint> {
+    let mut result = Vec::with_capacity(cps.len());
+    let mut others = cps.iter().filter(|cp| !is_unicode(cp));
+    for run in cps.split(|cp| !is_unicode(cp)) {
+        let chars = unicode_chars(run);
+        match form {
+            NormalizationForm::Nfc => result.extend(chars.nfc().map(Codepoint::from)),
+            NormalizationForm::Nfd => result.extend(chars.nfd().map(Codepoint::from)),
+            NormalizationForm::Nfkc => result.extend(chars.nfkc().map(Codepoint::from)),
+            NormalizationForm::Nfkd => result.extend(chars.nfkd().map(Codepoint::from)),
+        }
+        result.extend(others.next());
+    }
+    result
+}
+
+/// Run the quick check algorithm for FORM on CPS.
+pub fn quick_check_codepoints(cps: &[Codepoint], form: NormalizationForm) -> IsNormalized {
+    cps.split(|cp| !is_unicode(cp))
+        .map(|run| {
+            let chars = unicode_chars(run);
+            match form {
+                NormalizationForm::Nfc => is_nfc_quick(chars),
+                NormalizationForm::Nfd => is_nfd_quick(chars),
+                NormalizationForm::Nfkc => is_nfkc_quick(chars),
+                NormalizationForm::Nfkd => is_nfkd_quick(chars),
+            }
+        })
+        .fold(IsNormalized::Yes, |answer, run_answer| {
+            match (answer, run_answer) {
+                (IsNormalized::No, _) | (_, IsNormalized::No) => IsNormalized::No,
+                (IsNormalized::Maybe, _) | (_, IsNormalized::Maybe) => IsNormalized::Maybe,
+                _ => IsNormalized::Yes,
+            }
+        })
+}
+
+/// Return the characters of STRING for normalization.  The bytes of a
+/// unibyte string are raw bytes, which normalization leaves alone.
+fn normalization_codepoints(string: LispStringRef) -> Vec<Codepoint> {
+    if string.is_multibyte() {
+        string.chars().collect()
+    } else {
+        string
+            .as_slice()
+            .iter()
+            .map(|&b| Codepoint::from_raw(b))
+            .collect()
+    }
+}
+
+/// Make a multibyte string of the characters CPS.
+fn string_from_codepoints(cps: &[Codepoint]) -> LispObject {
+    let mut bytes = Vec::with_capacity(cps.len());
+    let mut buf = [0; MAX_MULTIBYTE_LENGTH];
+    for &cp in cps {
+        let len = cp.write_to(&mut buf);
+        bytes.extend_from_slice(&buf[..len]);
+    }
+    unsafe {
+        make_multibyte_string(
+            bytes.as_ptr() as *const c_char,
+            cps.len() as ptrdiff_t,
+            bytes.len() as ptrdiff_t,
+        )
+    }
+}
+
+/// Return STRING normalized to the Unicode normalization form FORM.
+/// FORM is one of the symbols `nfc', `nfd', `nfkc' and `nfkd'.  NFD
+/// decomposes characters into base characters and combining marks, and
+/// NFC composes them again; NFKD and NFKC also replace compatibility
+/// characters, such as ligatures and full-width forms, by their plain
+/// equivalents.
+///
+/// Characters that are not Unicode characters, such as raw bytes, are
+/// left alone, and no character is composed with one across them.
+/// The result has no text properties.  If STRING is already normalized,
+/// it may be returned itself.
+#[lisp_fn]
+pub fn string_normalize(string: LispStringRef, form: LispObject) -> LispObject {
+    let form = NormalizationForm::from(form);
+    let cps = normalization_codepoints(string);
+    if quick_check_codepoints(&cps, form) == IsNormalized::Yes {
+        return string.into();
+    }
+    let normalized = normalize_codepoints(&cps, form);
+    if normalized == cps {
+        string.into()
+    } else {
+        string_from_codepoints(&normalized)
+    }
+}
+
+/// Return non-nil if STRING is in the Unicode normalization form FORM.
+/// FORM is one of the symbols `nfc', `nfd', `nfkc' and `nfkd'; see
+/// `string-normalize'.
+///
+/// If QUICK is non-nil, only use the quick check algorithm, which is
+/// much faster than normalizing STRING but cannot decide every string:
+/// return t or nil if it can, and `maybe' if it cannot.
+#[lisp_fn(min = "2")]
+pub fn string_normalized_p(string: LispStringRef, form: LispObject, quick: bool) -> LispObject {
+    let form = NormalizationForm::from(form);
+    let cps = normalization_codepoints(string);
+    match quick_check_codepoints(&cps, form) {
+        IsNormalized::Yes => Qt,
+        IsNormalized::No => Qnil,
+        IsNormalized::Maybe if quick => Qmaybe,
+        IsNormalized::Maybe => normalize_codepoints(&cps, form).eq(&cps).into(),
+    }
+}
+
+/// Normalize the text between START and END to the Unicode normalization form FORM.
+/// FORM is one of the symbols `nfc', `nfd', `nfkc' and `nfkd'; see
+/// `string-normalize'.
+/// Only the characters that change are replaced, so text properties and
+/// markers elsewhere in the region are kept.  Return non-nil if the text
+/// changed.
+#[lisp_fn]
+pub fn normalize_region(start: LispObject, end: LispObject, form: LispObject) -> bool {
+    let form = NormalizationForm::from(form);
+    let (start, end) = validate_region_rust(start, end);
+    let text = buffer_substring_no_properties(start.into(), end.into()).force_string();
+    let cps = normalization_codepoints(text);
+    if quick_check_codepoints(&cps, form) == IsNormalized::Yes {
+        return false;
+    }
+    let normalized = normalize_codepoints(&cps, form);
+
+    let prefix = cps
+        .iter()
+        .zip(&normalized)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == cps.len() && prefix == normalized.len() {
+        return false;
+    }
+    let suffix = cps[prefix..]
+        .iter()
+        .rev()
+        .zip(normalized[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let replacement = string_from_codepoints(&normalized[prefix..normalized.len() - suffix]);
+    unsafe {
+        replace_range(
+            start + prefix as isize,
+            end - suffix as isize,
+            replacement,
+            true,
+            false,
+            true,
+            false,
+        )
+    };
+    true
+}
+
+def_lisp_sym!(Qnfc, "nfc");
+def_lisp_sym!(Qnfd, "nfd");
+def_lisp_sym!(Qnfkc, "nfkc");
+def_lisp_sym!(Qnfkd, "nfkd");
+def_lisp_sym!(Qmaybe, "maybe");
+
+include!(concat!(env!("OUT_DIR"), "/multibyte_exports.rs"));
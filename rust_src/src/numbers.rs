@@ -3,11 +3,14 @@
 use std::cmp;
 use std::sync::Mutex;
 
+use num_bigint::BigInt;
+use num_traits::Zero;
 use rand::{rngs::StdRng, FromEntropy, Rng, SeedableRng};
 
 use remacs_macros::lisp_fn;
 
 use crate::{
+    bignum::{float_to_bigint, LispBignumRef},
     hashtable::LispHashTableRef,
     lisp::{LispObject, LispStructuralEqual},
     remacs_sys::{
@@ -141,9 +144,8 @@ impl LispObject {
         }
     }
 
-    /// TODO: Bignum support? (Current Emacs doesn't have it)
     pub fn is_integer(self) -> bool {
-        self.is_fixnum()
+        self.is_fixnum() || self.is_bignum()
     }
 }
 
@@ -151,6 +153,7 @@ impl LispObject {
 #[derive(Clone, Copy)]
 pub enum LispNumber {
     Fixnum(EmacsInt),
+    Bignum(LispBignumRef),
     Float(EmacsDouble),
 }
 
@@ -185,12 +188,40 @@ pub fn check_range(num: impl Into<EmacsInt>, from: impl Into<EmacsInt>, to: impl
 }
 
 impl LispNumber {
+    /// Return the number as an `EmacsInt`.  Floats are truncated, and
+    /// bignums saturate to the smallest or largest `EmacsInt`, which is
+    /// out of range for any buffer position.
     pub fn to_fixnum(&self) -> EmacsInt {
         match *self {
             LispNumber::Fixnum(v) => v,
+            LispNumber::Bignum(b) => {
+                if b.is_negative() {
+                    EmacsInt::min_value()
+                } else {
+                    EmacsInt::max_value()
+                }
+            }
             LispNumber::Float(v) => v as EmacsInt,
         }
     }
+
+    pub fn to_float(&self) -> EmacsDouble {
+        match *self {
+            LispNumber::Fixnum(v) => v as EmacsDouble,
+            LispNumber::Bignum(b) => b.to_float(),
+            LispNumber::Float(v) => v,
+        }
+    }
+
+    /// Return the number as a `BigInt`.  Floats are truncated, and
+    /// infinities and NaNs become zero.
+    pub fn to_bigint(&self) -> BigInt {
+        match *self {
+            LispNumber::Fixnum(v) => BigInt::from(v),
+            LispNumber::Bignum(b) => b.to_bigint(),
+            LispNumber::Float(v) => float_to_bigint(v.trunc()).unwrap_or_else(BigInt::zero),
+        }
+    }
 }
 
 impl LispStructuralEqual for EmacsInt {
@@ -222,6 +253,8 @@ impl From<LispObject> for Option<LispNumber> {
     fn from(o: LispObject) -> Self {
         if let Some(n) = o.as_fixnum() {
             Some(LispNumber::Fixnum(n))
+        } else if let Some(b) = o.as_bignum() {
+            Some(LispNumber::Bignum(b))
         } else if let Some(f) = o.as_float() {
             Some(LispNumber::Float(f))
         } else if let Some(m) = o.as_marker() {
@@ -235,7 +268,8 @@ impl From<LispObject> for Option<LispNumber> {
 impl From<LispNumber> for LispObject {
     fn from(n: LispNumber) -> Self {
         match n {
-            LispNumber::Fixnum(v) => v.into(),
+            LispNumber::Fixnum(v) => Self::from_integer(v),
+            LispNumber::Bignum(b) => b.into(),
             LispNumber::Float(v) => Self::from_float(v),
         }
     }
@@ -243,7 +277,7 @@ impl From<LispNumber> for LispObject {
 
 impl LispObject {
     pub fn is_number(self) -> bool {
-        self.is_fixnum() || self.is_float()
+        self.is_integer() || self.is_float()
     }
 
     /*
@@ -279,6 +313,18 @@ pub fn integerp(object: LispObject) -> bool {
     object.is_integer()
 }
 
+/// Return t if OBJECT is an integer that fits in a fixnum.
+#[lisp_fn]
+pub fn fixnump(object: LispObject) -> bool {
+    object.is_fixnum()
+}
+
+/// Return t if OBJECT is an integer that does not fit in a fixnum.
+#[lisp_fn]
+pub fn bignump(object: LispObject) -> bool {
+    object.is_bignum()
+}
+
 /// Return t if OBJECT is an integer or a marker (editor pointer).
 #[lisp_fn]
 pub fn integer_or_marker_p(object: LispObject) -> bool {
@@ -288,7 +334,7 @@ pub fn integer_or_marker_p(object: LispObject) -> bool {
 /// Return t if OBJECT is a non-negative integer.
 #[lisp_fn]
 pub fn natnump(object: LispObject) -> bool {
-    object.is_natnum()
+    object.is_natnum() || object.as_bignum().map_or(false, |b| !b.is_negative())
 }
 
 /// Return t if OBJECT is a number (floating point or integer).
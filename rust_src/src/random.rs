@@ -0,0 +1,340 @@
+//! Seedable random number generators.
+//!
+//! Unlike `random', which draws from one generator shared by the whole
+//! session, a random generator is an object of its own that yields a
+//! reproducible sequence.  The algorithm is fixed so that a seed gives
+//! the same sequence in every build:
+//!
+//! - The generator is xoshiro256** by Blackman and Vigna, whose state
+//!   is four 64-bit words.
+//! - A seed is first reduced to a 64-bit value: an integer modulo 2^64,
+//!   or the 64-bit FNV-1a hash of the bytes of a string.  The four
+//!   state words are then the first four outputs of SplitMix64 started
+//!   at that value.
+//! - An integer in a range of size N is drawn by taking as many 64-bit
+//!   outputs as N needs bits, least significant first, masking them to
+//!   that many bits and retrying until the result is less than N.
+//! - A float is the top 53 bits of one output, scaled to [0, 1).
+//! - A sequence is shuffled with the Fisher-Yates algorithm, from the
+//!   last element down, each swap drawing an index as above.
+//!
+//! A generator is a record of type `random-generator' holding the state
+//! words as 32-bit halves, least significant first, so it can be printed
+//! and read back and copied with `copy-sequence'.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{Signed, ToPrimitive};
+
+use remacs_macros::lisp_fn;
+
+use crate::{
+    alloc::record,
+    fns::{append, concat, vconcat},
+    lisp::LispObject,
+    remacs_sys::{Qnil, Qrandom_generator, Qrandom_generator_p},
+    vectors::LispVectorlikeSlotsRef,
+};
+
+/// The number of 32-bit slots holding the state in a generator record.
+const STATE_SLOTS: usize = 8;
+
+/// The xoshiro256** generator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    fn from_seed(seed: u64) -> Self {
+        let mut x = seed;
+        let mut s = [0; 4];
+        for word in s.iter_mut() {
+            *word = splitmix64(&mut x);
+        }
+        Self { s }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Return a float uniformly distributed in [0, 1).
+    fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Return an integer uniformly distributed in [0, N), for a positive N.
+    fn below(&mut self, n: &BigUint) -> BigUint {
+        let bits = n.bits();
+        let words = (bits + 63) / 64;
+        let top_bits = bits - (words - 1) * 64;
+        loop {
+            let mut digits = Vec::with_capacity(words * 2);
+            for i in 0..words {
+                let mut word = self.next_u64();
+                if i == words - 1 && top_bits < 64 {
+                    word &= (1 << top_bits) - 1;
+                }
+                digits.push(word as u32);
+                digits.push((word >> 32) as u32);
+            }
+            let candidate = BigUint::new(digits);
+            if &candidate < n {
+                return candidate;
+            }
+        }
+    }
+
+    /// Return an index uniformly distributed in [0, N), for a positive N.
+    fn below_usize(&mut self, n: usize) -> usize {
+        let bits = 64 - (n as u64).leading_zeros();
+        let mask = if bits == 64 {
+            u64::max_value()
+        } else {
+            (1 << bits) - 1
+        };
+        loop {
+            let candidate = self.next_u64() & mask;
+            if candidate < n as u64 {
+                return candidate as usize;
+            }
+        }
+    }
+}
+
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Reduce SEED, an integer or a string, to the 64-bit value the state is
+/// derived from.
+fn seed_value(seed: LispObject) -> u64 {
+    if let Some(s) = seed.as_string() {
+        fnv1a64(s.as_slice())
+    } else {
+        let n = seed.as_bigint_or_error();
+        // Two's complement modulo 2^64.
+        let modulus = BigInt::from(1) << 64;
+        let mut n = n % &modulus;
+        if n.is_negative() {
+            n += modulus;
+        }
+        n.to_u64().unwrap()
+    }
+}
+
+/// Return the record slots of OBJECT if it is a random generator.
+fn as_generator(object: LispObject) -> Option<LispVectorlikeSlotsRef> {
+    object
+        .as_vectorlike()
+        .and_then(|v| v.as_record())
+        .filter(|r| {
+            r.len() == STATE_SLOTS + 1
+                && r.get(0).eq(Qrandom_generator)
+                && r.as_slice()[1..].iter().all(|s| {
+                    s.as_fixnum()
+                        .map_or(false, |n| 0 <= n && n <= u32::max_value().into())
+                })
+        })
+}
+
+fn generator_slots(generator: LispObject) -> LispVectorlikeSlotsRef {
+    as_generator(generator).unwrap_or_else(|| wrong_type!(Qrandom_generator_p, generator))
+}
+
+fn load_state(generator: LispObject) -> Xoshiro256 {
+    let slots = generator_slots(generator);
+    let halves = &slots.as_slice()[1..];
+    let mut s = [0; 4];
+    for (i, word) in s.iter_mut().enumerate() {
+        let low = halves[2 * i].as_fixnum().unwrap() as u64;
+        let high = halves[2 * i + 1].as_fixnum().unwrap() as u64;
+        *word = low | high << 32;
+    }
+    Xoshiro256 { s }
+}
+
+fn store_state(generator: LispObject, state: &Xoshiro256) {
+    let mut slots = generator_slots(generator);
+    for (i, &word) in state.s.iter().enumerate() {
+        slots.set(2 * i + 1, LispObject::from(word & 0xffff_ffff));
+        slots.set(2 * i + 2, LispObject::from(word >> 32));
+    }
+}
+
+/// Run F on the state of GENERATOR and save the state it leaves behind.
+fn with_state<T>(generator: LispObject, f: impl FnOnce(&mut Xoshiro256) -> T) -> T {
+    let mut state = load_state(generator);
+    let result = f(&mut state);
+    store_state(generator, &state);
+    result
+}
+
+/// Return a new random generator.
+/// SEED may be an integer or a string; the same seed always gives the
+/// same sequence of numbers, in every session and every build.  If SEED
+/// is nil or omitted, seed the generator from the system's entropy pool.
+///
+/// Drawing numbers from the generator with `random-generator-integer',
+/// `random-generator-float' and `random-generator-shuffle' does not
+/// affect `random', nor any other generator.  The algorithm is
+/// xoshiro256**, with its state initialized by SplitMix64 from SEED.
+#[lisp_fn(min = "0")]
+pub fn make_random_generator(seed: LispObject) -> LispObject {
+    let seed = if seed.is_nil() {
+        rand::random()
+    } else {
+        seed_value(seed)
+    };
+    let mut slots = [LispObject::from(0); STATE_SLOTS + 1];
+    slots[0] = Qrandom_generator;
+    let generator = record(&mut slots);
+    store_state(generator, &Xoshiro256::from_seed(seed));
+    generator
+}
+
+/// Return t if OBJECT is a random generator.
+#[lisp_fn]
+pub fn random_generator_p(object: LispObject) -> bool {
+    as_generator(object).is_some()
+}
+
+def_lisp_sym!(Qrandom_generator_p, "random-generator-p");
+
+/// Return a copy of the random generator GENERATOR.
+/// The copy yields the same sequence of numbers as GENERATOR would, and
+/// drawing from one does not affect the other.
+#[lisp_fn]
+pub fn copy_random_generator(generator: LispObject) -> LispObject {
+    record(generator_slots(generator).as_mut_slice())
+}
+
+/// Return a random integer N with LOW <= N < HIGH, drawn from GENERATOR.
+/// LOW and HIGH are integers, and HIGH must be greater than LOW.
+#[lisp_fn]
+pub fn random_generator_integer(
+    generator: LispObject,
+    low: LispObject,
+    high: LispObject,
+) -> LispObject {
+    let low_value = low.as_bigint_or_error();
+    let range = high.as_bigint_or_error() - &low_value;
+    if !range.is_positive() {
+        args_out_of_range!(low, high);
+    }
+    let range = range.to_biguint().unwrap();
+    let n = with_state(generator, |state| state.below(&range));
+    (low_value + BigInt::from_biguint(Sign::Plus, n)).into()
+}
+
+/// Return a random float N with 0.0 <= N < 1.0, drawn from GENERATOR.
+#[lisp_fn]
+pub fn random_generator_float(generator: LispObject) -> LispObject {
+    LispObject::from_float(with_state(generator, Xoshiro256::next_float))
+}
+
+/// Return a shuffled copy of SEQUENCE, using GENERATOR.
+/// SEQUENCE may be a list, a vector or a string, and the result is a new
+/// sequence of the same kind.  Every order is equally likely.
+#[lisp_fn]
+pub fn random_generator_shuffle(generator: LispObject, sequence: LispObject) -> LispObject {
+    // Check GENERATOR before copying.
+    generator_slots(generator);
+    let shuffled = vconcat(&mut [sequence]);
+    let mut elements = shuffled.force_vectorlike_slots();
+    with_state(generator, |state| {
+        let elements = elements.as_mut_slice();
+        for i in (1..elements.len()).rev() {
+            elements.swap(i, state.below_usize(i + 1));
+        }
+    });
+
+    if sequence.is_string() {
+        concat(&mut [shuffled])
+    } else if sequence.is_vector() {
+        shuffled
+    } else {
+        append(&mut [shuffled, Qnil])
+    }
+}
+
+def_lisp_sym!(Qrandom_generator, "random-generator");
+
+include!(concat!(env!("OUT_DIR"), "/random_exports.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splitmix64() {
+        // Reference values from the published implementation.
+        let mut x = 1234567;
+        assert_eq!(splitmix64(&mut x), 6457827717110365317);
+        assert_eq!(splitmix64(&mut x), 3203168211198807973);
+    }
+
+    #[test]
+    fn test_xoshiro256() {
+        // Reference values from the published implementation.
+        let mut state = Xoshiro256 { s: [1, 2, 3, 4] };
+        let expected = [
+            11520,
+            0,
+            1509978240,
+            1215971899390074240,
+            1216172134540287360,
+            607988272756665600,
+        ];
+        for &e in expected.iter() {
+            assert_eq!(state.next_u64(), e);
+        }
+    }
+
+    #[test]
+    fn test_below() {
+        let mut state = Xoshiro256::from_seed(42);
+        let n = BigUint::from(10u32);
+        for _ in 0..1000 {
+            assert!(state.below(&n) < n);
+            assert!(state.below_usize(3) < 3);
+        }
+        let n = BigUint::from(1u32) << 100;
+        assert!((0..100).any(|_| state.below(&n).bits() > 64));
+        assert_eq!(state.below_usize(1), 0);
+    }
+
+    #[test]
+    fn test_next_float() {
+        let mut state = Xoshiro256::from_seed(0);
+        for _ in 0..1000 {
+            let f = state.next_float();
+            assert!(0.0 <= f && f < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_fnv1a64() {
+        assert_eq!(fnv1a64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a64(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -113,8 +113,6 @@ extern "C" {
         filename: LispObject,
         id_format: LispObject,
     ) -> LispObject;
-    #[cfg(unix)]
-    pub fn filemode_string(f: LispObject) -> LispObject;
 
     pub fn unchain_both(b: *mut Lisp_Buffer, ov: LispObject);
     pub fn emacs_get_tty_pgrp(p: *mut Lisp_Process) -> libc::pid_t;
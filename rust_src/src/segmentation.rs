@@ -0,0 +1,271 @@
+//! Text segmentation.
+//!
+//! Boundaries between extended grapheme clusters and between words, as
+//! defined by Unicode Standard Annex #29.  A grapheme cluster is what a
+//! user perceives as one character: a base character with its combining
+//! marks, an emoji ZWJ sequence, a pair of regional indicators forming a
+//! flag, a Hangul syllable spelled with conjoining jamo, or CR LF.
+//!
+//! Characters that are not Unicode characters, such as raw bytes, are
+//! segmented as if they were U+FFFD, so each of them is a cluster of its
+//! own.  Boundaries are character positions: offsets into a string, or
+//! buffer positions.
+
+use std::cmp::max;
+use std::convert::TryFrom;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use remacs_macros::lisp_fn;
+
+use crate::{
+    buffers::validate_region_rust,
+    editfns::buffer_substring_no_properties,
+    lisp::LispObject,
+    lists::list,
+    multibyte::{Codepoint, LispStringRef},
+    remacs_sys::EmacsInt,
+    threads::ThreadState,
+};
+
+/// The kinds of segment UAX #29 defines boundaries for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    Grapheme,
+    Word,
+}
+
+/// Return C as a Rust character for segmentation.
+pub fn segmentation_char(c: Codepoint) -> char {
+    char::try_from(u32::from(c)).unwrap_or('\u{FFFD}')
+}
+
+/// Return the text of STRING as a Rust string for segmentation.
+/// Non-Unicode characters become U+FFFD, so the result has as many
+/// characters as STRING.
+pub fn segmentation_text(string: LispStringRef) -> String {
+    if string.is_multibyte() {
+        string.chars().map(segmentation_char).collect()
+    } else {
+        string
+            .as_slice()
+            .iter()
+            .map(|&b| if b < 0x80 { char::from(b) } else { '\u{FFFD}' })
+            .collect()
+    }
+}
+
+/// Return the boundaries between segments of kind SEGMENT in TEXT, as
+/// character offsets in increasing order.  Both 0 and the length of TEXT
+/// are included.
+pub fn segment_boundaries(text: &str, segment: Segment) -> Vec<usize> {
+    let segments: Box<dyn Iterator<Item = &str>> = match segment {
+        Segment::Grapheme => Box::new(text.graphemes(true)),
+        Segment::Word => Box::new(text.split_word_bounds()),
+    };
+    let mut boundaries = vec![0];
+    let mut offset = 0;
+    for s in segments {
+        offset += s.chars().count();
+        boundaries.push(offset);
+    }
+    boundaries
+}
+
+/// Return the number of characters in the first grapheme cluster of
+/// the text CHARS yields, reading no more of it than needed.
+pub fn first_grapheme_len(mut chars: impl Iterator<Item = char>) -> usize {
+    let mut text = String::new();
+    let mut len = 0;
+    let mut wanted = 8;
+    loop {
+        let before = len;
+        for c in chars.by_ref().take(wanted) {
+            text.push(c);
+            len += 1;
+        }
+        let first = text.graphemes(true).next().map_or(0, |g| g.chars().count());
+        // The cluster is complete once a character follows it, since a
+        // boundary never depends on the text after the next character.
+        if first < len || len - before < wanted {
+            return first;
+        }
+        wanted *= 2;
+    }
+}
+
+fn string_boundaries(string: LispStringRef, segment: Segment) -> LispObject {
+    let boundaries: Vec<LispObject> = segment_boundaries(&segmentation_text(string), segment)
+        .into_iter()
+        .map(LispObject::from)
+        .collect();
+    list(&boundaries)
+}
+
+/// Return the boundaries between segments of kind SEGMENT in the
+/// current buffer's text from START to END, as buffer positions.
+/// START and END are treated as boundaries.
+fn region_boundaries(start: isize, end: isize, segment: Segment) -> Vec<isize> {
+    let text = buffer_substring_no_properties(start.into(), end.into()).force_string();
+    segment_boundaries(&segmentation_text(text), segment)
+        .into_iter()
+        .map(|offset| start + offset as isize)
+        .collect()
+}
+
+/// Return the characters of the current buffer from FROM to TO for
+/// segmentation, converted as `segmentation_text` converts a string.
+fn buffer_chars(from: isize, to: isize) -> impl Iterator<Item = char> {
+    let buffer = ThreadState::current_buffer_unchecked();
+    let multibyte = buffer.multibyte_characters_enabled();
+    let mut byte = buffer.charpos_to_bytepos(from);
+    (from..to).map(move |_| {
+        let c = buffer.fetch_char(byte);
+        byte = buffer.inc_pos(byte);
+        if multibyte || u32::from(c) < 0x80 {
+            segmentation_char(c)
+        } else {
+            '\u{FFFD}'
+        }
+    })
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+/// Return the grapheme cluster boundary before POS in the current
+/// buffer, which must be after BEGV.  Only a window of text before POS
+/// is segmented, and it is widened until its start cannot affect the
+/// result: a whole cluster must precede the one found, and so must a
+/// character that is not a regional indicator, since regional
+/// indicators pair up from the start of their run.
+fn previous_grapheme_boundary(pos: isize, begv: isize) -> isize {
+    let mut window = 16;
+    loop {
+        let start = max(begv, pos - window);
+        let text: String = buffer_chars(start, pos).collect();
+        let boundaries = segment_boundaries(&text, Segment::Grapheme);
+        let last = boundaries[boundaries.len() - 2];
+        if start == begv
+            || (boundaries[1] < last && text.chars().take(last).any(|c| !is_regional_indicator(c)))
+        {
+            return start + last as isize;
+        }
+        window *= 2;
+    }
+}
+
+/// Return the position COUNT grapheme clusters after POS in the current
+/// buffer, or before it if COUNT is negative, and the number of clusters
+/// that could not be moved over before reaching the edge of the
+/// accessible portion.  The sign of that number is the sign of COUNT.
+pub fn forward_grapheme_position(mut pos: isize, mut count: EmacsInt) -> (isize, EmacsInt) {
+    let buffer = ThreadState::current_buffer_unchecked();
+    while count > 0 && pos < buffer.zv {
+        pos += first_grapheme_len(buffer_chars(pos, buffer.zv)) as isize;
+        count -= 1;
+    }
+    while count < 0 && pos > buffer.begv {
+        pos = previous_grapheme_boundary(pos, buffer.begv);
+        count += 1;
+    }
+    (pos, count)
+}
+
+/// Return the boundaries between grapheme clusters in STRING.
+/// The value is a list of character offsets in increasing order,
+/// starting with 0 and ending with the length of STRING.  A grapheme
+/// cluster is a sequence of characters that is perceived as a single
+/// character, such as a base character with its combining marks, an
+/// emoji ZWJ sequence or a flag, as defined by Unicode Standard Annex #29.
+#[lisp_fn]
+pub fn string_grapheme_boundaries(string: LispStringRef) -> LispObject {
+    string_boundaries(string, Segment::Grapheme)
+}
+
+/// Return the word boundaries in STRING.
+/// The value is a list of character offsets in increasing order,
+/// starting with 0 and ending with the length of STRING.  Boundaries are
+/// as defined by Unicode Standard Annex #29, which puts them around words
+/// and numbers, between other characters that are not spaces, and around
+/// runs of spaces.
+#[lisp_fn]
+pub fn string_word_boundaries(string: LispStringRef) -> LispObject {
+    string_boundaries(string, Segment::Word)
+}
+
+/// Return the boundaries between grapheme clusters from START to END.
+/// The value is a list of buffer positions in increasing order, starting
+/// with the smaller of START and END and ending with the larger.  See
+/// `string-grapheme-boundaries'.
+#[lisp_fn]
+pub fn grapheme_boundaries(start: LispObject, end: LispObject) -> LispObject {
+    let (start, end) = validate_region_rust(start, end);
+    let boundaries: Vec<LispObject> = region_boundaries(start, end, Segment::Grapheme)
+        .into_iter()
+        .map(LispObject::from)
+        .collect();
+    list(&boundaries)
+}
+
+/// Return the word boundaries from START to END.
+/// The value is a list of buffer positions in increasing order, starting
+/// with the smaller of START and END and ending with the larger.  See
+/// `string-word-boundaries'.
+#[lisp_fn]
+pub fn word_boundaries(start: LispObject, end: LispObject) -> LispObject {
+    let (start, end) = validate_region_rust(start, end);
+    let boundaries: Vec<LispObject> = region_boundaries(start, end, Segment::Word)
+        .into_iter()
+        .map(LispObject::from)
+        .collect();
+    list(&boundaries)
+}
+
+include!(concat!(env!("OUT_DIR"), "/segmentation_exports.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grapheme_boundaries() {
+        let b = |s| segment_boundaries(s, Segment::Grapheme);
+        assert_eq!(b(""), vec![0]);
+        assert_eq!(b("abc"), vec![0, 1, 2, 3]);
+        // Combining mark.
+        assert_eq!(b("e\u{301}x"), vec![0, 2, 3]);
+        // CR LF.
+        assert_eq!(b("a\r\nb"), vec![0, 1, 3, 4]);
+        // Emoji ZWJ sequence.
+        assert_eq!(b("\u{1F469}\u{200D}\u{1F4BB}!"), vec![0, 3, 4]);
+        // Two flags.
+        assert_eq!(b("\u{1F1EB}\u{1F1F7}\u{1F1E9}\u{1F1EA}"), vec![0, 2, 4]);
+        // Conjoining jamo.
+        assert_eq!(b("\u{1100}\u{1161}\u{11A8}"), vec![0, 3]);
+        // Replacement characters stand alone.
+        assert_eq!(b("a\u{FFFD}\u{301}"), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_first_grapheme_len() {
+        let len = |s: &str| first_grapheme_len(s.chars());
+        assert_eq!(len(""), 0);
+        assert_eq!(len("ab"), 1);
+        assert_eq!(len("\u{1F1EB}\u{1F1F7}\u{1F1E9}"), 2);
+        let zalgo: String = "a"
+            .chars()
+            .chain(std::iter::repeat('\u{301}').take(40))
+            .collect();
+        assert_eq!(len(&zalgo), 41);
+        assert_eq!(len(&(zalgo.clone() + "b")), 41);
+    }
+
+    #[test]
+    fn test_word_boundaries() {
+        let b = |s| segment_boundaries(s, Segment::Word);
+        assert_eq!(b("hello, world"), vec![0, 5, 6, 7, 12]);
+        assert_eq!(b("can't  3.14"), vec![0, 5, 7, 11]);
+    }
+}
@@ -180,6 +180,12 @@ pub fn clear_string(mut string: LispStringRef) {
 /// width of a multibyte character in STRING, only the base leading-code is
 /// considered; the validity of the following bytes is not checked.  Tabs in
 /// STRING are always taken to occupy `tab-width' columns.
+///
+/// Each grapheme cluster is measured as a whole, so a flag, an emoji
+/// sequence joined with ZERO WIDTH JOINER or an emoji followed by a
+/// variation selector counts as it is displayed by terminals.  Characters
+/// of ambiguous East Asian width take `east-asian-ambiguous-width' columns
+/// when that is set.
 #[lisp_fn]
 pub fn string_width(string: LispStringRef) -> usize {
     string.width()
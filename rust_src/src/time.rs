@@ -7,12 +7,16 @@ use std::ptr;
 use libc::timespec as c_timespec;
 use libc::{c_int, c_long, time_t};
 
-use remacs_lib::current_timespec;
+use remacs_lib::{boottime_timespec, current_timespec, monotonic_timespec};
 use remacs_macros::lisp_fn;
 
 use crate::{
     lisp::LispObject,
-    numbers::MOST_NEGATIVE_FIXNUM,
+    lists::nth,
+    multibyte::LispStringRef,
+    numbers::{MOST_NEGATIVE_FIXNUM, MOST_POSITIVE_FIXNUM},
+    remacs_sys::Fdecode_time,
+    remacs_sys::Qt,
     remacs_sys::{lisp_time, EmacsDouble, EmacsInt},
 };
 
@@ -149,8 +153,25 @@ fn make_lisp_time_1(t: c_timespec) -> LispObject {
     list!(hi_time(s), lo_time(s), ns / 1_000, ns % 1_000 * 1_000)
 }
 
+/// Split the time TICKS/HZ seconds, where HZ is positive, into the
+/// components (HIGH LOW USEC PSEC) of a Lisp time list, truncating
+/// toward minus infinity.
+fn ticks_components(ticks: EmacsInt, hz: EmacsInt) -> (EmacsInt, EmacsInt, EmacsInt, EmacsInt) {
+    let seconds = ticks.div_euclid(hz);
+    let ps = i128::from(ticks.rem_euclid(hz)) * 1_000_000_000_000 / i128::from(hz);
+    let ps = ps as EmacsInt;
+    (
+        seconds >> LO_TIME_BITS,
+        seconds & ((1 << LO_TIME_BITS) - 1),
+        ps / 1_000_000,
+        ps % 1_000_000,
+    )
+}
+
 /// Decode a Lisp list `SPECIFIED_TIME` that represents a time.
 /// Set `*PHIGH`, `*PLOW`, `*PUSEC`, `*PPSEC` to its parts; do not check their values.
+/// A cons (TICKS . HZ) of two integers is split into the parts of the
+/// equivalent (HIGH LOW USEC PSEC) list.
 /// Return 2, 3, or 4 to indicate the effective length of `SPECIFIED_TIME`
 /// if successful, 0 if unsuccessful.
 #[no_mangle]
@@ -170,6 +191,18 @@ pub unsafe extern "C" fn disassemble_lisp_time(
     let mut len = 4;
 
     if let Some((car, cdr)) = specified_time.into() {
+        if let (Some(ticks), Some(hz)) = (car.as_fixnum(), cdr.as_fixnum()) {
+            if hz <= 0 {
+                return 0;
+            }
+            let (h, l, us, ps) = ticks_components(ticks, hz);
+            *phigh = LispObject::from(h);
+            *plow = LispObject::from(l);
+            *pusec = LispObject::from(us);
+            *ppsec = LispObject::from(ps);
+            return 4;
+        }
+
         high = car;
         low = cdr;
 
@@ -426,13 +459,39 @@ pub fn current_time() -> LispObject {
     make_lisp_time_1(current_timespec())
 }
 
+/// The number of ticks per second of `current-monotonic-time'.
+const MONOTONIC_HZ: EmacsInt = 1_000_000_000;
+
+/// Return the time of a monotonic clock, as a Lisp time value
+/// (TICKS . HZ) meaning TICKS/HZ seconds.
+/// Unlike `current-time', this clock is not affected by changes to the
+/// system time, so it is suitable for measuring elapsed time.  Its
+/// origin is unspecified; only differences between its values are
+/// meaningful.
+///
+/// If BOOT is non-nil, use a clock that also counts the time the system
+/// was suspended, and whose origin is the time the system booted.  Where
+/// no such clock exists, BOOT is ignored.
+#[lisp_fn(min = "0")]
+pub fn current_monotonic_time(boot: bool) -> LispObject {
+    let ts = if boot {
+        boottime_timespec()
+    } else {
+        monotonic_timespec()
+    };
+    make_lisp_ticks(
+        i128::from(ts.tv_sec) * i128::from(MONOTONIC_HZ) + i128::from(ts.tv_nsec),
+        MONOTONIC_HZ,
+    )
+}
+
 /// Return the current time, as a float number of seconds since the
 /// epoch.  If TIME is given, it is the time to convert to float
 /// instead of the current time.  The argument should have the form
 /// (HIGH LOW) or (HIGH LOW USEC) or (HIGH LOW USEC PSEC).  Thus, you
 /// can use times from `current-time' and from `file-attributes'.
-/// TIME can also have the form (HIGH . LOW), but this is considered
-/// obsolete.
+/// TIME can also have the form (TICKS . HZ), meaning TICKS/HZ seconds,
+/// as returned by `parse-rfc3339-time'.
 ///
 /// WARNING: Since the result is floating point, it may not be exact.
 /// If precise time stamps are required, use either `current-time',
@@ -456,4 +515,450 @@ pub fn float_time(time: LispObject) -> EmacsDouble {
     t
 }
 
+/// Make a Lisp time value (TICKS . HZ), signaling an error if TICKS
+/// does not fit in a fixnum.
+pub fn make_lisp_ticks(ticks: i128, hz: EmacsInt) -> LispObject {
+    if ticks < i128::from(MOST_NEGATIVE_FIXNUM) || ticks > i128::from(MOST_POSITIVE_FIXNUM) {
+        time_overflow();
+    }
+    LispObject::cons(ticks as EmacsInt, hz)
+}
+
+/// Return the number of days from 1970-01-01 to the proleptic
+/// Gregorian date YEAR-MONTH-DAY.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Return the date (YEAR MONTH DAY) that is DAYS days after 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (
+        if month <= 2 {
+            yoe + era * 400 + 1
+        } else {
+            yoe + era * 400
+        },
+        month,
+        day,
+    )
+}
+
+pub(crate) fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Return the ISO 8601 day of the week of DAYS, a count of days since
+/// 1970-01-01, from 0 for Monday to 6 for Sunday.
+fn iso_weekday(days: i64) -> i64 {
+    (days + 3).rem_euclid(7)
+}
+
+/// Return the day number of the Monday that starts ISO week 1 of YEAR,
+/// the week that contains January 4th.
+fn iso_week_one(year: i64) -> i64 {
+    let jan4 = days_from_civil(year, 1, 4);
+    jan4 - iso_weekday(jan4)
+}
+
+/// The maximum number of fraction digits kept by `parse-rfc3339-time'.
+const RFC3339_MAX_DIGITS: u32 = 9;
+
+/// A timestamp parsed by `parse_rfc3339`: SECONDS since the epoch plus
+/// FRACTION / 10^DIGITS seconds.
+#[derive(Debug, PartialEq)]
+struct Rfc3339Time {
+    seconds: i64,
+    fraction: i64,
+    digits: u32,
+}
+
+/// A cursor over the bytes of a timestamp being parsed.
+struct Rfc3339Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Rfc3339Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, pred: impl Fn(u8) -> bool) -> Option<u8> {
+        let c = self.peek().filter(|&c| pred(c))?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        self.eat(|b| b == c).map(|_| ())
+    }
+
+    /// Read exactly N decimal digits.
+    fn digits(&mut self, n: usize) -> Option<i64> {
+        let digits = self.bytes.get(self.pos..self.pos + n)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        self.pos += n;
+        Some(digits.iter().fold(0, |v, &d| v * 10 + i64::from(d - b'0')))
+    }
+
+    /// Read the number of days since the epoch of a calendar date
+    /// YYYY-MM-DD, a week date YYYY-Www-D or an ordinal date YYYY-DDD.
+    fn date(&mut self) -> Option<i64> {
+        let year = self.digits(4)?;
+        self.expect(b'-')?;
+        if self.expect(b'W').is_some() {
+            let week = self.digits(2)?;
+            self.expect(b'-')?;
+            let weekday = self.digits(1)?;
+            let weeks = (iso_week_one(year + 1) - iso_week_one(year)) / 7;
+            if !(1..=weeks).contains(&week) || !(1..=7).contains(&weekday) {
+                return None;
+            }
+            return Some(iso_week_one(year) + (week - 1) * 7 + weekday - 1);
+        }
+
+        let start = self.pos;
+        if let (Some(ordinal), false) = (self.digits(3), self.peek() == Some(b'-')) {
+            let days = if is_leap_year(year) { 366 } else { 365 };
+            if !(1..=days).contains(&ordinal) {
+                return None;
+            }
+            return Some(days_from_civil(year, 1, 1) + ordinal - 1);
+        }
+
+        self.pos = start;
+        let month = self.digits(2)?;
+        self.expect(b'-')?;
+        let day = self.digits(2)?;
+        if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+            return None;
+        }
+        Some(days_from_civil(year, month, day))
+    }
+
+    /// Read a time of day HH:MM[:SS[.FFF]], returning the seconds since
+    /// midnight, the fraction and its number of digits.
+    fn time(&mut self) -> Option<(i64, i64, u32)> {
+        let hour = self.digits(2)?;
+        self.expect(b':')?;
+        let minute = self.digits(2)?;
+        let (mut second, mut fraction, mut digits) = (0, 0, 0);
+        if self.expect(b':').is_some() {
+            second = self.digits(2)?;
+            if self.eat(|c| c == b'.' || c == b',').is_some() {
+                let start = self.pos;
+                while let Some(d) = self.eat(|c| c.is_ascii_digit()) {
+                    if digits < RFC3339_MAX_DIGITS {
+                        fraction = fraction * 10 + i64::from(d - b'0');
+                        digits += 1;
+                    }
+                }
+                if self.pos == start {
+                    return None;
+                }
+            }
+        }
+        // A leap second, 60, is accepted and counts as the first second
+        // of the next minute.
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        Some((hour * 3600 + minute * 60 + second, fraction, digits))
+    }
+
+    /// Read a zone designator, Z or +HH:MM, returning its offset in
+    /// seconds east of UTC.  The colon and minutes may be omitted.
+    fn zone(&mut self) -> Option<i64> {
+        if self.eat(|c| c == b'Z' || c == b'z').is_some() {
+            return Some(0);
+        }
+        let sign = match self.eat(|c| c == b'+' || c == b'-')? {
+            b'-' => -1,
+            _ => 1,
+        };
+        let hour = self.digits(2)?;
+        let minute = if self.peek().is_none() {
+            0
+        } else {
+            self.expect(b':');
+            self.digits(2)?
+        };
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        Some(sign * (hour * 3600 + minute * 60))
+    }
+}
+
+/// Parse the RFC 3339 or ISO 8601 timestamp BYTES.  If it has no zone
+/// designator, use DEFAULT_OFFSET, or fail if that is `None`.
+fn parse_rfc3339(bytes: &[u8], default_offset: Option<i64>) -> Option<Rfc3339Time> {
+    let mut parser = Rfc3339Parser { bytes, pos: 0 };
+    let days = parser.date()?;
+    parser.eat(|c| c == b'T' || c == b't' || c == b' ')?;
+    let (seconds, fraction, digits) = parser.time()?;
+    let offset = if parser.peek().is_none() {
+        default_offset?
+    } else {
+        parser.zone()?
+    };
+    if parser.peek().is_some() {
+        return None;
+    }
+    Some(Rfc3339Time {
+        seconds: days * 86400 + seconds - offset,
+        fraction,
+        digits,
+    })
+}
+
+/// Format the time SECONDS + NANOS / 10^9 since the epoch as an RFC 3339
+/// timestamp with DIGITS fraction digits, in the zone OFFSET seconds
+/// east of UTC, or in UTC with a `Z' designator if OFFSET is `None`.
+/// OFFSET is truncated to whole minutes.
+fn format_rfc3339(seconds: i64, nanos: i64, digits: u32, offset: Option<i64>) -> String {
+    let offset_minutes = offset.map(|o| o / 60);
+    let local = seconds + offset_minutes.unwrap_or(0) * 60;
+    let (year, month, day) = civil_from_days(local.div_euclid(86400));
+    let time = local.rem_euclid(86400);
+
+    let mut result = if year < 0 {
+        format!("-{:04}", -year)
+    } else {
+        format!("{:04}", year)
+    };
+    result.push_str(&format!(
+        "-{:02}-{:02}T{:02}:{:02}:{:02}",
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    ));
+    if digits > 0 {
+        let fraction = nanos / 10_i64.pow(RFC3339_MAX_DIGITS - digits);
+        result.push_str(&format!(".{:01$}", fraction, digits as usize));
+    }
+    match offset_minutes {
+        None => result.push('Z'),
+        Some(minutes) => result.push_str(&format!(
+            "{}{:02}:{:02}",
+            if minutes < 0 { '-' } else { '+' },
+            minutes.abs() / 60,
+            minutes.abs() % 60
+        )),
+    }
+    result
+}
+
+/// Return the fewest fraction digits that represent NANOS exactly.
+fn significant_digits(nanos: i64) -> u32 {
+    (0..RFC3339_MAX_DIGITS)
+        .find(|&d| nanos % 10_i64.pow(RFC3339_MAX_DIGITS - d) == 0)
+        .unwrap_or(RFC3339_MAX_DIGITS)
+}
+
+/// Parse STRING, an RFC 3339 timestamp, and return it as a Lisp time
+/// value (TICKS . HZ), meaning TICKS/HZ seconds since the epoch.  HZ is
+/// 10 to the power of the number of fraction digits in STRING, so that
+/// no precision is lost; digits beyond nanoseconds are truncated.
+///
+/// For example, "1985-04-12T23:20:50.52Z" is parsed as
+/// (48209405052 . 100).
+///
+/// Besides calendar dates like "2019-12-31", STRING may use the ISO 8601
+/// week date "2020-W01-2" or ordinal date "2019-365" forms.  The date
+/// and time may be separated by "T" or a space.  The seconds and their
+/// fraction, which may follow a period or a comma, are optional, and a
+/// leap second, 60, counts as the first second of the next minute.  The
+/// zone is "Z" for UTC or an offset such as "+05:30" or "-0800".
+///
+/// If STRING has no zone, the optional argument ZONE says how to
+/// interpret it: t means UTC and an integer is an offset in seconds east
+/// of UTC.  If ZONE is nil, a zone is required.
+///
+/// Signal an error if STRING is not a valid timestamp.
+#[lisp_fn(min = "1")]
+pub fn parse_rfc3339_time(string: LispStringRef, zone: LispObject) -> LispObject {
+    let default_offset = if zone.is_nil() {
+        None
+    } else if zone.eq(Qt) {
+        Some(0)
+    } else {
+        Some(zone.as_fixnum_or_error())
+    };
+
+    match parse_rfc3339(string.as_slice(), default_offset) {
+        Some(time) => {
+            let hz = 10_i64.pow(time.digits);
+            make_lisp_ticks(
+                i128::from(time.seconds) * i128::from(hz) + i128::from(time.fraction),
+                hz,
+            )
+        }
+        None => error!("Invalid RFC 3339 time string: {}", string),
+    }
+}
+
+/// Format TIME as an RFC 3339 timestamp such as
+/// "1985-04-12T23:20:50.52Z".
+///
+/// TIME may be a Lisp time value (TICKS . HZ), as returned by
+/// `parse-rfc3339-time', meaning TICKS/HZ seconds since the epoch, or any
+/// time value accepted by `format-time-string'; nil means the current
+/// time.  The obsolete form (HIGH . LOW) is not accepted.
+///
+/// The optional ZONE is nil for Emacs local time, t for UTC, or any
+/// other zone accepted by `decode-time'.  UTC is written as "Z", other
+/// zones as an offset such as "+05:30", truncated to whole minutes.
+///
+/// DIGITS is the number of digits of the fraction of a second to write,
+/// from 0 to 9.  If it is nil, TIME is written exactly: with as many
+/// digits as HZ has zeros if HZ is a power of ten, or else with the
+/// fewest digits needed to represent TIME to the nanosecond.
+#[lisp_fn(min = "0")]
+pub fn format_rfc3339_time(time: LispObject, zone: LispObject, digits: LispObject) -> LispObject {
+    let ticks = time
+        .as_cons()
+        .and_then(|c| Some((c.car().as_fixnum()?, c.cdr().as_fixnum()?)))
+        .filter(|&(_, hz)| hz > 0);
+
+    let (seconds, nanos, exact_digits) = if let Some((ticks, hz)) = ticks {
+        let nanos = i128::from(ticks.rem_euclid(hz)) * 1_000_000_000 / i128::from(hz);
+        let nanos = nanos as i64;
+        let decimal = (0..=RFC3339_MAX_DIGITS).find(|&d| 10_i64.pow(d) == hz);
+        (
+            ticks.div_euclid(hz),
+            nanos,
+            decimal.unwrap_or_else(|| significant_digits(nanos)),
+        )
+    } else {
+        let t = unsafe { lisp_time_struct(time, ptr::null_mut()) };
+        let nanos = i64::from(t.us) * 1000 + i64::from(t.ps) / 1000;
+        (
+            (t.hi << LO_TIME_BITS) + i64::from(t.lo),
+            nanos,
+            significant_digits(nanos),
+        )
+    };
+
+    let digits = if digits.is_nil() {
+        exact_digits
+    } else {
+        match digits.as_fixnum_or_error() {
+            d @ 0..=9 => d as u32,
+            _ => args_out_of_range!(digits, 0, RFC3339_MAX_DIGITS),
+        }
+    };
+
+    let offset = if zone.eq(Qt) {
+        None
+    } else {
+        let decoded = unsafe { Fdecode_time(LispObject::from(seconds), zone) };
+        Some(nth(8, decoded).as_fixnum().unwrap_or(0))
+    };
+
+    LispObject::from(format_rfc3339(seconds, nanos, digits, offset).as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        for &days in &[-719_468, -1, 0, 11016, 11017, 2_932_896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let parse = |s: &str| parse_rfc3339(s.as_bytes(), None);
+        let time = |seconds, fraction, digits| {
+            Some(Rfc3339Time {
+                seconds,
+                fraction,
+                digits,
+            })
+        };
+        assert_eq!(parse("1985-04-12T23:20:50.52Z"), time(482_196_050, 52, 2));
+        assert_eq!(parse("1996-12-19T16:39:57-08:00"), time(851_042_397, 0, 0));
+        assert_eq!(parse("1990-12-31t23:59:60z"), time(662_688_000, 0, 0));
+        assert_eq!(parse("2009-W53-7 00:00+0100"), time(1_262_473_200, 0, 0));
+        assert_eq!(parse("2008-W01-1T00:00Z"), parse("2007-12-31T00:00Z"));
+        assert_eq!(parse("2020-060T00:00:00,5Z"), time(1_582_934_400, 5, 1));
+        assert_eq!(
+            parse("2020-01-01T00:00:00.1234567891234Z"),
+            time(1_577_836_800, 123_456_789, 9)
+        );
+        assert_eq!(
+            parse_rfc3339(b"1970-01-01T01:00", Some(3600)),
+            time(0, 0, 0)
+        );
+
+        for bad in &[
+            "",
+            "1970-01-01",
+            "1970-01-01T00:00",
+            "1970-01-01T00:00:00.Z",
+            "1970-02-30T00:00Z",
+            "1970-01-01T24:00Z",
+            "2019-366T00:00Z",
+            "2019-W53-1T00:00Z",
+            "1970-01-01T00:00Z ",
+        ] {
+            assert_eq!(parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(
+            format_rfc3339(482_196_050, 520_000_000, 2, None),
+            "1985-04-12T23:20:50.52Z"
+        );
+        assert_eq!(
+            format_rfc3339(851_042_397, 0, 0, Some(-8 * 3600)),
+            "1996-12-19T16:39:57-08:00"
+        );
+        assert_eq!(
+            format_rfc3339(-1, 999_999_999, 3, Some(19800)),
+            "1970-01-01T05:29:59.999+05:30"
+        );
+        assert_eq!(significant_digits(0), 0);
+        assert_eq!(significant_digits(500_000_000), 1);
+        assert_eq!(significant_digits(1), 9);
+    }
+}
+
 include!(concat!(env!("OUT_DIR"), "/time_exports.rs"));
@@ -0,0 +1,762 @@
+//! Time zones from the tz database.
+//!
+//! Zone names such as "Europe/Paris" are looked up in the TZif files
+//! under /usr/share/zoneinfo (or $TZDIR), described in RFC 8536, and
+//! the parsed zones are cached.  This lets `decode-time', `encode-time'
+//! and `format-time-string' use a named zone without setting TZ in the
+//! environment of the process, which is slow and affects other threads.
+//! Zones that are not found here, such as POSIX TZ strings like
+//! "EST5EDT", are left to the C library.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::{CString, OsStr};
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use libc::{c_int, time_t};
+
+use remacs_macros::lisp_fn;
+
+use crate::{
+    fns::nreverse,
+    lisp::LispObject,
+    lists::list,
+    multibyte::LispStringRef,
+    remacs_sys::{Fgetenv_internal, Qerror, Qnil},
+    time::{civil_from_days, days_from_civil, is_leap_year, lisp_time_struct, time_overflow},
+};
+
+/// A local time type: an offset from UTC in seconds, whether it is
+/// daylight saving time, and its abbreviation.
+#[derive(Debug, PartialEq)]
+struct LocalTimeType {
+    offset: i64,
+    is_dst: bool,
+    abbrev: CString,
+}
+
+/// The day of the year on which a POSIX TZ rule changes time.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum RuleDay {
+    /// Jn: the day N from 1 to 365, not counting February 29th.
+    Julian(i64),
+    /// n: the day N from 0 to 365, counting February 29th.
+    Zero(i64),
+    /// Mm.w.d: the day D (0 is Sunday) of week W (5 is the last) of month M.
+    Month(i64, i64, i64),
+}
+
+impl RuleDay {
+    /// Return the number of days since the epoch of this day in YEAR.
+    fn days(self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+        match self {
+            RuleDay::Julian(n) => jan1 + n - 1 + (is_leap_year(year) && n >= 60) as i64,
+            RuleDay::Zero(n) => jan1 + n,
+            RuleDay::Month(month, week, weekday) => {
+                let first = days_from_civil(year, month, 1);
+                // 1970-01-01 was a Thursday.
+                let mut day = first + (weekday - (first + 4)).rem_euclid(7) + (week - 1) * 7;
+                while civil_from_days(day).1 != month {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+/// The daylight saving time part of a POSIX TZ rule: the time type and
+/// when it starts and ends, in local time of the type in effect before.
+#[derive(Debug, PartialEq)]
+struct DstRule {
+    dst: LocalTimeType,
+    start: (RuleDay, i64),
+    end: (RuleDay, i64),
+}
+
+/// A POSIX TZ rule such as "CET-1CEST,M3.5.0,M10.5.0/3".
+#[derive(Debug, PartialEq)]
+struct PosixRule {
+    std: LocalTimeType,
+    dst: Option<DstRule>,
+}
+
+impl PosixRule {
+    /// Return the times at which daylight saving time starts and ends
+    /// in YEAR.
+    fn transitions(&self, year: i64) -> Option<(i64, i64)> {
+        self.dst.as_ref().map(|rule| {
+            let (start_day, start_time) = rule.start;
+            let (end_day, end_time) = rule.end;
+            (
+                start_day.days(year) * 86400 + start_time - self.std.offset,
+                end_day.days(year) * 86400 + end_time - rule.dst.offset,
+            )
+        })
+    }
+
+    fn at(&self, t: i64) -> &LocalTimeType {
+        let rule = match self.dst {
+            Some(ref rule) => rule,
+            None => return &self.std,
+        };
+        let year = civil_from_days((t + self.std.offset).div_euclid(86400)).0;
+        let (start, end) = self.transitions(year).unwrap();
+        let in_dst = if start < end {
+            start <= t && t < end
+        } else {
+            // Southern hemisphere: DST spans the new year.
+            !(end <= t && t < start)
+        };
+        if in_dst {
+            &rule.dst
+        } else {
+            &self.std
+        }
+    }
+}
+
+/// A cursor over a POSIX TZ string.
+struct RuleParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RuleParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        if self.eat(c) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self) -> Option<i64> {
+        let start = self.pos;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits = &self.bytes[start..self.pos];
+        if digits.is_empty() || digits.len() > 3 {
+            return None;
+        }
+        Some(digits.iter().fold(0, |v, &d| v * 10 + i64::from(d - b'0')))
+    }
+
+    /// Read an abbreviation: three or more letters, or any characters
+    /// between angle brackets.
+    fn abbrev(&mut self) -> Option<CString> {
+        let quoted = self.eat(b'<');
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let ok = if quoted {
+                c != b'>'
+            } else {
+                c.is_ascii_alphabetic()
+            };
+            if !ok {
+                break;
+            }
+            self.pos += 1;
+        }
+        let abbrev = &self.bytes[start..self.pos];
+        if abbrev.len() < 3 || (quoted && !self.eat(b'>')) {
+            return None;
+        }
+        CString::new(abbrev).ok()
+    }
+
+    /// Read a signed time [+-]hh[:mm[:ss]] in seconds.
+    fn time(&mut self) -> Option<i64> {
+        let sign = if self.eat(b'-') {
+            -1
+        } else {
+            self.eat(b'+');
+            1
+        };
+        let mut seconds = self.number()? * 3600;
+        if self.eat(b':') {
+            seconds += self.number()? * 60;
+            if self.eat(b':') {
+                seconds += self.number()?;
+            }
+        }
+        Some(sign * seconds)
+    }
+
+    fn rule_day(&mut self) -> Option<(RuleDay, i64)> {
+        let day = if self.eat(b'J') {
+            RuleDay::Julian(self.number().filter(|n| (1..=365).contains(n))?)
+        } else if self.eat(b'M') {
+            let month = self.number().filter(|n| (1..=12).contains(n))?;
+            self.expect(b'.')?;
+            let week = self.number().filter(|n| (1..=5).contains(n))?;
+            self.expect(b'.')?;
+            let weekday = self.number().filter(|n| (0..=6).contains(n))?;
+            RuleDay::Month(month, week, weekday)
+        } else {
+            RuleDay::Zero(self.number().filter(|n| (0..=365).contains(n))?)
+        };
+        let time = if self.eat(b'/') { self.time()? } else { 7200 };
+        Some((day, time))
+    }
+
+    fn rule(&mut self) -> Option<PosixRule> {
+        let std_abbrev = self.abbrev()?;
+        // POSIX offsets are positive west of Greenwich.
+        let std_offset = -self.time()?;
+        let std = LocalTimeType {
+            offset: std_offset,
+            is_dst: false,
+            abbrev: std_abbrev,
+        };
+        if self.peek().is_none() {
+            return Some(PosixRule { std, dst: None });
+        }
+
+        let dst_abbrev = self.abbrev()?;
+        let dst_offset = match self.peek() {
+            Some(b',') | None => std_offset + 3600,
+            _ => -self.time()?,
+        };
+        let (start, end) = if self.eat(b',') {
+            let start = self.rule_day()?;
+            self.expect(b',')?;
+            (start, self.rule_day()?)
+        } else {
+            // The default rule of POSIX implementations, from the US.
+            (
+                (RuleDay::Month(3, 2, 0), 7200),
+                (RuleDay::Month(11, 1, 0), 7200),
+            )
+        };
+        if self.peek().is_some() {
+            return None;
+        }
+        Some(PosixRule {
+            std,
+            dst: Some(DstRule {
+                dst: LocalTimeType {
+                    offset: dst_offset,
+                    is_dst: true,
+                    abbrev: dst_abbrev,
+                },
+                start,
+                end,
+            }),
+        })
+    }
+}
+
+fn parse_posix_rule(rule: &[u8]) -> Option<PosixRule> {
+    RuleParser {
+        bytes: rule,
+        pos: 0,
+    }
+    .rule()
+}
+
+/// A time zone parsed from a TZif file: the times of its transitions
+/// with the index of the time type that starts at each, and the rule
+/// for times after the last transition.  Leap seconds are ignored.
+#[derive(Debug)]
+struct TimeZone {
+    transitions: Vec<(i64, usize)>,
+    types: Vec<LocalTimeType>,
+    rule: Option<PosixRule>,
+}
+
+/// A cursor over the contents of a TZif file.
+struct TzifReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> TzifReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn int(&mut self, size: usize) -> Option<i64> {
+        let bytes = self.take(size)?;
+        let unsigned = bytes.iter().fold(0u64, |v, &b| v << 8 | u64::from(b));
+        // Sign-extend the SIZE-byte value.
+        let shift = 64 - 8 * size as u32;
+        Some((unsigned << shift) as i64 >> shift)
+    }
+
+    /// Read a header, returning the version and the counts isutcnt,
+    /// isstdcnt, leapcnt, timecnt, typecnt and charcnt.
+    fn header(&mut self) -> Option<(u8, [usize; 6])> {
+        if self.take(4)? != b"TZif" {
+            return None;
+        }
+        let version = self.take(16)?[0];
+        let mut counts = [0; 6];
+        for count in counts.iter_mut() {
+            *count = self.int(4)? as u32 as usize;
+        }
+        Some((version, counts))
+    }
+}
+
+fn parse_tzif(data: &[u8]) -> Option<TimeZone> {
+    let mut reader = TzifReader { data, pos: 0 };
+    let (version, mut counts) = reader.header()?;
+    let mut time_size = 4;
+    if version >= b'2' {
+        // Skip the version 1 data, which has 32-bit times.
+        let [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt] = counts;
+        reader.take(timecnt * 5 + typecnt * 6 + charcnt + leapcnt * 8 + isstdcnt + isutcnt)?;
+        counts = reader.header()?.1;
+        time_size = 8;
+    }
+
+    let [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt] = counts;
+    if typecnt == 0 {
+        return None;
+    }
+    let times = (0..timecnt)
+        .map(|_| reader.int(time_size))
+        .collect::<Option<Vec<_>>>()?;
+    let indices = reader.take(timecnt)?;
+    let mut raw_types = Vec::with_capacity(typecnt);
+    for _ in 0..typecnt {
+        let offset = reader.int(4)?;
+        let flags = reader.take(2)?;
+        raw_types.push((offset, flags[0] != 0, usize::from(flags[1])));
+    }
+    let chars = reader.take(charcnt)?;
+    reader.take(leapcnt * (time_size + 4) + isstdcnt + isutcnt)?;
+
+    let mut types = Vec::with_capacity(typecnt);
+    for (offset, is_dst, index) in raw_types {
+        let abbrev = chars.get(index..)?;
+        let len = abbrev.iter().position(|&c| c == 0)?;
+        types.push(LocalTimeType {
+            offset,
+            is_dst,
+            abbrev: CString::new(&abbrev[..len]).ok()?,
+        });
+    }
+
+    let mut transitions = Vec::with_capacity(timecnt);
+    for (&time, &index) in times.iter().zip(indices) {
+        if usize::from(index) >= typecnt {
+            return None;
+        }
+        transitions.push((time, usize::from(index)));
+    }
+
+    // The footer is a POSIX TZ string between newlines, possibly empty.
+    let rule = if time_size == 8 {
+        let footer = reader.take(data.len() - reader.pos)?;
+        match footer {
+            [b'\n', b'\n'] => None,
+            [b'\n', rule @ .., b'\n'] => Some(parse_posix_rule(rule)?),
+            _ => return None,
+        }
+    } else {
+        None
+    };
+
+    Some(TimeZone {
+        transitions,
+        types,
+        rule,
+    })
+}
+
+impl TimeZone {
+    /// Return the local time type in effect at T, in seconds since the
+    /// epoch.
+    fn at(&self, t: i64) -> &LocalTimeType {
+        // The number of transitions at or before T.
+        let index = match self.transitions.binary_search_by(|&(time, _)| time.cmp(&t)) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+        match (index, &self.rule) {
+            (i, Some(rule)) if i == self.transitions.len() => rule.at(t),
+            // Before the first transition, the first time type is used.
+            (0, _) => &self.types[0],
+            (i, _) => &self.types[self.transitions[i - 1].1],
+        }
+    }
+
+    /// Return the time in seconds since the epoch of the local time
+    /// LOCAL, in seconds since the epoch as if the zone were UTC.  An
+    /// ambiguous local time is taken as the earlier time, and a local
+    /// time skipped by a transition as if the transition had not
+    /// happened yet, as `mktime' does.
+    fn to_utc(&self, local: i64) -> i64 {
+        let mut offsets: Vec<i64> = self.types.iter().map(|t| t.offset).collect();
+        if let Some(ref rule) = self.rule {
+            offsets.push(rule.std.offset);
+            offsets.extend(rule.dst.as_ref().map(|r| r.dst.offset));
+        }
+        let mut candidates: Vec<i64> = offsets.iter().map(|o| local - o).collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+            .iter()
+            .cloned()
+            .find(|&t| local - self.at(t).offset == t)
+            .unwrap_or_else(|| local - self.at(candidates[0]).offset)
+    }
+
+    /// Return the transitions between START and END, as pairs of their
+    /// time and the local time type they start.  Transitions that do
+    /// not change the time type are omitted.
+    fn transitions_between(&self, start: i64, end: i64) -> Vec<(i64, &LocalTimeType)> {
+        let mut result: Vec<(i64, &LocalTimeType)> = self
+            .transitions
+            .iter()
+            .map(|&(t, index)| (t, &self.types[index]))
+            .collect();
+        if let Some(ref rule) = self.rule {
+            let last = self.transitions.last().map_or(i64::min_value(), |t| t.0);
+            // A zone without transitions has used its rule forever; do not
+            // list its transitions before 1900.
+            let from = start.max(last).max(-2_208_988_800);
+            let first_year = civil_from_days(from.div_euclid(86400)).0 - 1;
+            let last_year = civil_from_days(end.div_euclid(86400)).0 + 1;
+            for year in first_year..=last_year {
+                if let Some((dst_start, dst_end)) = rule.transitions(year) {
+                    let mut pair = [(dst_start, true), (dst_end, false)];
+                    pair.sort_unstable();
+                    for &(t, is_dst) in &pair {
+                        if t > last {
+                            let dst = &rule.dst.as_ref().unwrap().dst;
+                            result.push((t, if is_dst { dst } else { &rule.std }));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut previous: Option<&LocalTimeType> = None;
+        result.retain(|&(_, ltt)| {
+            let changed = previous != Some(ltt);
+            previous = Some(ltt);
+            changed
+        });
+        result.retain(|&(t, _)| start <= t && t <= end);
+        result
+    }
+}
+
+lazy_static! {
+    /// The zones read so far, by file name.  Files that could not be
+    /// read are not recorded, so that they are retried.  Zones are never
+    /// evicted, so that the abbreviations they own can be referenced by
+    /// a `struct tm`.
+    static ref ZONES: Mutex<HashMap<PathBuf, Arc<TimeZone>>> = Mutex::new(HashMap::new());
+}
+
+/// Return the TZif file for the zone NAME, or `None` if NAME cannot be
+/// a zone name.  A leading colon, as in the TZ variable, is ignored.
+fn zone_file(name: &[u8]) -> Option<PathBuf> {
+    let name = if name.starts_with(b":") {
+        &name[1..]
+    } else {
+        name
+    };
+    let name = String::from_utf8(name.to_vec()).ok()?;
+    if name.is_empty() || name.split('/').any(|c| c == "..") {
+        return None;
+    }
+    if name.starts_with('/') {
+        return Some(PathBuf::from(name));
+    }
+    let tzdir = unsafe { Fgetenv_internal(LispObject::from("TZDIR"), Qnil) };
+    let dir = match tzdir.as_string() {
+        Some(dir) if !dir.is_empty() => PathBuf::from(OsStr::from_bytes(dir.as_slice())),
+        _ => PathBuf::from("/usr/share/zoneinfo"),
+    };
+    Some(dir.join(name))
+}
+
+/// Return the zone named NAME, reading it if it is not cached yet.
+fn find_zone(name: &[u8]) -> Option<Arc<TimeZone>> {
+    let file = zone_file(name)?;
+    let mut zones = ZONES.lock().unwrap();
+    if let Some(zone) = zones.get(&file) {
+        return Some(Arc::clone(zone));
+    }
+    let zone = Arc::new(parse_tzif(&fs::read(&file).ok()?)?);
+    zones.insert(file, Arc::clone(&zone));
+    Some(zone)
+}
+
+/// Return the zone named by the Lisp time zone rule ZONE, if it is a
+/// string naming a zoneinfo file.  POSIX TZ rules such as "JST-9" are
+/// left to the C library without looking for a file, as they are used
+/// often and are seldom the names of files.
+fn lisp_zone(zone: LispObject) -> Option<Arc<TimeZone>> {
+    let name = zone.as_string()?;
+    if parse_posix_rule(name.as_slice()).is_some() {
+        return None;
+    }
+    find_zone(name.as_slice())
+}
+
+/// If ZONE names a zoneinfo file, store the local time of T in that
+/// zone into *TM and return true.  Otherwise return false, and leave
+/// the conversion to the C library.  The `tm_zone` member of *TM points
+/// into the zone cache and stays valid.
+#[no_mangle]
+pub unsafe extern "C" fn rust_tz_localtime(zone: LispObject, t: time_t, tm: *mut libc::tm) -> bool {
+    let zone = match lisp_zone(zone) {
+        Some(zone) => zone,
+        None => return false,
+    };
+    let t = i64::from(t);
+    let ltt = zone.at(t);
+    let local = t + ltt.offset;
+    let days = local.div_euclid(86400);
+    let seconds = local.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let year = match c_int::try_from(year - 1900) {
+        Ok(year) => year,
+        Err(_) => time_overflow(),
+    };
+
+    *tm = std::mem::zeroed();
+    (*tm).tm_sec = (seconds % 60) as c_int;
+    (*tm).tm_min = (seconds / 60 % 60) as c_int;
+    (*tm).tm_hour = (seconds / 3600) as c_int;
+    (*tm).tm_mday = day as c_int;
+    (*tm).tm_mon = (month - 1) as c_int;
+    (*tm).tm_year = year;
+    // 1970-01-01 was a Thursday.
+    (*tm).tm_wday = (days + 4).rem_euclid(7) as c_int;
+    (*tm).tm_yday = (days - days_from_civil(i64::from(year) + 1900, 1, 1)) as c_int;
+    (*tm).tm_isdst = ltt.is_dst as c_int;
+    (*tm).tm_gmtoff = ltt.offset as _;
+    (*tm).tm_zone = ltt.abbrev.as_ptr() as _;
+    true
+}
+
+/// If ZONE names a zoneinfo file, store the time of the local time *TM
+/// in that zone into *RESULT and return true.  The members of *TM may be
+/// out of range, as for `mktime'.  Otherwise return false, and leave
+/// the conversion to the C library.
+#[no_mangle]
+pub unsafe extern "C" fn rust_tz_mktime(
+    zone: LispObject,
+    tm: *const libc::tm,
+    result: *mut time_t,
+) -> bool {
+    let zone = match lisp_zone(zone) {
+        Some(zone) => zone,
+        None => return false,
+    };
+    let tm = &*tm;
+    let month = i64::from(tm.tm_mon);
+    let year = i64::from(tm.tm_year) + 1900 + month.div_euclid(12);
+    let days = days_from_civil(year, month.rem_euclid(12) + 1, 1) + i64::from(tm.tm_mday) - 1;
+    let local = days * 86400
+        + i64::from(tm.tm_hour) * 3600
+        + i64::from(tm.tm_min) * 60
+        + i64::from(tm.tm_sec);
+    *result = zone.to_utc(local) as time_t;
+    true
+}
+
+/// Return the number of seconds since the epoch of the Lisp time TIME.
+fn lisp_seconds(time: LispObject) -> i64 {
+    let t = unsafe { lisp_time_struct(time, ptr::null_mut()) };
+    (t.hi << 16) + i64::from(t.lo)
+}
+
+/// Return the transitions of the time zone ZONE between START and END.
+/// ZONE is the name of a zone in the tz database, such as
+/// "Europe/Paris".  START and END are Lisp time values; START defaults
+/// to the earliest transition and END to one year from now.
+///
+/// The value is a list of elements (TIME OFFSET DST ABBREVIATION), one
+/// for each time TIME, an integer number of seconds since the epoch, at
+/// which the zone starts to be OFFSET seconds east of UTC, with the
+/// abbreviation ABBREVIATION.  DST is t if the new time is daylight
+/// saving time.
+///
+/// Zones are read from the TZif files in the directory named by the
+/// environment variable TZDIR, or /usr/share/zoneinfo, and cached.
+/// Signal an error if ZONE is not found there.
+#[lisp_fn(min = "1")]
+pub fn time_zone_transitions(
+    zone: LispStringRef,
+    start: LispObject,
+    end: LispObject,
+) -> LispObject {
+    let tz = match find_zone(zone.as_slice()) {
+        Some(tz) => tz,
+        None => xsignal!(Qerror, "Invalid time zone specification", zone),
+    };
+    let start = if start.is_nil() {
+        i64::min_value()
+    } else {
+        lisp_seconds(start)
+    };
+    let end = if end.is_nil() {
+        lisp_seconds(Qnil) + 366 * 86400
+    } else {
+        lisp_seconds(end)
+    };
+
+    let mut result = Qnil;
+    for (t, ltt) in tz.transitions_between(start, end) {
+        let abbrev = ltt.abbrev.to_string_lossy();
+        result = LispObject::cons(
+            list(&[
+                t.into(),
+                ltt.offset.into(),
+                ltt.is_dst.into(),
+                abbrev.as_ref().into(),
+            ]),
+            result,
+        );
+    }
+    nreverse(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ltt(offset: i64, is_dst: bool, abbrev: &str) -> LocalTimeType {
+        LocalTimeType {
+            offset,
+            is_dst,
+            abbrev: CString::new(abbrev).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_posix_rule() {
+        assert_eq!(
+            parse_posix_rule(b"<+0530>-5:30"),
+            Some(PosixRule {
+                std: ltt(19800, false, "+0530"),
+                dst: None,
+            })
+        );
+        let rule = parse_posix_rule(b"CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(rule.std, ltt(3600, false, "CET"));
+        let dst = rule.dst.as_ref().unwrap();
+        assert_eq!(dst.dst, ltt(7200, true, "CEST"));
+        assert_eq!(dst.start, (RuleDay::Month(3, 5, 0), 7200));
+        assert_eq!(dst.end, (RuleDay::Month(10, 5, 0), 10800));
+        // 2021-03-28T01:00Z and 2021-10-31T01:00Z.
+        assert_eq!(rule.transitions(2021), Some((1_616_893_200, 1_635_642_000)));
+        assert_eq!(rule.at(1_616_893_199).offset, 3600);
+        assert_eq!(rule.at(1_616_893_200).offset, 7200);
+        assert_eq!(rule.at(1_635_642_000).offset, 3600);
+
+        // Southern hemisphere, with DST over the new year.
+        let rule = parse_posix_rule(b"AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(rule.at(1_609_459_200).offset, 39600);
+        assert_eq!(rule.at(1_625_097_600).offset, 36000);
+
+        assert_eq!(RuleDay::Julian(60).days(2020), days_from_civil(2020, 3, 1));
+        assert_eq!(RuleDay::Zero(59).days(2020), days_from_civil(2020, 2, 29));
+
+        assert_eq!(parse_posix_rule(b"EST5EDT,M3.2.0"), None);
+        assert_eq!(parse_posix_rule(b"X1"), None);
+    }
+
+    /// Build a version 2 TZif file with the given 64-bit data.
+    fn tzif(
+        transitions: &[(i64, u8)],
+        types: &[(i32, bool, u8)],
+        chars: &[u8],
+        footer: &[u8],
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+        let header = |data: &mut Vec<u8>, counts: [u32; 6]| {
+            data.extend_from_slice(b"TZif2");
+            data.extend_from_slice(&[0; 15]);
+            for count in &counts {
+                data.extend_from_slice(&count.to_be_bytes());
+            }
+        };
+        // An empty version 1 block.
+        header(&mut data, [0, 0, 0, 0, 1, 1]);
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+        let counts = [
+            0,
+            0,
+            0,
+            transitions.len() as u32,
+            types.len() as u32,
+            chars.len() as u32,
+        ];
+        header(&mut data, counts);
+        for &(t, _) in transitions {
+            data.extend_from_slice(&t.to_be_bytes());
+        }
+        data.extend(transitions.iter().map(|&(_, i)| i));
+        for &(offset, is_dst, index) in types {
+            data.extend_from_slice(&offset.to_be_bytes());
+            data.push(is_dst as u8);
+            data.push(index);
+        }
+        data.extend_from_slice(chars);
+        data.extend_from_slice(footer);
+        data
+    }
+
+    #[test]
+    fn test_parse_tzif() {
+        let data = tzif(
+            &[(-1_000_000_000, 1), (0, 2)],
+            &[(561, false, 0), (0, false, 4), (3600, false, 8)],
+            b"LMT\0GMT\0CET\0",
+            b"\nCET-1CEST,M3.5.0,M10.5.0/3\n",
+        );
+        let zone = parse_tzif(&data).unwrap();
+        assert_eq!(zone.at(-2_000_000_000).abbrev.to_bytes(), b"LMT");
+        assert_eq!(zone.at(-1).abbrev.to_bytes(), b"GMT");
+        assert_eq!(zone.at(0).abbrev.to_bytes(), b"CET");
+        assert_eq!(zone.at(1_625_097_600).abbrev.to_bytes(), b"CEST");
+
+        // 2021-03-28T02:30 does not exist, and 2021-10-31T02:30 is ambiguous.
+        let local = days_from_civil(2021, 3, 28) * 86400 + 2 * 3600 + 1800;
+        assert_eq!(zone.to_utc(local), local - 3600);
+        let local = days_from_civil(2021, 10, 31) * 86400 + 2 * 3600 + 1800;
+        assert_eq!(zone.to_utc(local), local - 7200);
+        assert_eq!(zone.to_utc(3600), 0);
+
+        let transitions = zone.transitions_between(-1, 1_640_995_200);
+        assert_eq!(transitions[0].0, 0);
+        assert_eq!(transitions[0].1.abbrev.to_bytes(), b"CET");
+        // Twice a year from 1970 to 2021.
+        assert_eq!(transitions.len(), 1 + 52 * 2);
+        assert_eq!(transitions.last().unwrap().0, 1_635_642_000);
+
+        assert!(parse_tzif(b"TZif2").is_none());
+        assert!(parse_tzif(&data[..data.len() - 5]).is_none());
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/timezone_exports.rs"));
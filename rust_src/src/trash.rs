@@ -0,0 +1,617 @@
+//! The freedesktop.org trash.
+//!
+//! A file is moved to the home trash if it lives on the same file system,
+//! and otherwise to the trash at the top directory of its own file
+//! system, as described in the trash specification at
+//! https://specifications.freedesktop.org/trash-spec/trashspec-1.0.html.
+//! Each trash directory keeps the trashed files in "files" and, for each
+//! of them, a ".trashinfo" file in "info" recording where it came from
+//! and when it was trashed.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+use libc::{c_char, timespec as c_timespec};
+
+use remacs_macros::lisp_fn;
+
+use crate::{
+    coding::encode_file_name,
+    dired_unix::mount_points,
+    fileio::{expand_file_name, find_file_name_handler, report_io_error},
+    fns::nreverse,
+    lisp::LispObject,
+    lists::{list, LispConsCircularChecks, LispConsEndChecks},
+    multibyte::LispStringRef,
+    remacs_sys::{decode_file_name, make_unibyte_string, Fgetenv_internal, Frename_file},
+    remacs_sys::{Qfile_already_exists, Qnil, Qtrash_file},
+    time::make_lisp_time,
+};
+
+/// A trash directory, with the top directory of its file system for
+/// the trashes of other file systems than the home one.  File names in
+/// the .trashinfo files of such a trash are relative to that top
+/// directory.
+struct TrashDir {
+    path: PathBuf,
+    topdir: Option<PathBuf>,
+}
+
+/// A trashed file, as described by its .trashinfo file.
+struct TrashEntry {
+    file: PathBuf,
+    info: PathBuf,
+    original: PathBuf,
+    deletion_date: Option<String>,
+}
+
+impl TrashDir {
+    fn files(&self) -> PathBuf {
+        self.path.join("files")
+    }
+
+    fn info(&self) -> PathBuf {
+        self.path.join("info")
+    }
+
+    /// The home trash, in $XDG_DATA_HOME/Trash.
+    fn home() -> Self {
+        let data_home = unsafe { Fgetenv_internal(LispObject::from("XDG_DATA_HOME"), Qnil) };
+        let data_home = match data_home.as_string() {
+            Some(dir) if !dir.is_empty() => dir,
+            _ => LispStringRef::from(LispObject::from("~/.local/share")),
+        };
+        Self {
+            path: lisp_to_path(expand_file_name(data_home, None)).join("Trash"),
+            topdir: None,
+        }
+    }
+
+    /// The trash of the file system whose top directory is TOPDIR.  The
+    /// shared $topdir/.Trash is used if an administrator has set it up,
+    /// and $topdir/.Trash-$uid otherwise.  Either is created if needed.
+    fn for_topdir(topdir: &Path) -> io::Result<Self> {
+        let uid = unsafe { libc::getuid() };
+        let shared = topdir.join(".Trash");
+        // The spec requires the shared trash to be a sticky directory,
+        // not a symbolic link, so that users cannot meddle with each
+        // other's trash.
+        let shared_ok = fs::symlink_metadata(&shared).map_or(false, |md| {
+            md.is_dir() && md.mode() & libc::S_ISVTX as u32 != 0
+        });
+        if shared_ok {
+            let trash = Self {
+                path: shared.join(uid.to_string()),
+                topdir: Some(topdir.to_owned()),
+            };
+            if trash.create().is_ok() {
+                return Ok(trash);
+            }
+        }
+
+        let trash = Self {
+            path: topdir.join(format!(".Trash-{}", uid)),
+            topdir: Some(topdir.to_owned()),
+        };
+        trash.create()?;
+        Ok(trash)
+    }
+
+    /// The trash directories that exist: the home one and those at the top
+    /// of each mounted file system.
+    fn all() -> Vec<Self> {
+        let uid = unsafe { libc::getuid() };
+        let mut trashes = vec![Self::home()];
+        for topdir in mount_points() {
+            let candidates = [
+                topdir.join(".Trash").join(uid.to_string()),
+                topdir.join(format!(".Trash-{}", uid)),
+            ];
+            for path in candidates.iter() {
+                if path.join("info").is_dir() && !trashes.iter().any(|t| &t.path == path) {
+                    trashes.push(Self {
+                        path: path.to_owned(),
+                        topdir: Some(topdir.to_owned()),
+                    });
+                }
+            }
+        }
+        trashes
+    }
+
+    /// The trash that holds FILE, a file in its "files" directory.
+    fn containing(file: &Path) -> Option<Self> {
+        let files = file.parent()?;
+        if files.file_name()? != "files" {
+            return None;
+        }
+        let path = files.parent()?.to_owned();
+        let home = Self::home();
+        if path == home.path {
+            return Some(home);
+        }
+
+        let parent = path.parent()?;
+        let topdir = if parent.file_name()? == ".Trash" {
+            parent.parent()?
+        } else {
+            parent
+        };
+        Some(Self {
+            topdir: Some(topdir.to_owned()),
+            path,
+        })
+    }
+
+    fn create(&self) -> io::Result<()> {
+        let mut builder = fs::DirBuilder::new();
+        builder.mode(0o700).recursive(true);
+        builder.create(self.files())?;
+        builder.create(self.info())?;
+
+        // Do not put our files into somebody else's directory.
+        let md = fs::symlink_metadata(&self.path)?;
+        if !md.is_dir() || md.uid() != unsafe { libc::getuid() } {
+            return Err(io::Error::from_raw_os_error(libc::EPERM));
+        }
+        Ok(())
+    }
+
+    /// The file name to record in the .trashinfo file for ORIGINAL.
+    fn info_path<'a>(&self, original: &'a Path) -> &'a Path {
+        self.topdir
+            .as_ref()
+            .and_then(|topdir| original.strip_prefix(topdir).ok())
+            .unwrap_or(original)
+    }
+
+    fn entry(&self, info: PathBuf) -> Option<TrashEntry> {
+        let name = info.file_stem()?;
+        let file = self.files().join(name);
+        fs::symlink_metadata(&file).ok()?;
+
+        let (path, deletion_date) = parse_trashinfo(&fs::read(&info).ok()?)?;
+        let path = PathBuf::from(OsStr::from_bytes(&path));
+        let original = match self.topdir {
+            Some(ref topdir) if path.is_relative() => topdir.join(path),
+            _ => path,
+        };
+        Some(TrashEntry {
+            file,
+            info,
+            original,
+            deletion_date,
+        })
+    }
+
+    fn entries(&self) -> Vec<TrashEntry> {
+        let mut infos: Vec<PathBuf> = match fs::read_dir(self.info()) {
+            Ok(dir) => dir
+                .filter_map(Result::ok)
+                .map(|e| e.path())
+                .filter(|p| p.extension().map_or(false, |e| e == "trashinfo"))
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        infos.sort();
+        infos.into_iter().filter_map(|i| self.entry(i)).collect()
+    }
+
+    /// Move FILE into this trash, returning its new name.
+    fn put(&self, file: &Path) -> io::Result<PathBuf> {
+        let base = file
+            .file_name()
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
+        let contents = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(self.info_path(file).as_os_str().as_bytes()),
+            deletion_date_now()
+        );
+
+        // Reserve a name by creating its .trashinfo file exclusively,
+        // adding a number to the name until one is free.
+        let mut n = 1;
+        let (name, info) = loop {
+            let mut name = base.to_owned();
+            if n > 1 {
+                name.push(format!(".{}", n));
+            }
+            n += 1;
+            if fs::symlink_metadata(self.files().join(&name)).is_ok() {
+                continue;
+            }
+            let mut info_name = name.clone();
+            info_name.push(".trashinfo");
+            let info = self.info().join(info_name);
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info)
+            {
+                Ok(mut f) => {
+                    if let Err(err) = f.write_all(contents.as_bytes()) {
+                        let _ = fs::remove_file(&info);
+                        return Err(err);
+                    }
+                    break (name, info);
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        };
+
+        let trashed = self.files().join(name);
+        if let Err(err) = fs::rename(file, &trashed) {
+            let _ = fs::remove_file(&info);
+            return Err(err);
+        }
+        Ok(trashed)
+    }
+}
+
+/// The characters that stay as they are in the Path key of a .trashinfo
+/// file; the slash is kept for the sake of programs that expect it.
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"/-_.!~*'()".contains(&b)
+}
+
+fn percent_encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| {
+            if is_unreserved(b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Parse the contents of a .trashinfo file into the decoded original
+/// file name and the deletion date, if any.
+fn parse_trashinfo(contents: &[u8]) -> Option<(Vec<u8>, Option<String>)> {
+    let contents = String::from_utf8_lossy(contents);
+    let mut lines = contents.lines().map(str::trim_end);
+    if lines.next()? != "[Trash Info]" {
+        return None;
+    }
+
+    let mut path = None;
+    let mut deletion_date = None;
+    for line in lines {
+        if line.starts_with('[') {
+            break;
+        } else if line.starts_with("Path=") && path.is_none() {
+            path = Some(percent_decode(&line[5..]));
+        } else if line.starts_with("DeletionDate=") && deletion_date.is_none() {
+            deletion_date = Some(line[13..].to_string());
+        }
+    }
+    Some((path?, deletion_date))
+}
+
+/// The current local time in the YYYY-MM-DDThh:mm:ss format of the
+/// DeletionDate key.
+fn deletion_date_now() -> String {
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        )
+    }
+}
+
+/// Convert a DeletionDate, which is in local time, to a Lisp timestamp.
+fn deletion_date_to_lisp(date: &str) -> LispObject {
+    let fields: Vec<i32> = date
+        .split(|c| c == '-' || c == 'T' || c == ':')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+    if fields.len() != 6 {
+        return Qnil;
+    }
+
+    let mut tm: libc::tm = unsafe { mem::zeroed() };
+    tm.tm_year = fields[0] - 1900;
+    tm.tm_mon = fields[1] - 1;
+    tm.tm_mday = fields[2];
+    tm.tm_hour = fields[3];
+    tm.tm_min = fields[4];
+    tm.tm_sec = fields[5];
+    tm.tm_isdst = -1;
+    match unsafe { libc::mktime(&mut tm) } {
+        -1 => Qnil,
+        secs => make_lisp_time(c_timespec {
+            tv_sec: secs,
+            tv_nsec: 0,
+        }),
+    }
+}
+
+/// The top directory of the file system holding PATH, on device DEV: the
+/// highest ancestor of PATH that is on the same device.
+fn topdir(path: &Path, dev: u64) -> PathBuf {
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| fs::metadata(dir).map_or(false, |md| md.dev() == dev))
+        .last()
+        .unwrap_or(path)
+        .to_owned()
+}
+
+/// The device of PATH, or of its closest existing ancestor.
+fn device_of(path: &Path) -> Option<u64> {
+    path.ancestors()
+        .find_map(|dir| fs::metadata(dir).ok())
+        .map(|md| md.dev())
+}
+
+fn lisp_to_path(name: LispStringRef) -> PathBuf {
+    PathBuf::from(OsStr::from_bytes(encode_file_name(name).as_slice()))
+}
+
+fn path_to_lisp(path: &Path) -> LispObject {
+    let bytes = path.as_os_str().as_bytes();
+    unsafe {
+        decode_file_name(make_unibyte_string(
+            bytes.as_ptr() as *const c_char,
+            bytes.len() as isize,
+        ))
+    }
+}
+
+/// Delete the trashed file FILE, which may be a directory.
+fn remove_trashed(file: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(file) {
+        Ok(ref md) if md.is_dir() => fs::remove_dir_all(file),
+        _ => fs::remove_file(file),
+    }
+}
+
+/// Move FILENAME to the trash, following the freedesktop.org conventions.
+/// FILENAME goes to the home trash, in $XDG_DATA_HOME/Trash, if it is on
+/// the same file system, and otherwise to the trash at the top directory
+/// of its own file system: the shared ".Trash/UID" directory if the
+/// administrator has provided one, else ".Trash-UID".  A ".trashinfo"
+/// file records the original name and the deletion date, so that
+/// `trash-restore' can put the file back.
+///
+/// FILENAME may be a directory.  A symbolic link is trashed itself, not
+/// the file it points to.
+///
+/// Return the name of the file in the trash.
+#[lisp_fn]
+pub fn trash_file(filename: LispStringRef) -> LispObject {
+    let absname = expand_file_name(filename, None);
+    let handler = find_file_name_handler(absname, Qtrash_file);
+    if handler.is_not_nil() {
+        return call!(handler, Qtrash_file, absname.into());
+    }
+
+    let file = lisp_to_path(absname);
+    // Trim a trailing slash, so that the last component is the name.
+    let file = file.components().collect::<PathBuf>();
+    let md = match fs::symlink_metadata(&file) {
+        Ok(md) => md,
+        Err(err) => report_io_error(b"Moving to trash\0", absname, &err),
+    };
+
+    let home = TrashDir::home();
+    if home.path.starts_with(&file) {
+        error!(
+            "The trash directory {} is inside {}",
+            home.path.display(),
+            file.display()
+        );
+    }
+
+    let trash = if device_of(&home.path) == Some(md.dev()) {
+        home.create().map(|_| home)
+    } else {
+        TrashDir::for_topdir(&topdir(&file, md.dev()))
+    };
+    let trashed = match trash.and_then(|trash| trash.put(&file)) {
+        Ok(trashed) => trashed,
+        Err(err) => report_io_error(b"Moving to trash\0", absname, &err),
+    };
+    path_to_lisp(&trashed)
+}
+
+/// Return a list of the files in the freedesktop.org trash.
+/// This covers the home trash and the trash directories at the top of
+/// mounted file systems.  Each element has the form
+///
+///   (TRASHED ORIGINAL DELETION-TIME)
+///
+/// where TRASHED is the absolute name of the file in the trash, ORIGINAL
+/// is the absolute name it had before it was trashed, and DELETION-TIME
+/// is the time it was trashed, as in `current-time', or nil if unknown.
+/// Files that lack a valid ".trashinfo" file are left out.
+#[lisp_fn]
+pub fn trash_list() -> LispObject {
+    let mut result = Qnil;
+    for trash in TrashDir::all() {
+        for entry in trash.entries() {
+            let date = entry
+                .deletion_date
+                .as_ref()
+                .map_or(Qnil, |d| deletion_date_to_lisp(d));
+            let item = list(&[
+                path_to_lisp(&entry.file),
+                path_to_lisp(&entry.original),
+                date,
+            ]);
+            result = LispObject::cons(item, result);
+        }
+    }
+    nreverse(result)
+}
+
+/// Move the file TRASHED out of the trash, back to where it came from.
+/// TRASHED is a file name as returned by `trash-list' or `trash-file'.
+/// If NEWNAME is non-nil, restore the file under that name instead.
+/// Missing parent directories are created.  Signal a
+/// `file-already-exists' error if the file to restore would replace an
+/// existing one.
+///
+/// Return the name of the restored file.
+#[lisp_fn(min = "1")]
+pub fn trash_restore(trashed: LispStringRef, newname: LispObject) -> LispObject {
+    let trashed_abs = expand_file_name(trashed, None);
+    let file = lisp_to_path(trashed_abs);
+    let entry = TrashDir::containing(&file).and_then(|trash| {
+        let mut info_name = file.file_name()?.to_owned();
+        info_name.push(".trashinfo");
+        trash.entry(trash.info().join(info_name))
+    });
+    let entry = match entry {
+        Some(entry) => entry,
+        None => error!("Not a file in the trash: {}", file.display()),
+    };
+
+    let (target, target_lisp) = match newname.as_string() {
+        Some(newname) => {
+            let newname = expand_file_name(newname, None);
+            (lisp_to_path(newname), newname)
+        }
+        None => {
+            let original = path_to_lisp(&entry.original);
+            (entry.original, original.into())
+        }
+    };
+    if fs::symlink_metadata(&target).is_ok() {
+        xsignal!(
+            Qfile_already_exists,
+            LispObject::from("File already exists"),
+            target_lisp
+        );
+    }
+
+    if let Err(err) = target.parent().map_or(Ok(()), fs::create_dir_all) {
+        report_io_error(b"Restoring from trash\0", target_lisp, &err);
+    }
+    // Like `rename-file', copy the file and delete it if the target is
+    // on another file system.
+    unsafe { Frename_file(path_to_lisp(&entry.file), target_lisp.into(), Qnil) };
+    let _ = fs::remove_file(&entry.info);
+    target_lisp.into()
+}
+
+/// Permanently delete all files in the freedesktop.org trash.
+/// This empties the home trash and the trash directories at the top of
+/// mounted file systems.  If FILES is non-nil, it is a list of names of
+/// files in the trash, as returned by `trash-list', and only those are
+/// deleted.  Files that cannot be deleted are left in place.
+///
+/// Emptying the whole trash also deletes the files in it that have no
+/// valid ".trashinfo" file, and so are not listed by `trash-list'.
+#[lisp_fn(min = "0")]
+pub fn trash_empty(files: LispObject) {
+    let only: Option<Vec<PathBuf>> = if files.is_nil() {
+        None
+    } else {
+        Some(
+            files
+                .iter_cars(LispConsEndChecks::on, LispConsCircularChecks::on)
+                .map(|f| lisp_to_path(expand_file_name(f.into(), None)))
+                .collect(),
+        )
+    };
+
+    for trash in TrashDir::all() {
+        for entry in trash.entries() {
+            if only
+                .as_ref()
+                .map_or(false, |only| !only.contains(&entry.file))
+            {
+                continue;
+            }
+            if remove_trashed(&entry.file).is_ok() {
+                let _ = fs::remove_file(&entry.info);
+            }
+        }
+        if only.is_none() {
+            if let Ok(dir) = fs::read_dir(trash.files()) {
+                for orphan in dir.filter_map(Result::ok) {
+                    let _ = remove_trashed(&orphan.path());
+                }
+            }
+        }
+        // The cache of the sizes of trashed directories is now stale.
+        let _ = fs::remove_file(trash.path.join("directorysizes"));
+    }
+}
+
+def_lisp_sym!(Qtrash_file, "trash-file");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode(b"/home/u/a b.txt"), "/home/u/a%20b.txt");
+        assert_eq!(percent_encode("/tmp/ä%".as_bytes()), "/tmp/%C3%A4%25");
+        assert_eq!(percent_decode("/tmp/%C3%A4%25"), "/tmp/ä%".as_bytes());
+        // A stray percent sign is kept.
+        assert_eq!(percent_decode("100%"), b"100%");
+    }
+
+    #[test]
+    fn test_parse_trashinfo() {
+        let info = b"[Trash Info]\nPath=foo/bar%20baz\nDeletionDate=2004-08-31T22:32:08\n";
+        assert_eq!(
+            parse_trashinfo(info),
+            Some((
+                b"foo/bar baz".to_vec(),
+                Some("2004-08-31T22:32:08".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_trashinfo(b"[Trash Info]\r\nPath=/a\r\n"),
+            Some((b"/a".to_vec(), None))
+        );
+        assert_eq!(parse_trashinfo(b"Path=/a\n"), None);
+        assert_eq!(parse_trashinfo(b"[Trash Info]\nDeletionDate=x\n"), None);
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/trash_exports.rs"));
@@ -314,6 +314,8 @@ impl LispStructuralEqual for LispVectorlikeRef {
             false
         } else if let (Some(bv1), Some(bv2)) = (self.as_bool_vector(), other.as_bool_vector()) {
             bv1.equal(bv2, kind, depth, ht)
+        } else if let (Some(b1), Some(b2)) = (self.as_bignum(), other.as_bignum()) {
+            b1.equal(b2, kind, depth, ht)
         } else if let (Some(cf1), Some(cf2)) = (
             self.as_window_configuration(),
             other.as_window_configuration(),
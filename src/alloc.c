@@ -5478,7 +5478,8 @@ purecopy (Lisp_Object obj)
       struct Lisp_Hash_Table *h = purecopy_hash_table (table);
       XSET_HASH_TABLE (obj, h);
     }
-  else if (COMPILEDP (obj) || VECTORP (obj) || RECORDP (obj))
+  else if (COMPILEDP (obj) || VECTORP (obj) || RECORDP (obj)
+	   || BIGNUMP (obj))
     {
       struct Lisp_Vector *objp = XVECTOR (obj);
       ptrdiff_t nbytes = vector_nbytes (objp);
@@ -6446,6 +6447,11 @@ mark_object (Lisp_Object arg)
 	    VECTOR_MARK (ptr);
 	    break;
 
+	  case PVEC_BIGNUM:
+	    /* Nor in a bignum.  */
+	    VECTOR_MARK (ptr);
+	    break;
+
 	  case PVEC_SUBR:
 	    break;
 
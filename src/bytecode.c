@@ -962,24 +962,22 @@ exec_byte_code (Lisp_Object bytestr, Lisp_Object vector, Lisp_Object maxdepth,
 	  NEXT;
 
 	CASE (Bsub1):
-	  TOP = INTEGERP (TOP) ? make_number (XINT (TOP) - 1) : Fsub1 (TOP);
+	  TOP = (INTEGERP (TOP) && XINT (TOP) != MOST_NEGATIVE_FIXNUM
+		 ? make_number (XINT (TOP) - 1) : Fsub1 (TOP));
 	  NEXT;
 
 	CASE (Badd1):
-	  TOP = INTEGERP (TOP) ? make_number (XINT (TOP) + 1) : Fadd1 (TOP);
+	  TOP = (INTEGERP (TOP) && XINT (TOP) != MOST_POSITIVE_FIXNUM
+		 ? make_number (XINT (TOP) + 1) : Fadd1 (TOP));
 	  NEXT;
 
 	CASE (Beqlsign):
 	  {
 	    Lisp_Object v2 = POP, v1 = TOP;
-	    if (FLOATP (v1) || FLOATP (v2))
-	      TOP = arithcompare (v1, v2, ARITH_EQUAL);
+	    if (INTEGERP (v1) && INTEGERP (v2))
+	      TOP = EQ (v1, v2) ? Qt : Qnil;
 	    else
-	      {
-		CHECK_NUMBER_OR_FLOAT_COERCE_MARKER (v1);
-		CHECK_NUMBER_OR_FLOAT_COERCE_MARKER (v2);
-		TOP = EQ (v1, v2) ? Qt : Qnil;
-	      }
+	      TOP = arithcompare (v1, v2, ARITH_EQUAL);
 	    NEXT;
 	  }
 
@@ -1017,7 +1015,8 @@ exec_byte_code (Lisp_Object bytestr, Lisp_Object vector, Lisp_Object maxdepth,
 	  NEXT;
 
 	CASE (Bnegate):
-	  TOP = INTEGERP (TOP) ? make_number (- XINT (TOP)) : Fminus (1, &TOP);
+	  TOP = (INTEGERP (TOP) && XINT (TOP) != MOST_NEGATIVE_FIXNUM
+		 ? make_number (- XINT (TOP)) : Fminus (1, &TOP));
 	  NEXT;
 
 	CASE (Bplus):
@@ -1211,11 +1210,11 @@ exec_byte_code (Lisp_Object bytestr, Lisp_Object vector, Lisp_Object maxdepth,
 	  NEXT;
 
 	CASE (Bupcase):
-	  TOP = Fupcase (TOP);
+	  TOP = Fupcase (TOP, Qnil);
 	  NEXT;
 
 	CASE (Bdowncase):
-	  TOP = Fdowncase (TOP);
+	  TOP = Fdowncase (TOP, Qnil);
 	  NEXT;
 
 	CASE (Bstringeqlsign):
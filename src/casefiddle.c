@@ -30,6 +30,14 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #include "composite.h"
 #include "keymap.h"
 
+/* Languages whose casing rules differ from the default ones.  */
+enum casing_language
+{
+  CASING_LANG_DEFAULT,
+  CASING_LANG_TURKIC,		/* Turkish and Azerbaijani.  */
+  CASING_LANG_LITHUANIAN
+};
+
 /* State for casing individual characters.  */
 struct casing_context
 {
@@ -52,16 +60,90 @@ struct casing_context
 
   /* Whether the context is within a word.  */
   bool inword;
+
+  /* The language whose casing rules apply.  */
+  enum casing_language lang;
+
+  /* The canonical combining class char-table, or nil.  Non-nil implies
+     lang is not CASING_LANG_DEFAULT.  */
+  Lisp_Object combining_class_table;
+
+  /* Whether the context follows an upcased Soft_Dotted character, or a
+     downcased capital I, with no character of combining class 0 or 230
+     (Above) since.  A dot above there is dropped.  */
+  bool after_soft_dotted;
+  bool after_capital_i;
+};
+
+/* The text following the character being cased, which some casing rules
+   depend on.  */
+struct casing_next
+{
+  /* If non-NULL, the text is in a string, starting here.  */
+  const unsigned char *p;
+
+  /* Otherwise, the text is in the current buffer, starting at this byte
+     position.  */
+  ptrdiff_t pos_byte;
+
+  /* The number of characters in the text.  */
+  ptrdiff_t nchars;
 };
 
-/* Initialize CTX structure for casing characters.  */
+/* Return the next character of NEXT and advance over it, or return -1 if
+   there are no more characters.  */
+static int
+casing_next_char (struct casing_next *next)
+{
+  int ch;
+
+  if (next->nchars <= 0)
+    return -1;
+  next->nchars--;
+  if (next->p)
+    return STRING_CHAR_ADVANCE (next->p);
+  ch = FETCH_MULTIBYTE_CHAR (next->pos_byte);
+  INC_POS (next->pos_byte);
+  return ch;
+}
+
+/* Return the casing language named by LANG, a string or symbol such as
+   "tr", `lt' or "az_AZ", or nil for the default rules.  Languages without
+   rules of their own use the default ones.  */
+static enum casing_language
+casing_language (Lisp_Object lang)
+{
+  if (NILP (lang))
+    return CASING_LANG_DEFAULT;
+  if (SYMBOLP (lang))
+    lang = SYMBOL_NAME (lang);
+  CHECK_STRING (lang);
+
+  const char *name = SSDATA (lang);
+  if (SBYTES (lang) > 2 && !strchr ("_-.@", name[2]))
+    return CASING_LANG_DEFAULT;
+  if (!strncmp (name, "tr", 2) || !strncmp (name, "az", 2))
+    return CASING_LANG_TURKIC;
+  if (!strncmp (name, "lt", 2))
+    return CASING_LANG_LITHUANIAN;
+  return CASING_LANG_DEFAULT;
+}
+
+/* Initialize CTX structure for casing characters, with the rules of the
+   language LANG.  */
 static void
 prepare_casing_context (struct casing_context *ctx,
-			enum case_action flag, bool inbuffer)
+			enum case_action flag, bool inbuffer, Lisp_Object lang)
 {
   ctx->flag = flag;
   ctx->inbuffer = inbuffer;
   ctx->inword = false;
+  ctx->lang = casing_language (lang);
+  ctx->combining_class_table
+    = (ctx->lang == CASING_LANG_DEFAULT ? Qnil
+       : uniprop_table (Qcanonical_combining_class));
+  ctx->after_soft_dotted = false;
+  ctx->after_capital_i = false;
   ctx->titlecase_char_table
     = (flag < CASE_CAPITALIZE ? Qnil
        : uniprop_table (Qtitlecase));
@@ -90,6 +172,140 @@ struct casing_str_buf
   unsigned char len_bytes;
 };
 
+enum { COMBINING_DOT_ABOVE = 0x0307 };
+enum { COMBINING_CLASS_ABOVE = 230 };
+enum { LATIN_CAPITAL_LETTER_I_WITH_DOT_ABOVE = 0x0130 }; /* İ */
+enum { LATIN_SMALL_LETTER_DOTLESS_I = 0x0131 }; /* ı */
+
+/* Return the canonical combining class of CH, based on CTX.  */
+static int
+combining_class (struct casing_context *ctx, int ch)
+{
+  Lisp_Object cc;
+
+  if (NILP (ctx->combining_class_table))
+    return 0;
+  cc = CHAR_TABLE_REF (ctx->combining_class_table, ch);
+  return INTEGERP (cc) ? XINT (cc) : 0;
+}
+
+/* Return true if CH has the Unicode property Soft_Dotted: it loses its dot
+   when an accent is put above it.  */
+static bool
+soft_dotted_p (int ch)
+{
+  static const int soft_dotted[][2] = {
+    { 0x0069, 0x006A }, { 0x012F, 0x012F }, { 0x0249, 0x0249 },
+    { 0x0268, 0x0268 }, { 0x029D, 0x029D }, { 0x02B2, 0x02B2 },
+    { 0x03F3, 0x03F3 }, { 0x0456, 0x0456 }, { 0x0458, 0x0458 },
+    { 0x1D62, 0x1D62 }, { 0x1D96, 0x1D96 }, { 0x1DA4, 0x1DA4 },
+    { 0x1DA8, 0x1DA8 }, { 0x1E2D, 0x1E2D }, { 0x1ECB, 0x1ECB },
+    { 0x2071, 0x2071 }, { 0x2148, 0x2149 }, { 0x2C7C, 0x2C7C },
+    { 0x1D422, 0x1D423 }, { 0x1D456, 0x1D457 }, { 0x1D48A, 0x1D48B },
+    { 0x1D4BE, 0x1D4BF }, { 0x1D4F2, 0x1D4F3 }, { 0x1D526, 0x1D527 },
+    { 0x1D55A, 0x1D55B }, { 0x1D58E, 0x1D58F }, { 0x1D5C2, 0x1D5C3 },
+    { 0x1D5F6, 0x1D5F7 }, { 0x1D62A, 0x1D62B }, { 0x1D65E, 0x1D65F },
+    { 0x1D692, 0x1D693 },
+  };
+
+  for (int i = 0; i < ARRAYELTS (soft_dotted); i++)
+    if (soft_dotted[i][0] <= ch && ch <= soft_dotted[i][1])
+      return true;
+  return false;
+}
+
+/* Based on CTX, return the first character of NEXT that has combining
+   class 0 or 230 (Above), or -1 if there is none.  NEXT may be NULL if the
+   following text is not known.  */
+static int
+next_base_or_above (struct casing_context *ctx,
+		    const struct casing_next *next)
+{
+  struct casing_next rest;
+  int ch, cc;
+
+  if (!next)
+    return -1;
+  rest = *next;
+  while ((ch = casing_next_char (&rest)) >= 0)
+    {
+      cc = combining_class (ctx, ch);
+      if (cc == 0 || cc == COMBINING_CLASS_ABOVE)
+	break;
+    }
+  return ch;
+}
+
+/* Apply the language-specific rules of SpecialCasing.txt for CTX->lang to
+   CH, which is being cased according to FLAG, one of CASE_UP, CASE_DOWN or
+   CASE_CAPITALIZE.  NEXT is the text after CH, or NULL.  If a rule applies,
+   store the characters CH maps to in CASED and return their number;
+   otherwise return -1.  */
+static int
+case_language_character (int cased[3], struct casing_context *ctx,
+			 enum case_action flag, int ch,
+			 const struct casing_next *next)
+{
+  int next_ch;
+
+  switch (ctx->lang)
+    {
+    case CASING_LANG_TURKIC:
+      if (flag != CASE_DOWN)
+	{
+	  if (ch != 'i')
+	    return -1;
+	  cased[0] = LATIN_CAPITAL_LETTER_I_WITH_DOT_ABOVE;
+	  return 1;
+	}
+      if (ch == LATIN_CAPITAL_LETTER_I_WITH_DOT_ABOVE)
+	{
+	  cased[0] = 'i';
+	  return 1;
+	}
+      /* I followed by a dot above is an i; the dot is dropped by
+	 case_character_impl.  */
+      if (ch == 'I' && next_base_or_above (ctx, next) != COMBINING_DOT_ABOVE)
+	{
+	  cased[0] = LATIN_SMALL_LETTER_DOTLESS_I;
+	  return 1;
+	}
+      return -1;
+
+    case CASING_LANG_LITHUANIAN:
+      /* The dot of a soft-dotted letter is written out when it gets an
+	 accent above, and dropped by case_character_impl when the letter
+	 is upcased.  */
+      if (flag != CASE_DOWN)
+	return -1;
+      switch (ch)
+	{
+	case 0x00CC: /* Ì */
+	case 0x00CD: /* Í */
+	case 0x0128: /* Ĩ */
+	  cased[0] = 'i';
+	  cased[1] = COMBINING_DOT_ABOVE;
+	  cased[2] = ch == 0x00CC ? 0x0300 : ch == 0x00CD ? 0x0301 : 0x0303;
+	  return 3;
+
+	case 'I':
+	case 'J':
+	case 0x012E: /* Į */
+	  next_ch = next_base_or_above (ctx, next);
+	  if (next_ch < 0
+	      || combining_class (ctx, next_ch) != COMBINING_CLASS_ABOVE)
+	    return -1;
+	  cased[0] = ch == 0x012E ? 0x012F : ch + ('a' - 'A');
+	  cased[1] = COMBINING_DOT_ABOVE;
+	  return 2;
+	}
+      return -1;
+
+    default:
+      return -1;
+    }
+}
+
 /* Based on CTX, case character CH.  If BUF is NULL, return cased character.
    Otherwise, if BUF is non-NULL, save result in it and return whether the
    character has been changed.
@@ -98,17 +314,47 @@ struct casing_str_buf
    use case_single_character or case_character instead.  */
 static int
 case_character_impl (struct casing_str_buf *buf,
-		     struct casing_context *ctx, int ch)
+		     struct casing_context *ctx, int ch,
+		     const struct casing_next *next)
 {
   enum case_action flag;
   Lisp_Object prop;
-  int cased;
+  int cased, language_cased[3], n;
+  struct casing_context before = *ctx;
 
   /* Update inword state */
   bool was_inword = ctx->inword;
   ctx->inword = SYNTAX (ch) == Sword &&
     (!ctx->inbuffer || was_inword || !syntax_prefix_flag_p (ch));
 
+  /* Update the state the language rules depend on.  */
+  if (ctx->lang != CASING_LANG_DEFAULT)
+    {
+      int cc = combining_class (ctx, ch);
+      if (cc == 0 || cc == COMBINING_CLASS_ABOVE)
+	{
+	  bool up = (ctx->flag == CASE_UP
+		     || (ctx->flag != CASE_DOWN && !was_inword));
+	  bool down = (ctx->flag == CASE_DOWN
+		       || (ctx->flag == CASE_CAPITALIZE && was_inword));
+	  ctx->after_soft_dotted = (ctx->lang == CASING_LANG_LITHUANIAN
+				    && up && soft_dotted_p (ch));
+	  ctx->after_capital_i = (ctx->lang == CASING_LANG_TURKIC
+				  && down && ch == 'I');
+	}
+    }
+
+  /* Drop the dot above that the cased character before made redundant.  */
+  if (ch == COMBINING_DOT_ABOVE
+      && (before.after_soft_dotted || before.after_capital_i))
+    {
+      if (!buf)
+	return ch;
+      buf->len_chars = 0;
+      buf->len_bytes = 0;
+      return 1;
+    }
+
   /* Normalize flag so its one of CASE_UP, CASE_DOWN or CASE_CAPITALIZE.  */
   if (ctx->flag == CASE_CAPITALIZE)
     flag = ctx->flag - was_inword;
@@ -122,6 +368,21 @@ case_character_impl (struct casing_str_buf *buf,
       goto done;
     }
 
+  /* Look through the rules of the language.  Without BUF, only a
+     character can be returned.  */
+  n = case_language_character (language_cased, &before, flag, ch, next);
+  if (n >= 0)
+    {
+      if (!buf)
+	return n == 1 ? language_cased[0] : ch;
+      buf->len_chars = n;
+      buf->len_bytes = 0;
+      for (int i = 0; i < n; i++)
+	buf->len_bytes += CHAR_STRING (language_cased[i],
+				       buf->data + buf->len_bytes);
+      return n > 1 || language_cased[0] != ch;
+    }
+
   /* Look through the special casing entries.  */
   if (buf && !NILP (ctx->specialcase_char_tables[flag]))
     {
@@ -186,30 +447,32 @@ enum { GREEK_SMALL_LETTER_FINAL_SIGMA = 0x03C2 }; /* ς */
 static inline int
 case_single_character (struct casing_context *ctx, int ch)
 {
-  return case_character_impl (NULL, ctx, ch);
+  return case_character_impl (NULL, ctx, ch, NULL);
 }
 
 /* Save in BUF result of casing character CH.  Return whether casing changed the
    character.
 
-   If not-NULL, NEXT points to the next character in the cased string.  If NULL,
-   it is assumed current character is the last one being cased.  This is used to
-   apply some rules which depend on proceeding state.
+   NEXT is the rest of the text being cased, after CH.  This is used to apply
+   some rules which depend on proceeding state.
 
    This is like case_single_character but also handles one-to-many casing
    rules.  */
 static bool
 case_character (struct casing_str_buf *buf, struct casing_context *ctx,
-		int ch, const unsigned char *next)
+		int ch, const struct casing_next *next)
 {
   bool was_inword = ctx->inword;
-  bool changed = case_character_impl (buf, ctx, ch);
+  bool changed = case_character_impl (buf, ctx, ch, next);
+  struct casing_next rest = *next;
+  int next_ch;
 
   /* If we have just down-cased a capital sigma and the next character no longer
      has a word syntax (i.e. current character is end of word), use final
      sigma.  */
   if (was_inword && ch == GREEK_CAPITAL_LETTER_SIGMA && changed
-      && (!next || SYNTAX (STRING_CHAR (next)) != Sword))
+      && ((next_ch = casing_next_char (&rest)) < 0
+	  || SYNTAX (next_ch) != Sword))
     {
       buf->len_bytes = CHAR_STRING (GREEK_SMALL_LETTER_FINAL_SIGMA, buf->data);
       buf->len_chars = 1;
@@ -277,8 +540,8 @@ do_casify_multibyte_string (struct casing_context *ctx, Lisp_Object obj)
       if (dst_end - o < sizeof (struct casing_str_buf))
 	string_overflow ();
       int ch = STRING_CHAR_ADVANCE (src);
-      case_character ((struct casing_str_buf *) o, ctx, ch,
-		      size > 1 ? src : NULL);
+      struct casing_next next = { src, 0, size - 1 };
+      case_character ((struct casing_str_buf *) o, ctx, ch, &next);
       n += ((struct casing_str_buf *) o)->len_chars;
       o += ((struct casing_str_buf *) o)->len_bytes;
     }
@@ -314,10 +577,10 @@ do_casify_unibyte_string (struct casing_context *ctx, Lisp_Object obj)
 /* Common case-conversion routine, used by upcase, capitalize, etc. */
 
 Lisp_Object
-casify_object (enum case_action flag, Lisp_Object obj)
+casify_object (enum case_action flag, Lisp_Object obj, Lisp_Object lang)
 {
   struct casing_context ctx;
-  prepare_casing_context (&ctx, flag, false);
+  prepare_casing_context (&ctx, flag, false, lang);
 
   if (NATNUMP (obj))
     return do_casify_natnum (&ctx, obj);
@@ -387,8 +650,8 @@ do_casify_multibyte_region (struct casing_context *ctx,
       int len;
       int ch = STRING_CHAR_AND_LENGTH (BYTE_POS_ADDR (pos_byte), len);
       struct casing_str_buf buf;
-      if (!case_character (&buf, ctx, ch,
-			   size > 1 ? BYTE_POS_ADDR (pos_byte + len) : NULL))
+      struct casing_next next = { NULL, pos_byte + len, size - 1 };
+      if (!case_character (&buf, ctx, ch, &next))
 	{
 	  pos_byte += len;
 	  ++pos;
@@ -427,10 +690,12 @@ do_casify_multibyte_region (struct casing_context *ctx,
 }
 
 /* flag is CASE_UP, CASE_DOWN or CASE_CAPITALIZE or CASE_CAPITALIZE_UP.  b and
-   e specify range of buffer to operate on.  Return character position of the
-   end of the region after changes.  */
+   e specify range of buffer to operate on.  lang names the language whose
+   casing rules apply, or is nil.  Return character position of the end of
+   the region after changes.  */
 ptrdiff_t
-casify_region (enum case_action flag, Lisp_Object b, Lisp_Object e)
+casify_region (enum case_action flag, Lisp_Object b, Lisp_Object e,
+	       Lisp_Object lang)
 {
   ptrdiff_t added;
   struct casing_context ctx;
@@ -442,7 +707,7 @@ casify_region (enum case_action flag, Lisp_Object b, Lisp_Object e)
     /* Not modifying because nothing marked.  */
     return end;
   modify_text (start, end);
-  prepare_casing_context (&ctx, flag, true);
+  prepare_casing_context (&ctx, flag, true, lang);
 
   ptrdiff_t orig_end = end;
   record_delete (start, make_buffer_string (start, end, true), false);
@@ -516,6 +516,8 @@ signed char HEXDIGIT_CONST hexdigit[UCHAR_MAX + 1] =
     ['a'] = 10, ['b'] = 11, ['c'] = 12, ['d'] = 13, ['e'] = 14, ['f'] = 15
   };
 
+extern void rust_syms_of_character (void);
+
 void
 syms_of_character (void)
 {
@@ -594,4 +596,6 @@ All Unicode characters have one of the following values (symbol):
 See The Unicode Standard for the meaning of those values.  */);
   /* The correct char-table is setup in characters.el.  */
   Vunicode_category_table = Qnil;
+
+  rust_syms_of_character ();
 }
@@ -61,7 +61,6 @@ Lisp_Object file_attributes_c_internal (char const *,
 					Lisp_Object,
 					Lisp_Object,
 					Lisp_Object);
-Lisp_Object filemode_string(Lisp_Object);
 #endif
 
 #ifdef WINDOWSNT
@@ -1020,43 +1019,7 @@ file_attributes_c_internal (char const *name,
 #endif /* !WINDOWSNT */
 
 
-#ifndef WINDOWSNT
-/*
- * (temp) Filemode support for Remacs
- */
-
-static Lisp_Object
-filemode_string_core (int fd, char const *name)
-{
-  struct stat s;
-  int lstat_result;
-
-  /* An array to hold the mode string generated by filemodestring, 
-     including its terminating space and null byte.  */
-  char modes[sizeof "-rwxr-xr-x "];
-
-  lstat_result = fstatat (fd, name, &s, AT_SYMLINK_NOFOLLOW);
-
-  if (lstat_result < 0)
-    return Qnil;
-
-  filemodestring (&s, modes);
-
-  return make_string(modes, 10);
-}
-
-Lisp_Object
-filemode_string (Lisp_Object filename)
-{
-  Lisp_Object encoded;
-  filename = internal_condition_case_2 (Fexpand_file_name, filename, Qnil,
-					Qt, Fidentity);
-  if (!STRINGP (filename))
-    return Qnil;
-  encoded = ENCODE_FILE (filename);
-  return filemode_string_core (AT_FDCWD, SSDATA (encoded));
-}
-#endif /* !WINDOWSNT */
+extern void rust_syms_of_dired (void);
 
 void
 syms_of_dired (void)
@@ -1080,4 +1043,6 @@ these strings or when displaying a list of completions.
 It ignores directory names if they match any string in this list which
 ends in a slash.  */);
   Vcompletion_ignored_extensions = Qnil;
+
+  rust_syms_of_dired ();
 }
@@ -96,6 +96,17 @@ void general_insert_function (void (*) (const char *, ptrdiff_t), void (*) (Lisp
 
 enum { tzeqlen = sizeof "TZ=" - 1 };
 
+/* Conversions for time zones named by zoneinfo files, done in Rust
+   without setting TZ.  They return false if ZONE is not such a name.
+   They fill in or rely on tm_zone and tm_gmtoff.  */
+#if defined HAVE_TM_ZONE && HAVE_TM_GMTOFF && !defined WINDOWSNT
+extern bool rust_tz_localtime (Lisp_Object, time_t, struct tm *);
+extern bool rust_tz_mktime (Lisp_Object, struct tm const *, time_t *);
+#else
+# define rust_tz_localtime(zone, t, tm) false
+# define rust_tz_mktime(zone, tm, t) false
+#endif
+
 /* Time zones equivalent to current local time and to UTC, respectively.  */
 static timezone_t local_tz;
 static timezone_t const utc_tz = 0;
@@ -812,8 +823,8 @@ DEFUN ("format-time-string", Fformat_time_string, Sformat_time_string, 1, 3, 0,
        doc: /* Use FORMAT-STRING to format the time TIME, or now if omitted or nil.
 TIME is specified as (HIGH LOW USEC PSEC), as returned by
 `current-time' or `file-attributes'.  It can also be a single integer
-number of seconds since the epoch.  The obsolete form (HIGH . LOW) is
-also still accepted.
+number of seconds since the epoch, or a cons (TICKS . HZ) meaning
+TICKS/HZ seconds since the epoch.
 
 The optional ZONE is omitted or nil for Emacs local time, t for
 Universal Time, `wall' for system wall clock time, or a string as in
@@ -884,6 +895,87 @@ usage: (format-time-string FORMAT-STRING &optional TIME ZONE)  */)
 			     t, zone, &tm);
 }
 
+/* Copy FORMATLEN bytes of FORMAT to DST, replacing each %s directive
+   with the decimal seconds count T, padded as the directive says.
+   Return the number of bytes written; if DST is null, just count them.
+   nstrftime computes %s from the broken-down time and its timezone,
+   which is wrong when a zone handled by rust_tz_localtime is passed to
+   it as UTC, so such callers substitute %s beforehand.  */
+static ptrdiff_t
+substitute_epoch_seconds (char *dst, char const *format, ptrdiff_t formatlen,
+			  time_t t)
+{
+  char digits[INT_STRLEN_BOUND (time_t) + 1];
+  bool negative = t < 0;
+  int ndigits = 0;
+  do
+    {
+      int d = t % 10;
+      digits[sizeof digits - 1 - ndigits++] = '0' + (negative ? -d : d);
+      t /= 10;
+    }
+  while (t != 0);
+
+  ptrdiff_t len = 0;
+  for (ptrdiff_t i = 0; i < formatlen; )
+    {
+      ptrdiff_t start = i;
+      if (format[i++] != '%')
+	{
+	  if (dst)
+	    dst[len] = format[start];
+	  len++;
+	  continue;
+	}
+
+      char pad = '0';
+      for (; i < formatlen && format[i] && strchr ("_-0^#", format[i]); i++)
+	if (format[i] != '^' && format[i] != '#')
+	  pad = format[i];
+      ptrdiff_t width = 0;
+      for (; i < formatlen && c_isdigit (format[i]); i++)
+	width = min (width * 10 + format[i] - '0', STRING_BYTES_BOUND);
+      for (; i < formatlen && (format[i] == 'E' || format[i] == 'O'); i++)
+	continue;
+
+      if (i < formatlen && format[i] == 's')
+	{
+	  i++;
+	  ptrdiff_t npad = (pad == '-' ? 0
+			    : max (0, width - ndigits - negative));
+	  if (pad == '_')
+	    {
+	      for (; 0 < npad; npad--, len++)
+		if (dst)
+		  dst[len] = ' ';
+	    }
+	  if (negative)
+	    {
+	      if (dst)
+		dst[len] = '-';
+	      len++;
+	    }
+	  for (; 0 < npad; npad--, len++)
+	    if (dst)
+	      dst[len] = '0';
+	  if (dst)
+	    memcpy (dst + len, digits + sizeof digits - ndigits, ndigits);
+	  len += ndigits;
+	}
+      else
+	{
+	  /* Copy the directive, including its conversion character, so
+	     that the second % of %% does not start a directive.  */
+	  if (i < formatlen)
+	    i++;
+	  if (dst)
+	    memcpy (dst + len, format + start, i - start);
+	  len += i - start;
+	}
+    }
+  return len;
+}
+
 static Lisp_Object
 format_time_string (char const *format, ptrdiff_t formatlen,
 		    struct timespec t, Lisp_Object zone, struct tm *tmp)
@@ -895,16 +987,31 @@ format_time_string (char const *format, ptrdiff_t formatlen,
   int ns = t.tv_nsec;
   USE_SAFE_ALLOCA;
 
-  timezone_t tz = tzlookup (zone, false);
+  timezone_t tz;
   /* On some systems, like 32-bit MinGW, tv_sec of struct timespec is
      a 64-bit type, but time_t is a 32-bit type.  emacs_localtime_rz
      expects a pointer to time_t value.  */
   time_t tsec = t.tv_sec;
-  tmp = emacs_localtime_rz (tz, &tsec, tmp);
-  if (! tmp)
+  if (rust_tz_localtime (zone, tsec, tmp))
     {
-      xtzfree (tz);
-      time_overflow ();
+      /* The zone is described by tm_zone and tm_gmtoff.  */
+      tz = utc_tz;
+      ptrdiff_t newlen = substitute_epoch_seconds (NULL, format, formatlen,
+						   tsec);
+      char *newformat = SAFE_ALLOCA (newlen);
+      substitute_epoch_seconds (newformat, format, formatlen, tsec);
+      format = newformat;
+      formatlen = newlen;
+    }
+  else
+    {
+      tz = tzlookup (zone, false);
+      tmp = emacs_localtime_rz (tz, &tsec, tmp);
+      if (! tmp)
+	{
+	  xtzfree (tz);
+	  time_overflow ();
+	}
     }
   synchronize_system_time_locale ();
 
@@ -939,7 +1046,7 @@ DEFUN ("decode-time", Fdecode_time, Sdecode_time, 0, 2, 0,
 The optional TIME should be a list of (HIGH LOW . IGNORED),
 as from `current-time' and `file-attributes', or nil to use the
 current time.  It can also be a single integer number of seconds since
-the epoch.  The obsolete form (HIGH . LOW) is also still accepted.
+the epoch, or a cons (TICKS . HZ) meaning TICKS/HZ seconds.
 
 The optional ZONE is omitted or nil for Emacs local time, t for
 Universal Time, `wall' for system wall clock time, or a string as in
@@ -963,9 +1070,13 @@ usage: (decode-time &optional TIME ZONE)  */)
 {
   time_t time_spec = lisp_seconds_argument (specified_time);
   struct tm local_tm, gmt_tm;
-  timezone_t tz = tzlookup (zone, false);
-  struct tm *tm = emacs_localtime_rz (tz, &time_spec, &local_tm);
-  xtzfree (tz);
+  struct tm *tm = &local_tm;
+  if (! rust_tz_localtime (zone, time_spec, &local_tm))
+    {
+      timezone_t tz = tzlookup (zone, false);
+      tm = emacs_localtime_rz (tz, &time_spec, &local_tm);
+      xtzfree (tz);
+    }
 
   if (! (tm
 	 && MOST_NEGATIVE_FIXNUM - TM_YEAR_BASE <= local_tm.tm_year
@@ -1042,9 +1153,12 @@ usage: (encode-time SECOND MINUTE HOUR DAY MONTH YEAR &optional ZONE)  */)
   tm.tm_year = check_tm_member (args[5], TM_YEAR_BASE);
   tm.tm_isdst = -1;
 
-  timezone_t tz = tzlookup (zone, false);
-  value = emacs_mktime_z (tz, &tm);
-  xtzfree (tz);
+  if (! rust_tz_mktime (zone, &tm, &value))
+    {
+      timezone_t tz = tzlookup (zone, false);
+      value = emacs_mktime_z (tz, &tm);
+      xtzfree (tz);
+    }
 
   if (value == (time_t) -1)
     time_overflow ();
@@ -1066,8 +1180,8 @@ If SPECIFIED-TIME is given, it is a time to format instead of the
 current time.  The argument should have the form (HIGH LOW . IGNORED).
 Thus, you can use times obtained from `current-time' and from
 `file-attributes'.  SPECIFIED-TIME can also be a single integer number
-of seconds since the epoch.  The obsolete form (HIGH . LOW) is also
-still accepted.
+of seconds since the epoch, or a cons (TICKS . HZ) meaning TICKS/HZ
+seconds since the epoch.
 
 The optional ZONE is omitted or nil for Emacs local time, t for
 Universal Time, `wall' for system wall clock time, or a string as in
@@ -1148,8 +1262,8 @@ If SPECIFIED-TIME is given, the time zone offset is determined from it
 instead of using the current time.  The argument should have the form
 \(HIGH LOW . IGNORED).  Thus, you can use times obtained from
 `current-time' and from `file-attributes'.  SPECIFIED-TIME can also be
-a single integer number of seconds since the epoch.  The obsolete form
-(HIGH . LOW) is also still accepted.
+a single integer number of seconds since the epoch, or a cons
+\(TICKS . HZ) meaning TICKS/HZ seconds since the epoch.
 
 The optional ZONE is omitted or nil for Emacs local time, t for
 Universal Time, `wall' for system wall clock time, or a string as in
@@ -2616,6 +2730,24 @@ styled_format (ptrdiff_t nargs, Lisp_Object *args, bool message)
 	  bool float_conversion
 	    = conversion == 'e' || conversion == 'f' || conversion == 'g';
 
+	  /* sprintf cannot format bignums, so format them in Rust and
+	     treat the result like 's'.  But don't do this conversion
+	     twice, which might happen after retrying.  */
+	  if (BIGNUMP (args[n])
+	      && (conversion == 'd' || conversion == 'i' || conversion == 'o'
+		  || conversion == 'x' || conversion == 'X'))
+	    {
+	      if (EQ (arg, args[n]))
+		spec->argument = arg
+		  = format_bignum (arg, conversion, plus_flag, space_flag,
+				   sharp_flag, precision_given ? precision : -1,
+				   zero_flag ? field_width : 0);
+	      conversion = 's';
+	      precision_given = false;
+	    }
+	  else if (BIGNUMP (arg) && float_conversion)
+	    spec->argument = arg = Ffloat (arg);
+
 	  if (conversion == 's')
 	    {
 	      if (format == end && format - format_start == 2
@@ -197,6 +197,11 @@ int initial_argc;
 static void sort_args (int argc, char **argv);
 static void syms_of_emacs (void);
 
+#if defined USE_FILE_NOTIFY && !defined WINDOWSNT
+/* Defined in filewatch.rs.  */
+extern void rust_syms_of_filewatch (void);
+#endif
+
 /* C99 needs each string to be at most 4095 characters, and the usage
    strings below are split to not overflow this limit.  */
 static char const *const usage_message[] =
@@ -1531,6 +1536,10 @@ Using an Emacs configured with --with-x-toolkit=lucid does not have this problem
       syms_of_gfilenotify ();
 #endif /* HAVE_GFILENOTIFY */
 
+#if defined USE_FILE_NOTIFY && !defined WINDOWSNT
+      rust_syms_of_filewatch ();
+#endif
+
 #ifdef HAVE_DBUS
       syms_of_dbusbind ();
 #endif /* HAVE_DBUS */
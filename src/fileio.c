@@ -100,6 +100,7 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #endif
 
 #include "commands.h"
+#include "remacs-lib.h"
 
 /* True during writing of auto-save files.  */
 static bool auto_saving;
@@ -379,7 +380,7 @@ Given a Unix syntax file name, returns a string ending in slash.  */)
       dostounix_filename (SSDATA (tem_fn));
 #ifdef WINDOWSNT
       if (!NILP (Vw32_downcase_file_names))
-	tem_fn = Fdowncase (tem_fn);
+	tem_fn = Fdowncase (tem_fn, Qnil);
 #endif
     }
   else
@@ -526,7 +527,7 @@ is already present.  */)
 
 #ifdef WINDOWSNT
   if (!NILP (Vw32_downcase_file_names))
-    file = Fdowncase (file);
+    file = Fdowncase (file, Qnil);
 #endif
   buf = SAFE_ALLOCA (SBYTES (file) + file_name_as_directory_slop + 1);
   length = file_name_as_directory (buf, SSDATA (file), SBYTES (file),
@@ -608,7 +609,7 @@ In Unix-syntax, this function just removes the final slash.  */)
 
 #ifdef WINDOWSNT
   if (!NILP (Vw32_downcase_file_names))
-    directory = Fdowncase (directory);
+    directory = Fdowncase (directory, Qnil);
 #endif
   buf = SAFE_ALLOCA (SBYTES (directory) + 1);
   length = directory_file_name (buf, SSDATA (directory), SBYTES (directory),
@@ -651,7 +652,9 @@ This function does not grok magic file names.  */)
   int kind = (NILP (dir_flag) ? GT_FILE
 	      : EQ (dir_flag, make_number (0)) ? GT_NOCREATE
 	      : GT_DIR);
-  int fd = gen_tempname (data, suffix_len, O_BINARY | O_CLOEXEC, kind);
+  int fd = (kind == GT_DIR
+	    ? rust_make_temp_dir (data, suffix_len)
+	    : gen_tempname (data, suffix_len, O_BINARY | O_CLOEXEC, kind));
   bool failed = fd < 0;
   if (!failed)
     {
@@ -849,7 +852,7 @@ the root directory.  */)
 
 #ifdef WINDOWSNT
   if (!NILP (Vw32_downcase_file_names))
-    default_directory = Fdowncase (default_directory);
+    default_directory = Fdowncase (default_directory, Qnil);
 #endif
 
   /* Make a local copy of NAME to protect it from GC in DECODE_FILE below.  */
@@ -950,7 +953,7 @@ the root directory.  */)
 	    }
 #ifdef WINDOWSNT
 	  if (!NILP (Vw32_downcase_file_names))
-	    name = Fdowncase (name);
+	    name = Fdowncase (name, Qnil);
 #endif
 #else /* not DOS_NT */
 	  if (strcmp (nm, SSDATA (name)) != 0)
@@ -1347,7 +1350,7 @@ the root directory.  */)
     dostounix_filename (SSDATA (result));
 #ifdef WINDOWSNT
     if (!NILP (Vw32_downcase_file_names))
-      result = Fdowncase (result);
+      result = Fdowncase (result, Qnil);
 #endif
 #else  /* !DOS_NT */
     result = make_specified_string (target, -1, o - target, multibyte);
@@ -1669,7 +1672,7 @@ those `/' is discarded.  */)
     {
 #ifdef WINDOWSNT
       if (!NILP (Vw32_downcase_file_names))
-	filename = Fdowncase (filename);
+	filename = Fdowncase (filename, Qnil);
 #endif
       SAFE_FREE ();
       return filename;
@@ -1690,7 +1693,7 @@ those `/' is discarded.  */)
     {
       Lisp_Object xname = make_specified_string (xnm, -1, x - xnm, multibyte);
 
-      filename = Fdowncase (xname);
+      filename = Fdowncase (xname, Qnil);
     }
   else
 #endif
@@ -2195,8 +2198,8 @@ This is what happens in interactive use with M-x.  */)
   if (!NILP (Ffile_name_case_insensitive_p (file)))
     {
       newname = Fexpand_file_name (newname, Qnil);
-      case_only_rename = !NILP (Fstring_equal (Fdowncase (file),
-					       Fdowncase (newname)));
+      case_only_rename = !NILP (Fstring_equal (Fdowncase (file, Qnil),
+					       Fdowncase (newname, Qnil)));
     }
 #endif
 
@@ -297,24 +297,28 @@ typedef struct
   char user[MAX_LFINFO + 1 + sizeof " (pid )" - sizeof "."];
 } lock_info_type;
 
-/* Write the name of the lock file for FNAME into LOCKNAME.  Length
-   will be that of FNAME plus two more for the leading ".#", plus one
-   for the null.  */
-#define MAKE_LOCK_NAME(lockname, fname) \
-  (lockname = SAFE_ALLOCA (SBYTES (fname) + 2 + 1), \
-   fill_in_lock_file_name (lockname, fname))
-
-static void
-fill_in_lock_file_name (char *lockfile, Lisp_Object fn)
-{
-  char *last_slash = memrchr (SSDATA (fn), '/', SBYTES (fn));
-  char *base = last_slash + 1;
-  ptrdiff_t dirlen = base - SSDATA (fn);
-  memcpy (lockfile, SSDATA (fn), dirlen);
-  lockfile[dirlen] = '.';
-  lockfile[dirlen + 1] = '#';
-  strcpy (lockfile + dirlen + 2, base);
-}
+/* Functions defined in filelock.rs.  */
+extern Lisp_Object make_lock_file_name (Lisp_Object);
+extern void record_lock_file_name (Lisp_Object, const char *);
+extern Lisp_Object take_lock_file_name (Lisp_Object);
+extern void acquire_advisory_lock (const char *);
+extern void release_advisory_lock (const char *);
+extern void rust_syms_of_filelock (void);
+
+/* Write the name of the lock file for FNAME into LOCKNAME.  Where
+   the lock file lives depends on `lock-file-directory', so the name
+   is computed by make_lock_file_name and copied into stack storage
+   that stays put across garbage collection.  */
+#define MAKE_LOCK_NAME(lockname, fname)					\
+  COPY_LOCK_NAME (lockname, make_lock_file_name (fname))
+
+/* Copy the lock file name LOCK_NAME, a string, into LOCKNAME.  */
+#define COPY_LOCK_NAME(lockname, lock_name)				\
+  do {									\
+    Lisp_Object lock_name_ = (lock_name);				\
+    lockname = SAFE_ALLOCA (SBYTES (lock_name_) + 1);			\
+    memcpy (lockname, SSDATA (lock_name_), SBYTES (lock_name_) + 1);	\
+  } while (false)
 
 /* For some reason Linux kernels return EPERM on file systems that do
    not support hard or symbolic links.  This symbol documents the quirk.
@@ -455,7 +459,10 @@ lock_file_1 (char *lfname, bool force)
                         user_name, host_name, pid))
     return ENAMETOOLONG;
 
-  return create_lock_file (lfname, lock_info_str, force);
+  int err = create_lock_file (lfname, lock_info_str, force);
+  if (!err)
+    acquire_advisory_lock (lfname);
+  return err;
 }
 
 /* Return true if times A and B are no more than one second apart.  */
@@ -673,7 +680,7 @@ lock_file (Lisp_Object fn)
   fn = Fexpand_file_name (fn, Qnil);
 #ifdef WINDOWSNT
   /* Ensure we have only '/' separators, to avoid problems with
-     looking (inside fill_in_lock_file_name) for backslashes in file
+     looking (inside make_lock_file_name) for backslashes in file
      names encoded by some DBCS codepage.  */
   dostounix_filename (SSDATA (fn));
 #endif
@@ -701,7 +708,10 @@ lock_file (Lisp_Object fn)
       MAKE_LOCK_NAME (lfname, encoded_fn);
 
       /* Try to lock the lock.  */
-      if (0 < lock_if_free (&lock_info, lfname))
+      int locked = lock_if_free (&lock_info, lfname);
+      if (locked == 0)
+	record_lock_file_name (encoded_fn, lfname);
+      else if (0 < locked)
 	{
 	  /* Someone else has the lock.  Consider breaking it.  */
 	  Lisp_Object attack;
@@ -715,8 +725,8 @@ lock_file (Lisp_Object fn)
 	  attack = call2 (intern ("ask-user-about-lock"), fn,
 			  build_string (lock_info.user));
 	  /* Take the lock if the user said so.  */
-	  if (!NILP (attack))
-	    lock_file_1 (lfname, 1);
+	  if (!NILP (attack) && lock_file_1 (lfname, 1) == 0)
+	    record_lock_file_name (encoded_fn, lfname);
 	}
       SAFE_FREE ();
     }
@@ -731,10 +741,15 @@ unlock_file (Lisp_Object fn)
   fn = Fexpand_file_name (fn, Qnil);
   fn = ENCODE_FILE (fn);
 
-  MAKE_LOCK_NAME (lfname, fn);
+  /* Remove the lock file that was taken, even if `lock-file-directory'
+     has changed since.  */
+  COPY_LOCK_NAME (lfname, take_lock_file_name (fn));
 
   if (current_lock_owner (0, lfname) == 2)
-    unlink (lfname);
+    {
+      release_advisory_lock (lfname);
+      unlink (lfname);
+    }
 
   SAFE_FREE ();
 }
@@ -774,4 +789,6 @@ syms_of_filelock (void)
   DEFVAR_BOOL ("create-lockfiles", create_lockfiles,
 	       doc: /* Non-nil means use lockfiles to avoid editing collisions.  */);
   create_lockfiles = 1;
+
+  rust_syms_of_filelock ();
 }
@@ -102,8 +102,8 @@ If string STR1 is greater, the value is a positive number N;
 
       if (! NILP (ignore_case))
 	{
-	  c1 = XINT (Fupcase (make_number (c1)));
-	  c2 = XINT (Fupcase (make_number (c2)));
+	  c1 = XINT (Fupcase (make_number (c1), Qnil));
+	  c2 = XINT (Fupcase (make_number (c2), Qnil));
 	}
 
       if (c1 == c2)
@@ -1550,9 +1550,10 @@ cmpfn_eql (struct hash_table_test *ht,
 	   Lisp_Object key1,
 	   Lisp_Object key2)
 {
-  return (FLOATP (key1)
-	  && FLOATP (key2)
-	  && XFLOAT_DATA (key1) == XFLOAT_DATA (key2));
+  return ((FLOATP (key1)
+	   && FLOATP (key2)
+	   && XFLOAT_DATA (key1) == XFLOAT_DATA (key2))
+	  || (BIGNUMP (key1) && BIGNUMP (key2) && !NILP (Feql (key1, key2))));
 }
 
 
@@ -1608,7 +1609,8 @@ hashfn_equal (struct hash_table_test *ht, Lisp_Object key)
 static EMACS_UINT
 hashfn_eql (struct hash_table_test *ht, Lisp_Object key)
 {
-  return FLOATP (key) ? hashfn_equal (ht, key) : hashfn_eq (ht, key);
+  return (FLOATP (key) || BIGNUMP (key)
+	  ? hashfn_equal (ht, key) : hashfn_eq (ht, key));
 }
 
 /* Value is a hash code for KEY for use in hash table H which uses as
@@ -2214,6 +2216,22 @@ sxhash_bool_vector (Lisp_Object vec)
 }
 
 
+/* Return a hash for bignum BIGNUM.  */
+
+static EMACS_UINT
+sxhash_bignum (Lisp_Object bignum)
+{
+  struct Lisp_Bignum *b = XBIGNUM (bignum);
+  EMACS_UINT hash = b->negative;
+  ptrdiff_t i;
+
+  for (i = 0; i < b->ndigits; i++)
+    hash = sxhash_combine (hash, b->digits[i]);
+
+  return SXHASH_REDUCE (hash);
+}
+
+
 /* Return a hash code for OBJ.  DEPTH is the current depth in the Lisp
    structure.  Value is an unsigned integer clipped to INTMASK.  */
 
@@ -2250,6 +2268,8 @@ sxhash (Lisp_Object obj, int depth)
 	hash = sxhash_vector (obj, depth);
       else if (BOOL_VECTOR_P (obj))
 	hash = sxhash_bool_vector (obj);
+      else if (BIGNUMP (obj))
+	hash = sxhash_bignum (obj);
       else
 	/* Others are `equal' if they are `eq', so let's take their
 	   address as hash.  */
@@ -532,7 +532,7 @@ font_prop_validate_symbol (Lisp_Object prop, Lisp_Object val)
   if (! SYMBOLP (val))
     val = Qerror;
   else if (EQ (prop, QCregistry))
-    val = Fintern (Fdowncase (SYMBOL_NAME (val)), Qnil);
+    val = Fintern (Fdowncase (SYMBOL_NAME (val), Qnil), Qnil);
   return val;
 }
 
@@ -1777,7 +1777,7 @@ font_parse_family_registry (Lisp_Object family, Lisp_Object registry, Lisp_Objec
 	  AUTO_STRING_WITH_LEN (extra, &"*-*"[asterisk], 3 - asterisk);
 	  registry = concat2 (registry, extra);
 	}
-      registry = Fdowncase (registry);
+      registry = Fdowncase (registry, Qnil);
       ASET (font_spec, FONT_REGISTRY_INDEX, Fintern (registry, Qnil));
     }
 }
@@ -1181,7 +1181,7 @@ fs_query_fontset (Lisp_Object name, int name_pattern)
   Lisp_Object tem;
   int i;
 
-  name = Fdowncase (name);
+  name = Fdowncase (name, Qnil);
   if (name_pattern != 1)
     {
       tem = Frassoc (name, Vfontset_alias_alist);
@@ -1514,7 +1514,7 @@ appended.  By default, FONT-SPEC overrides the previous settings.  */)
 	family = SYMBOL_NAME (family);
       registry = AREF (font_spec, FONT_REGISTRY_INDEX);
       if (! NILP (registry))
-	registry = Fdowncase (SYMBOL_NAME (registry));
+	registry = Fdowncase (SYMBOL_NAME (registry), Qnil);
       AUTO_STRING (dash, "-");
       encoding = find_font_encoding (concat3 (family, dash, registry));
       if (NILP (encoding))
@@ -1686,7 +1686,7 @@ FONT-SPEC is a vector, a cons, or a string.  See the documentation of
 
   CHECK_STRING (name);
 
-  name = Fdowncase (name);
+  name = Fdowncase (name, Qnil);
   id = fs_query_fontset (name, 0);
   if (id < 0)
     {
@@ -1779,7 +1779,7 @@ fontset_from_font (Lisp_Object font_object)
   fontset = make_fontset (Qnil, name, Qnil);
   Vfontset_alias_alist = Fcons (Fcons (name, SYMBOL_NAME (alias)),
 				Vfontset_alias_alist);
-  alias = Fdowncase (AREF (font_object, FONT_NAME_INDEX));
+  alias = Fdowncase (AREF (font_object, FONT_NAME_INDEX), Qnil);
   Vfontset_alias_alist = Fcons (Fcons (name, alias), Vfontset_alias_alist);
   auto_fontset_alist = Fcons (Fcons (font_spec, fontset), auto_fontset_alist);
   font_spec = Ffont_spec (0, NULL);
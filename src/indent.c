@@ -645,18 +645,27 @@ scan_for_column (ptrdiff_t *endpos, EMACS_INT *goalcol, ptrdiff_t *prevcol)
 	      col += tab_width;
 	      col = col / tab_width * tab_width;
 	    }
-	  else if (multibyte && LEADING_CODE_P (c))
+	  else if (multibyte
+		   && (LEADING_CODE_P (c)
+		       || (040 <= c && c < 0177 && scan + 1 < end
+			   && ! ASCII_CHAR_P (FETCH_BYTE (scan_byte + 1)))))
 	    {
-	      /* Start of multi-byte form.  */
-	      unsigned char *ptr;
-	      int bytes, width;
-
-	      ptr = BYTE_POS_ADDR (scan_byte);
-	      MULTIBYTE_BYTES_WIDTH (ptr, dp, bytes, width);
+	      /* Start of multi-byte form, or of an ASCII character that
+		 a multi-byte one may extend, as in a keycap sequence.
+		 Measure the grapheme cluster it begins, without passing
+		 the places where invisible text or a composition may
+		 start.  */
+	      ptrdiff_t limit = min (end, next_boundary);
+	      ptrdiff_t chars, bytes;
+
+	      if (scan < cmp_it.stop_pos && cmp_it.stop_pos < limit)
+		limit = cmp_it.stop_pos;
+	      col += grapheme_cluster_width (scan, scan_byte, limit, dp,
+					     &chars, &bytes);
 	      /* Subtract one to compensate for the increment
 		 that is going to happen below.  */
+	      scan += chars - 1;
 	      scan_byte += bytes - 1;
-	      col += width;
 	    }
 	  else if (ctl_arrow && (c < 040 || c == 0177))
 	    col += 2;
@@ -8111,7 +8111,7 @@ parse_tool_bar_item (Lisp_Object key, Lisp_Object item)
       else
 	label = "";
 
-      new_lbl = Fupcase_initials (build_string (label));
+      new_lbl = Fupcase_initials (build_string (label), Qnil);
       if (SCHARS (new_lbl) <= tool_bar_max_label_size)
         set_prop (TOOL_BAR_ITEM_LABEL, new_lbl);
       else
@@ -8366,8 +8366,8 @@ read_char_minibuf_menu_prompt (int commandflag,
 		  Lisp_Object s
 		    = AREF (item_properties, ITEM_PROPERTY_NAME);
 
-		  upcased_event = Fupcase (event);
-		  downcased_event = Fdowncase (event);
+		  upcased_event = Fupcase (event, Qnil);
+		  downcased_event = Fdowncase (event, Qnil);
 		  char_matches = (XINT (upcased_event) == SREF (s, 0)
 				  || XINT (downcased_event) == SREF (s, 0));
 		  if (! char_matches)
@@ -969,6 +969,7 @@ enum pvec_type
   PVEC_MUTEX,
   PVEC_CONDVAR,
   PVEC_MODULE_FUNCTION,
+  PVEC_BIGNUM,
 
   /* These should be last, check internal_equal to see why.  */
   PVEC_COMPILED,
@@ -2773,6 +2774,40 @@ XFLOAT_DATA (Lisp_Object f)
   return XFLOAT (f)->u.data;
 }
 
+/* Lisp bignums, integers that do not fit in a fixnum.  The arithmetic
+   on them is done in Rust; a bignum is never in fixnum range.  */
+
+struct Lisp_Bignum
+  {
+    union vectorlike_header header;
+    /* True if the number is negative.  */
+    bool negative;
+    /* The number of digits in DIGITS.  */
+    ptrdiff_t ndigits;
+    /* The magnitude, in base 2**32, least significant digit first.
+       The most significant digit is never zero.  */
+    uint32_t digits[FLEXIBLE_ARRAY_MEMBER];
+  };
+
+INLINE bool
+BIGNUMP (Lisp_Object x)
+{
+  return PSEUDOVECTORP (x, PVEC_BIGNUM);
+}
+
+INLINE struct Lisp_Bignum *
+XBIGNUM (Lisp_Object a)
+{
+  eassert (BIGNUMP (a));
+  return XUNTAG (a, Lisp_Vectorlike);
+}
+
+/* Defined in Rust.  */
+extern Lisp_Object make_bignum_str (char const *, ptrdiff_t, int, bool);
+extern Lisp_Object bignum_to_string (Lisp_Object, int);
+extern Lisp_Object format_bignum (Lisp_Object, char, bool, bool, bool,
+				  ptrdiff_t, ptrdiff_t);
+
 /* Most hosts nowadays use IEEE floating point, so they use IEC 60559
    representations, have infinities and NaNs, and do not trap on
    exceptions.  Define IEEE_FLOATING_POINT if this host is one of the
@@ -3912,6 +3947,9 @@ extern int float_to_string (char *, double);
 extern void init_print_once (void);
 extern void syms_of_print (void);
 
+/* Defined in Rust.  */
+extern int float_to_shortest (char *, double);
+
 /* Defined in doprnt.c.  */
 extern ptrdiff_t doprnt (char *, ptrdiff_t, const char *, const char *,
 			 va_list);
@@ -4258,8 +4296,10 @@ extern void syms_of_callint (void);
 /* Defined in casefiddle.c.  */
 
 enum case_action {CASE_UP, CASE_DOWN, CASE_CAPITALIZE, CASE_CAPITALIZE_UP};
-Lisp_Object casify_object (enum case_action flag, Lisp_Object obj);
-ptrdiff_t casify_region (enum case_action flag, Lisp_Object b, Lisp_Object e);
+Lisp_Object casify_object (enum case_action flag, Lisp_Object obj,
+			   Lisp_Object lang);
+ptrdiff_t casify_region (enum case_action flag, Lisp_Object b, Lisp_Object e,
+			 Lisp_Object lang);
 extern void syms_of_casefiddle (void);
 extern void keys_of_casefiddle (void);
 
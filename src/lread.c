@@ -2431,9 +2431,14 @@ static Lisp_Object
 read_integer (Lisp_Object readcharfun, EMACS_INT radix)
 {
   /* Room for sign, leading 0, other digits, trailing null byte.
-     Also, room for invalid syntax diagnostic.  */
-  char buf[max (1 + 1 + UINTMAX_WIDTH + 1,
-		sizeof "integer, radix " + INT_STRLEN_BOUND (EMACS_INT))];
+     Also, room for invalid syntax diagnostic.  The buffer is grown
+     for integers that need a bignum.  */
+  char stackbuf[max (1 + 1 + UINTMAX_WIDTH + 1,
+		     sizeof "integer, radix " + INT_STRLEN_BOUND (EMACS_INT))];
+  ptrdiff_t count = SPECPDL_INDEX ();
+  char *buf = stackbuf;
+  ptrdiff_t bufsize = sizeof stackbuf;
+  char *heapbuf = NULL;
 
   int valid = -1; /* 1 if valid, 0 if not, -1 if incomplete.  */
 
@@ -2470,10 +2475,13 @@ read_integer (Lisp_Object readcharfun, EMACS_INT radix)
 	  if (valid < 0)
 	    valid = 1;
 
-	  if (p < buf + sizeof buf - 1)
-	    *p++ = c;
-	  else
-	    valid = 0;
+	  if (p == buf + bufsize - 1)
+	    {
+	      ptrdiff_t offset = p - buf;
+	      buf = grow_read_buffer (buf, offset, &heapbuf, &bufsize, count);
+	      p = buf + offset;
+	    }
+	  *p++ = c;
 
 	  c = READCHAR;
 	}
@@ -2488,7 +2496,7 @@ read_integer (Lisp_Object readcharfun, EMACS_INT radix)
       invalid_syntax (buf);
     }
 
-  return string_to_number (buf, radix, false);
+  return unbind_to (count, string_to_number (buf, radix, false));
 }
 
 
@@ -3463,12 +3471,11 @@ substitute_in_interval (INTERVAL interval, void *arg)
 
 
 /* Convert STRING to a number, assuming base BASE.  Return a fixnum if
-   STRING has integer syntax and fits in a fixnum, else return the
-   nearest float if STRING has either floating point or integer syntax
-   and BASE is 10, else return nil.  If IGNORE_TRAILING, consider just
-   the longest prefix of STRING that has valid floating point syntax.
-   Signal an overflow if BASE is not 10 and the number has integer
-   syntax but does not fit.  */
+   STRING has integer syntax and fits in a fixnum, else a bignum if it
+   has integer syntax, else return the nearest float if STRING has
+   floating point syntax and BASE is 10, else return nil.  If
+   IGNORE_TRAILING, consider just the longest prefix of STRING that has
+   valid floating point syntax.  */
 
 Lisp_Object
 string_to_number (char const *string, int base, bool ignore_trailing)
@@ -3488,6 +3495,7 @@ string_to_number (char const *string, int base, bool ignore_trailing)
   enum { INTOVERFLOW = 1, LEAD_INT = 2, DOT_CHAR = 4, TRAIL_INT = 8,
 	 E_EXP = 16 };
   int state = 0;
+  char const *digits = cp;
   int leading_digit = digit_to_number (*cp, base);
   uintmax_t n = leading_digit;
   if (leading_digit >= 0)
@@ -3503,6 +3511,7 @@ string_to_number (char const *string, int base, bool ignore_trailing)
 	  n += digit;
 	}
     }
+  ptrdiff_t ndigits = cp - digits;
   if (*cp == '.')
     {
       state |= DOT_CHAR;
@@ -3562,30 +3571,22 @@ string_to_number (char const *string, int base, bool ignore_trailing)
 		     || float_syntax))))
     return Qnil;
 
-  /* If the number uses integer and not float syntax, and is in C-language
-     range, use its value, preferably as a fixnum.  */
+  /* If the number uses integer and not float syntax, use its value,
+     preferably as a fixnum.  */
   if (leading_digit >= 0 && ! float_syntax)
     {
-      if (state & INTOVERFLOW)
-	{
-	  /* Unfortunately there's no simple and accurate way to convert
-	     non-base-10 numbers that are out of C-language range.  */
-	  if (base != 10)
-	    xsignal1 (Qoverflow_error, build_string (string));
-	}
-      else if (n <= (negative ? -MOST_NEGATIVE_FIXNUM : MOST_POSITIVE_FIXNUM))
+      if (! (state & INTOVERFLOW)
+	  && n <= (negative ? -MOST_NEGATIVE_FIXNUM : MOST_POSITIVE_FIXNUM))
 	{
 	  EMACS_INT signed_n = n;
 	  return make_number (negative ? -signed_n : signed_n);
 	}
-      else
-	value = n;
+      return make_bignum_str (digits, ndigits, base, negative);
     }
 
-  /* Either the number uses float syntax, or it does not fit into a fixnum.
-     Convert it from string to floating point, unless the value is already
-     known because it is an infinity, a NAN, or its absolute value fits in
-     uintmax_t.  */
+  /* The number uses float syntax.  Convert it from string to floating
+     point, unless the value is already known because it is an infinity
+     or a NAN.  */
   if (! value)
     value = atof (string + signedp);
 
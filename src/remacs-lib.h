@@ -9,6 +9,11 @@
 // the same guarantees
 int rust_make_temp(char *template, int flags);
 
+// Like rust_make_temp, but create a directory with mode 0700,
+// like mkdtemp.  The last SUFFIX_LEN bytes of TEMPLATE follow
+// the XXXXXX.  Return 0 on success.
+int rust_make_temp_dir(char *template, int suffix_len);
+
 int rust_count_trailing_zero_bits(size_t val);
 int rust_count_one_bits(size_t val);
 
@@ -2948,7 +2948,7 @@ list_system_processes (void)
   for (tail = proclist; CONSP (tail); tail = next)
     {
       next = XCDR (tail);
-      XSETCAR (tail, Fstring_to_number (XCAR (tail), Qnil));
+      XSETCAR (tail, Fstring_to_number (XCAR (tail), Qnil, Qnil));
     }
 
   /* directory_files_internal returns the files in reverse order; undo
@@ -3329,7 +3329,7 @@ DEFUN ("internal-set-lisp-face-attribute-from-resource",
     value = Qunspecified;
   else if (EQ (attr, QCheight))
     {
-      value = Fstring_to_number (value, Qnil);
+      value = Fstring_to_number (value, Qnil, Qnil);
       if (!INTEGERP (value) || XINT (value) <= 0)
 	signal_error ("Invalid face height from X resource", value);
     }
@@ -4937,7 +4937,7 @@ be found.  Value is ALIST.  */)
       entry = Fcopy_sequence (entry);
       XSETCAR (tail, entry);
       for (tail2 = entry; CONSP (tail2); tail2 = XCDR (tail2))
-	XSETCAR (tail2, Fdowncase (XCAR (tail2)));
+	XSETCAR (tail2, Fdowncase (XCAR (tail2), Qnil));
     }
   Vface_alternative_font_registry_alist = alist;
   free_all_realized_faces (Qnil);
@@ -542,6 +542,19 @@ literals (Bug#20852)."
              (err (should-error (byte-compile-file source))))
         (should (equal (cdr err) '("Old-style backquotes detected!")))))))
 
+(ert-deftest bytecomp-tests--case-language ()
+  "Check that `upcase' and `downcase' compile with a LANGUAGE argument."
+  (bytecomp-tests--with-temp-file source
+    (write-region "(defun bytecomp-tests--case (c)
+  (list (upcase c) (upcase c 'tr) (downcase c) (downcase c 'tr)))"
+                  nil source)
+    (bytecomp-tests--with-temp-file destination
+      (let ((byte-compile-dest-file-function (lambda (_) destination))
+            (byte-compile-error-on-warn t))
+        (should (byte-compile-file source))
+        (load destination nil t)
+        (should (equal (bytecomp-tests--case ?i) '(?I ?İ ?i ?i)))))))
+
 
 (ert-deftest bytecomp-tests-function-put ()
   "Check `function-put' operates during compilation."
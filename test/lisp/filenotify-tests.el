@@ -174,7 +174,10 @@ Return nil when any other file notification watch is still active."
         file-notify--test-events nil
         file-notify--test-monitors nil))
 
-(setq password-cache-expiry nil
+;; The tests expect the events of the native library, not the
+;; coalesced ones of `filewatch'.
+(setq file-notify-coalesce-events nil
+      password-cache-expiry nil
       tramp-verbose 0
       tramp-message-show-message nil)
 
@@ -857,5 +857,23 @@ See <https://debbugs.gnu.org/19657#20>."
                                     process-environment)))
     (should (equal old (file-truename (abbreviate-file-name testfile))))))
 
+;; Large binary files are not visited as text, but can be visited
+;; literally.
+(ert-deftest files-tests-large-binary-file ()
+  (let ((file (make-temp-file "files-tests-binary"))
+        (large-file-warning-threshold 100))
+    (unwind-protect
+        (cl-letf (((symbol-function 'y-or-n-p) (lambda (&rest _) t)))
+          (with-temp-file file
+            (set-buffer-multibyte nil)
+            (insert (make-string 200 0)))
+          (should-error (find-file-noselect file) :type 'user-error)
+          (should-not (get-file-buffer file))
+          (kill-buffer (find-file-noselect file nil t))
+          (with-temp-file file
+            (insert (make-string 200 ?a)))
+          (kill-buffer (find-file-noselect file)))
+      (delete-file file))))
+
 (provide 'files-tests)
 ;;; files-tests.el ends here
@@ -0,0 +1,85 @@
+;;; bignum-tests.el --- Tests for bignum.rs -*- lexical-binding: t -*-
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest bignum-tests--promotion ()
+  (let ((big (1+ most-positive-fixnum)))
+    (should (integerp big))
+    (should (bignump big))
+    (should-not (fixnump big))
+    (should (> big most-positive-fixnum))
+    (should (fixnump (1- big)))
+    (should (= (1- big) most-positive-fixnum))
+    (should (bignump (1- most-negative-fixnum)))
+    (should (bignump (* most-positive-fixnum most-positive-fixnum)))
+    (should (bignump (- most-negative-fixnum)))
+    (should (= (- (* most-positive-fixnum 4) most-positive-fixnum
+                  most-positive-fixnum most-positive-fixnum)
+               most-positive-fixnum))
+    (should (fixnump (- big 1)))
+    (should (fixnump (/ (* big 4) 8)))))
+
+(ert-deftest bignum-tests--compare ()
+  (let ((big (* most-positive-fixnum 16)))
+    (should (< most-positive-fixnum big))
+    (should (> big 1.0))
+    (should (= (expt 2 70) (expt 2.0 70)))
+    (should (< (expt 2 70) (1+ (expt 2 70))))
+    (should-not (= (1+ (expt 2 70)) (expt 2.0 70)))
+    (should (< (- big) most-negative-fixnum))
+    (should (= (max 1 big 2) big))
+    (should (eql big (* most-positive-fixnum 16)))
+    (should (equal big (* most-positive-fixnum 16)))
+    (should-not (eq (1+ big) (1+ big)))
+    (should-not (eql big (float big)))))
+
+(ert-deftest bignum-tests--arithmetic ()
+  (let ((big (expt 2 100)))
+    (should (= (/ big (expt 2 98)) 4))
+    (should (= (% (1+ big) 2) 1))
+    (should (= (mod (- big) 7) (mod (- (mod big 7)) 7)))
+    (should (= (abs (- big)) big))
+    (should (floatp (+ big 0.5)))
+    (should (= (float big) 1.2676506002282294e+30))
+    (should (= (truncate (expt 2.0 80)) (expt 2 80)))
+    (should (= (floor (expt 2 100) 3) (/ (expt 2 100) 3)))
+    (should (= (ceiling (1+ (expt 2 100)) 2) (1+ (/ (expt 2 100) 2))))
+    (should (= (round (* 5 (expt 2 100)) 2) (* 5 (expt 2 99))))
+    (should-error (/ big 0) :type 'arith-error)))
+
+(ert-deftest bignum-tests--bitwise ()
+  (let ((big (expt 2 80)))
+    (should (= (ash 1 80) big))
+    (should (= (ash big -80) 1))
+    (should (= (ash (- big) -200) -1))
+    (should (= (logand (1- big) 255) 255))
+    (should (= (logior big 1) (1+ big)))
+    (should (= (logxor big big) 0))
+    (should (= (lognot big) (- -1 big)))
+    (should (= (logcount (1- big)) 80))
+    (should (= (logcount (- big)) 80))
+    (should-error (lsh (- big) -1) :type 'args-out-of-range)))
+
+(ert-deftest bignum-tests--read-print ()
+  (let ((big (expt 2 100)))
+    (should (equal (number-to-string big) "1267650600228229401496703205376"))
+    (should (= (read "1267650600228229401496703205376") big))
+    (should (= (read "-1267650600228229401496703205376") (- big)))
+    (should (= (read "#x10000000000000000000000000") big))
+    (should (= (string-to-number "1267650600228229401496703205376") big))
+    (should (equal (prin1-to-string big) "1267650600228229401496703205376"))
+    (should (equal (format "%d" big) "1267650600228229401496703205376"))
+    (should (equal (format "%x" big) "10000000000000000000000000"))
+    (should (equal (format "%#X" big) "0X10000000000000000000000000"))
+    (should (equal (format "%.3e" big) "1.268e+30"))
+    (should (equal (format "%35d" big)
+                   "    1267650600228229401496703205376"))))
+
+(ert-deftest bignum-tests--hash ()
+  (let ((table (make-hash-table :test 'eql)))
+    (puthash (expt 2 100) 'found table)
+    (should (eq (gethash (expt 2 100) table) 'found))
+    (should-not (gethash (expt 2.0 100) table))
+    (should (= (sxhash-eql (expt 2 100)) (sxhash-eql (expt 2 100))))))
@@ -50,5 +50,49 @@
   (let ((last-command-event ?a))
     (should-error (self-insert-command -1))))
 
+(ert-deftest cmds-test--forward-grapheme ()
+  (with-temp-buffer
+    (insert "e\u0301\U0001F1EB\U0001F1F7\r\nx")
+    (goto-char (point-min))
+    (forward-grapheme)
+    (should (= (point) 3))
+    (forward-grapheme 2)
+    (should (= (point) 7))
+    (should-error (forward-grapheme 2) :type 'end-of-buffer)
+    (should (= (point) (point-max)))
+    (backward-grapheme 2)
+    (should (= (point) 5))
+    (forward-grapheme -1)
+    (should (= (point) 3))
+    (should-error (backward-grapheme 5) :type 'beginning-of-buffer)
+    (should (= (point) (point-min)))))
+
+(ert-deftest cmds-test--backward-grapheme-context ()
+  ;; Clusters longer than the text first examined before point, and
+  ;; flags whose pairing depends on the start of their run.
+  (with-temp-buffer
+    (insert "a" (make-string 40 #x301) "b")
+    (backward-grapheme 2)
+    (should (= (point) (point-min)))
+    (erase-buffer)
+    (dotimes (_ 20)
+      (insert "\U0001F1EB\U0001F1F7"))
+    (backward-grapheme)
+    (should (= (point) (- (point-max) 2)))
+    (backward-grapheme 19)
+    (should (= (point) (point-min)))))
+
+(ert-deftest cmds-test--delete-grapheme ()
+  (with-temp-buffer
+    (insert "a\U0001F469\u200D\U0001F4BBb")
+    (goto-char 2)
+    (delete-grapheme 1)
+    (should (equal (buffer-string) "ab"))
+    (should-error (delete-grapheme -2) :type 'beginning-of-buffer)
+    (should (equal (buffer-string) "ab"))
+    (goto-char (point-max))
+    (delete-grapheme -1)
+    (should (equal (buffer-string) "a"))))
+
 (provide 'cmds-tests)
 ;;; cmds-tests.el ends here
@@ -146,6 +146,60 @@
   (should (= (string-to-number "1111" 2) 15))
   (should (= (string-to-number "FF" 16) 255)))
 
+(ert-deftest test-string-to-number-hex-float ()
+  (should (eql (string-to-number "0x1.8p3") 12.0))
+  (should (eql (string-to-number "  -0x.8p1") -1.0))
+  (should (eql (string-to-number "0x1p-1074") 5e-324))
+  (should (eql (string-to-number "0x1.fffffffffffffp1023") 1.7976931348623157e+308))
+  (should (eql (string-to-number "0x1p1024") 1.0e+INF))
+  ;; Without a binary exponent this is not a hexadecimal float.
+  (should (eql (string-to-number "0x1.8") 0))
+  (should (eql (string-to-number "0x1.8p3" 16) 0))
+  (should (eql (string-to-number "ap1" 16) 10)))
+
+(ert-deftest test-string-to-number-radix-prefix ()
+  (should (eql (string-to-number "#x1F") 31))
+  (should (eql (string-to-number "#XfF") 255))
+  (should (eql (string-to-number "#o17") 15))
+  (should (eql (string-to-number "#b101") 5))
+  (should (eql (string-to-number "-#x10") -16))
+  (should (eql (string-to-number "#x-10") -16))
+  (should (eql (string-to-number "#x1.8p1") 3.0))
+  (should (eql (string-to-number "#x10000000000000000") (expt 2 64)))
+  ;; An explicit BASE disables the prefixes.
+  (should (eql (string-to-number "#x10" 16) 0))
+  (should (eql (string-to-number "#z10") 0)))
+
+(ert-deftest test-string-to-number-separators ()
+  (should (eql (string-to-number "1_000_000") 1000000))
+  (should (eql (string-to-number "-1_000.000_5e1_0") -10000005000.0))
+  (should (eql (string-to-number "#xdead_beef") #xdeadbeef))
+  (should (eql (string-to-number "ff_ff" 16) #xffff))
+  ;; Only separators between two digits are ignored.
+  (should (eql (string-to-number "1__000") 1))
+  (should (eql (string-to-number "1_") 1))
+  (should (eql (string-to-number "_1") 0)))
+
+(ert-deftest test-string-to-number-exponent ()
+  (should (eql (string-to-number "1e-3") 0.001))
+  (should (eql (string-to-number "25E2") 2500.0))
+  (should (eql (string-to-number "1.5e+INF") 1.0e+INF))
+  (should (eql (string-to-number "1e3" 16) #x1e3)))
+
+(ert-deftest test-string-to-number-strict ()
+  (should (eql (string-to-number " 42\n" nil t) 42))
+  (should (eql (string-to-number "1_000.5" nil t) 1000.5))
+  (should (eql (string-to-number "#b11" nil t) 3))
+  (should (eql (string-to-number "0x1p4" nil t) 16.0))
+  (should (eql (string-to-number "ff" 16 t) 255))
+  (should-error (string-to-number "" nil t) :type 'invalid-read-syntax)
+  (should-error (string-to-number "12abc" nil t) :type 'invalid-read-syntax)
+  (should-error (string-to-number "abc" nil t) :type 'invalid-read-syntax)
+  (should-error (string-to-number "0x1p4x" nil t) :type 'invalid-read-syntax)
+  (should-error (string-to-number "1.5" 16 t) :type 'invalid-read-syntax)
+  (should-error (string-to-number "1_" nil t) :type 'invalid-read-syntax)
+  (should (eql (string-to-number "12abc") 12)))
+
 (ert-deftest test-store_symval_forwarding ()
   ;; store_symval_forwarding implements what happens when you set a
   ;; variable, so if it gets broken too badly we won't even be able to
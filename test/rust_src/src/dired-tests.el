@@ -46,3 +46,218 @@
       (should system-groups)
       (should (listp system-groups))
       (should (< 0 (length system-groups))))))
+
+(ert-deftest test-directory-files-walk ()
+  (let ((dir (file-name-as-directory (make-temp-file "dired-tests-walk-" t))))
+    (unwind-protect
+        (progn
+          (make-directory (concat dir "a/b") t)
+          (make-directory (concat dir "c") t)
+          (dolist (file '("top.el" "a/one.el" "a/two.txt" "a/b/three.el" "c/four.el"))
+            (write-region "" nil (concat dir file)))
+          (should (equal (directory-files-walk dir "\\.el\\'")
+                         (mapcar (lambda (f) (concat dir f))
+                                 '("a/b/three.el" "a/one.el" "c/four.el" "top.el"))))
+          (should (equal (directory-files-walk dir "\\.el\\'")
+                         (directory-files-recursively dir "\\.el\\'")))
+          (should (equal (directory-files-walk dir nil nil 0)
+                         (list (concat dir "top.el"))))
+          (should (equal (directory-files-walk dir "" t 0)
+                         (mapcar (lambda (f) (concat dir f)) '("a" "c" "top.el"))))
+          (should (equal (directory-files-walk
+                          dir "\\.el\\'" nil nil nil
+                          (lambda (d) (not (string-suffix-p "/a" d))))
+                         (mapcar (lambda (f) (concat dir f)) '("c/four.el" "top.el"))))
+          (let ((attrs (directory-files-walk dir "\\`top" nil nil nil nil t)))
+            (should (equal (caar attrs) (concat dir "top.el")))
+            (should (= 0 (file-attribute-size (cdar attrs))))))
+      (delete-directory dir t))))
+
+(ert-deftest test-directory-files-walk-symlinks ()
+  (skip-unless (not (eq system-type 'windows-nt)))
+  (let ((dir (file-name-as-directory (make-temp-file "dired-tests-walk-" t))))
+    (unwind-protect
+        (progn
+          (make-directory (concat dir "real") t)
+          (write-region "" nil (concat dir "real/file"))
+          (make-symbolic-link (concat dir "real") (concat dir "link"))
+          ;; A link back up the tree must not make the walk loop.
+          (make-symbolic-link dir (concat dir "real/loop"))
+          (should (equal (directory-files-walk dir "\\`file\\'")
+                         (list (concat dir "real/file"))))
+          ;; Each directory is visited once, through the first name
+          ;; that reaches it.
+          (should (equal (directory-files-walk dir "\\`file\\'" nil nil t)
+                         (list (concat dir "link/file")))))
+      (delete-directory dir t))))
+
+(ert-deftest test-directory-files-walk-undecodable-names ()
+  (skip-unless (not (eq system-type 'windows-nt)))
+  (let* ((dir (file-name-as-directory (make-temp-file "dired-tests-walk-" t)))
+         (file-name-coding-system 'utf-8)
+         ;; A name whose bytes are not valid UTF-8.
+         (odd (concat dir "sub/x" (string-to-multibyte "\377"))))
+    (unwind-protect
+        (progn
+          (make-directory (concat dir "sub") t)
+          (write-region "" nil odd)
+          (write-region "" nil (concat dir "sub/y"))
+          (should (equal (directory-files-walk dir)
+                         (list odd (concat dir "sub/y")))))
+      (delete-directory dir t))))
+
+(ert-deftest test-directory-files-respect-ignore-files ()
+  (let ((dir (file-name-as-directory (make-temp-file "dired-tests-ignore-" t))))
+    (unwind-protect
+        (progn
+          (make-directory (concat dir "node_modules") t)
+          (make-directory (concat dir "src") t)
+          (write-region "node_modules/\n*.log\n" nil (concat dir ".ignore"))
+          (dolist (file '("node_modules/dep.js" "src/main.js" "src/debug.log" "README"))
+            (write-region "" nil (concat dir file)))
+          (should (member "node_modules" (directory-files dir)))
+          (let ((directory-files-respect-ignore-files t))
+            (should (equal (directory-files dir)
+                           '("." ".." ".ignore" "README" "src")))
+            (should (equal (directory-files-walk dir)
+                           (mapcar (lambda (f) (concat dir f))
+                                   '("src/main.js" ".ignore" "README"))))))
+      (delete-directory dir t))))
+
+(ert-deftest test-directory-files-sort-and-count ()
+  (let ((dir (file-name-as-directory (make-temp-file "dired-tests-sort-" t))))
+    (unwind-protect
+        (progn
+          (write-region "1234" nil (concat dir "file10.txt"))
+          (write-region "1" nil (concat dir "file2.el"))
+          (write-region "12" nil (concat dir "file1.c"))
+          (set-file-times (concat dir "file10.txt") '(1000 0))
+          (set-file-times (concat dir "file2.el") '(3000 0))
+          (set-file-times (concat dir "file1.c") '(2000 0))
+          (should (equal (directory-files dir nil "\\`file" 'natural)
+                         '("file1.c" "file2.el" "file10.txt")))
+          (should (equal (directory-files dir nil "\\`file")
+                         '("file1.c" "file10.txt" "file2.el")))
+          (should (equal (directory-files dir nil "\\`file" 'extension)
+                         '("file1.c" "file2.el" "file10.txt")))
+          (should (equal (directory-files dir nil "\\`file" '(size . t))
+                         '("file10.txt" "file1.c" "file2.el")))
+          (should (equal (directory-files dir nil "\\`file" '(mtime . t) 2)
+                         '("file2.el" "file1.c")))
+          (should (equal (directory-files dir nil "\\`file" 'mtime 1)
+                         '("file10.txt")))
+          (should (= 3 (length (directory-files dir nil "\\`file" t))))
+          (should (equal (mapcar #'car (directory-files-and-attributes
+                                        dir nil "\\`file" 'size nil 2))
+                         '("file2.el" "file1.c"))))
+      (delete-directory dir t))))
+
+(defun dired-tests--fake-directory-files (_directory &optional _full match nosort)
+  "A `directory-files' handler that only accepts the historic arguments."
+  (let ((names '("b10" "a.z" "c.a" "b2")))
+    (when match
+      (setq names (delq nil (mapcar (lambda (name)
+                                      (and (string-match match name) name))
+                                    names))))
+    (if nosort
+        names
+      (sort (copy-sequence names) #'string-lessp))))
+
+(defun dired-tests--fake-handler (operation &rest args)
+  (if (eq operation 'directory-files)
+      (apply #'dired-tests--fake-directory-files args)
+    (let ((inhibit-file-name-handlers
+           (cons 'dired-tests--fake-handler
+                 (and (eq inhibit-file-name-operation operation)
+                      inhibit-file-name-handlers)))
+          (inhibit-file-name-operation operation))
+      (apply operation args))))
+
+(ert-deftest test-directory-files-handler-sort-and-count ()
+  (let ((file-name-handler-alist
+         (cons '("\\`/fake:" . dired-tests--fake-handler)
+               file-name-handler-alist)))
+    (should (equal (directory-files "/fake:/dir")
+                   '("a.z" "b10" "b2" "c.a")))
+    (should (equal (directory-files "/fake:/dir" nil nil 'natural)
+                   '("a.z" "b2" "b10" "c.a")))
+    (should (equal (directory-files "/fake:/dir" nil nil 'extension 2)
+                   '("b10" "b2")))
+    (should (equal (directory-files "/fake:/dir" nil nil '(name . t) 1)
+                   '("c.a")))
+    (should (equal (directory-files "/fake:/dir" nil nil nil 3)
+                   '("a.z" "b10" "b2")))
+    (should (equal (directory-files "/fake:/dir" nil nil t 2)
+                   '("b10" "a.z")))
+    (should (equal (directory-files "/fake:/dir" nil "\\`b")
+                   '("b10" "b2")))
+    (should (equal (directory-files "/fake:/dir" nil '(glob . "*.[az]"))
+                   '("a.z" "c.a")))))
+
+(ert-deftest test-file-attributes-extended ()
+  (should-not (file-attributes-extended "/this/file/should/not/exist"))
+  (let ((file (make-temp-file "dired-tests-extended-")))
+    (unwind-protect
+        (let (attrs)
+          (set-file-modes file #o640)
+          (setq attrs (file-attributes-extended file))
+          (should (equal (plist-get attrs :modes) "-rw-r-----"))
+          (should (equal (plist-get attrs :modes)
+                         (file-attribute-modes (file-attributes file))))
+          (should (listp (plist-get attrs :xattrs)))
+          (let ((birth (plist-get attrs :birth-time)))
+            (when birth
+              (should (time-less-p birth (time-add (current-time) 60)))))
+          (when (eq system-type 'gnu/linux)
+            (should (stringp (plist-get attrs :filesystem-type)))
+            (should (string-prefix-p (plist-get attrs :mount-point)
+                                     (file-truename file)))))
+      (delete-file file))))
+
+(ert-deftest test-directory-files-glob ()
+  (let ((dir (file-name-as-directory (make-temp-file "dired-tests-glob-" t))))
+    (unwind-protect
+        (progn
+          (make-directory (concat dir "lisp/sub") t)
+          (dolist (file '("a.c" "b.h" "c.rs" "x1.el" "lisp/y.el" "lisp/sub/z.el"))
+            (write-region "" nil (concat dir file)))
+          (should (equal (directory-files dir nil '(glob . "*.{c,h}"))
+                         '("a.c" "b.h")))
+          (should (equal (directory-files dir nil '(glob . "[a-c].*"))
+                         '("a.c" "b.h" "c.rs")))
+          (should (equal (directory-files dir nil '(glob . "[!a-c]*.el"))
+                         '("x1.el")))
+          (should (equal (directory-files-walk dir '(glob . "**/*.el"))
+                         (mapcar (lambda (f) (concat dir f))
+                                 '("lisp/sub/z.el" "lisp/y.el" "x1.el"))))
+          (should (equal (directory-files-walk dir '(glob . "lisp/*.el"))
+                         (list (concat dir "lisp/y.el"))))
+          (should (equal (directory-files-walk dir '(glob . "*.el"))
+                         (directory-files-walk dir "\\.el\\'")))
+          (should-error (directory-files dir nil '(glob . "{a,")))
+          (should-error (directory-files dir nil '(glob . "lisp/*.el")))
+          (should-error (directory-files-and-attributes
+                         dir nil '(glob . "**/*.el")))
+          (should-error (directory-files dir nil '(glob . 1))
+                        :type 'wrong-type-argument))
+      (delete-directory dir t))))
+
+(ert-deftest test-directory-disk-usage ()
+  (let ((dir (file-name-as-directory (make-temp-file "dired-tests-du-" t))))
+    (unwind-protect
+        (progn
+          (make-directory (concat dir "sub"))
+          (write-region (make-string 100 ?a) nil (concat dir "a"))
+          (write-region (make-string 50 ?b) nil (concat dir "sub/b"))
+          ;; A second hard link is not counted again.
+          (add-name-to-file (concat dir "a") (concat dir "sub/a"))
+          (let ((usage (directory-disk-usage dir))
+                (dirs-size (+ (file-attribute-size (file-attributes dir))
+                              (file-attribute-size
+                               (file-attributes (concat dir "sub"))))))
+            (should (= (nth 0 usage) (+ dirs-size 150)))
+            (should (natnump (nth 1 usage)))
+            (should (= (nth 2 usage) 4))
+            (should (equal (directory-disk-usage dir t) usage))))
+      (delete-directory dir t)))
+  (should-error (directory-disk-usage "/does/not/exist") :type 'file-missing))
@@ -39,3 +39,81 @@
       (should-not (file-name-case-insensitive-p file)))
     (when (eq system-type 'darwin)
       (should (file-name-case-insensitive-p file)))))
+
+(ert-deftest test-write-region-atomically ()
+  (skip-unless (fboundp 'write-region-atomically))
+  (let* ((dir (file-name-as-directory (make-temp-file "fileio-tests-" t)))
+         (file (concat dir "file"))
+         (link (concat dir "link")))
+    (unwind-protect
+        (with-temp-buffer
+          (insert "first")
+          (write-region-atomically nil nil file)
+          (should (equal (file-modes file) (logand #o666 (default-file-modes))))
+          (set-file-modes file #o640)
+          (make-symbolic-link "file" link)
+          (erase-buffer)
+          (insert "second")
+          (write-region-atomically nil nil link t)
+          ;; The link still points to the file, which has kept its modes.
+          (should (equal (file-symlink-p link) "file"))
+          (should (equal (file-modes file) #o640))
+          (should (equal (with-temp-buffer
+                           (insert-file-contents file)
+                           (buffer-string))
+                         "second"))
+          (write-region-atomically 1 4 file)
+          (should (= (file-attribute-size (file-attributes file)) 3))
+          ;; No temporary files are left behind.
+          (should (equal (directory-files dir nil "\\`[^.]") '("file" "link")))
+          (should (equal (directory-files dir nil "\\`\\.[^.]") nil)))
+      (delete-directory dir t))))
+
+(ert-deftest test-file-content-type ()
+  (let ((file (make-temp-file "fileio-tests-")))
+    (unwind-protect
+        (progn
+          (let ((coding-system-for-write 'no-conversion))
+            (write-region "\x89PNG\r\n\x1a\n\0\0\0\rIHDR" nil file nil 'nomessage))
+          (should (equal (file-content-type file) '("image/png" . binary)))
+          (write-region "hello, world\n" nil file nil 'nomessage)
+          (should (equal (file-content-type file) '("text/plain" . text)))
+          ;; Short magic numbers need a valid header to count.
+          (dolist (text '("BMW owners meet on Fridays.\n"
+                          "MZ stands for Mizoram.\n"))
+            (write-region text nil file nil 'nomessage)
+            (should (equal (file-content-type file) '("text/plain" . text))))
+          (should-error (file-content-type (concat file "-missing"))
+                        :type 'file-missing))
+      (delete-file file)))
+  (with-temp-buffer
+    (should (equal (file-content-type nil) '("text/plain" . text)))
+    (set-buffer-multibyte nil)
+    (insert "%PDF-1.7\n" "\x7fELF\x02\x01\x01")
+    (should (equal (file-content-type nil) '("application/pdf" . binary)))
+    (should (equal (file-content-type 10 (point-max))
+                   '("application/x-executable" . binary)))
+    (should (equal (file-content-type 1 5) '("text/plain" . text))))
+  (with-temp-buffer
+    (insert "\xef\xbb\xbfcaf\xc3\xa9")
+    (should (equal (file-content-type nil)
+                   '("text/plain; charset=utf-8" . text)))))
+
+(ert-deftest test-make-temp-file-directory ()
+  (let ((dir (make-temp-file "fileio-tests-" t ".d")))
+    (unwind-protect
+        (progn
+          (should (file-directory-p dir))
+          (should (string-suffix-p ".d" dir))
+          (should (equal (file-modes dir) #o700)))
+      (delete-directory dir t)))
+  (should-error (make-temp-file "/does/not/exist/fileio-tests-" t)
+                :type 'file-missing)
+  (let* ((files--temp-directories-to-delete nil)
+         (kill-emacs-hook nil)
+         (dir (make-temp-file "fileio-tests-" 'delete-on-exit)))
+    (write-region "" nil (expand-file-name "file" dir) nil 'nomessage)
+    (should (member dir files--temp-directories-to-delete))
+    (should (memq #'files--delete-temp-directories kill-emacs-hook))
+    (run-hooks 'kill-emacs-hook)
+    (should-not (file-exists-p dir))))
@@ -149,3 +149,45 @@
           (should (equal user (file-locked-p file))))
       (delete-file file nil)
       (delete-file lock-file nil))))
+
+(ert-deftest filelock-tests--lock-file-directory ()
+  "Check that `lock-file-directory' keeps lock files out of the file's directory."
+  (let* ((lock-file-directory (make-temp-file "filelock-tests--lock-dir-" t))
+         (file (make-temp-file "filelock-tests--lock-dir-file-" nil ".txt" "test"))
+         (adjacent (concat (file-name-directory file) (concat ".#" (file-name-nondirectory file)))))
+    (unwind-protect
+        (progn
+          (find-file-existing file)
+          (insert "modification")
+          (lock-buffer)
+          (should (file-locked-p file))
+          (should-not (file-symlink-p adjacent))
+          (should (= 1 (length (directory-files lock-file-directory nil "\\`[0-9a-f]+\\'"))))
+          (unlock-buffer)
+          (should-not (file-locked-p file))
+          (should-not (directory-files lock-file-directory nil "\\`[0-9a-f]+\\'")))
+      (set-buffer-modified-p nil)
+      (kill-buffer)
+      (delete-file file nil)
+      (delete-directory lock-file-directory t))))
+
+(ert-deftest filelock-tests--lock-file-directory-changed ()
+  "Check that unlocking removes the lock taken before `lock-file-directory' changed."
+  (let* ((lock-dir (make-temp-file "filelock-tests--lock-dir-" t))
+         (lock-file-directory lock-dir)
+         (file (make-temp-file "filelock-tests--lock-dir-file-" nil ".txt" "test")))
+    (unwind-protect
+        (progn
+          (find-file-existing file)
+          (insert "modification")
+          (lock-buffer)
+          (should (= 1 (length (directory-files lock-dir nil "\\`[0-9a-f]+\\'"))))
+          (setq lock-file-directory nil)
+          (unlock-buffer)
+          (should-not (directory-files lock-dir nil "\\`[0-9a-f]+\\'"))
+          (setq lock-file-directory lock-dir)
+          (should-not (file-locked-p file)))
+      (set-buffer-modified-p nil)
+      (kill-buffer)
+      (delete-file file nil)
+      (delete-directory lock-dir t))))
@@ -0,0 +1,84 @@
+;;; filewatch-tests.el --- Tests for filewatch.rs
+
+;;; Code:
+
+(require 'ert)
+
+(defun filewatch-tests--collect (dir flags thunk)
+  "Watch DIR with FLAGS, call THUNK and return the events in order."
+  (let* ((events nil)
+         (desc (filewatch-add-watch dir flags
+                                    (lambda (event) (push event events))
+                                    0.05)))
+    (unwind-protect
+        (progn
+          (funcall thunk)
+          ;; Wait for the coalescing delay to pass and the events to be
+          ;; read from the wakeup pipe.
+          (with-timeout (5 nil)
+            (while (progn (read-event nil nil 0.2) (null events))))
+          (read-event nil nil 0.2)
+          (nreverse events))
+      (filewatch-rm-watch desc))))
+
+(ert-deftest filewatch-tests--add-rm-watch ()
+  (skip-unless (featurep 'filewatch))
+  (let ((dir (make-temp-file "filewatch-tests-" t)))
+    (unwind-protect
+        (let ((desc (filewatch-add-watch dir '(change) #'ignore)))
+          (should (filewatch-valid-p desc))
+          (should (filewatch-rm-watch desc))
+          (should-not (filewatch-valid-p desc))
+          (should-error (filewatch-rm-watch desc) :type 'file-notify-error)
+          (dolist (delay '(-1.0 0.0e+NaN 1.0e+INF 1e300))
+            (should-error (filewatch-add-watch dir '(change) #'ignore delay))))
+      (delete-directory dir t)))
+  (should-error (filewatch-add-watch "/does/not/exist" '(change) #'ignore)
+                :type 'file-notify-error))
+
+(ert-deftest filewatch-tests--coalesce ()
+  (skip-unless (featurep 'filewatch))
+  (let* ((dir (make-temp-file "filewatch-tests-" t))
+         (file (expand-file-name "file" dir)))
+    (unwind-protect
+        (let ((events (filewatch-tests--collect
+                       dir '(change)
+                       (lambda ()
+                         (write-region "1" nil file nil 'nomessage)
+                         (write-region "2" nil file t 'nomessage)
+                         (write-region "3" nil file t 'nomessage)))))
+          ;; The writes that follow the creation are merged into it.
+          (should (equal (mapcar #'cdr events) `((created ,file)))))
+      (delete-directory dir t))))
+
+(ert-deftest filewatch-tests--rename ()
+  (skip-unless (featurep 'filewatch))
+  (let* ((dir (make-temp-file "filewatch-tests-" t))
+         (from (expand-file-name "from" dir))
+         (to (expand-file-name "to" dir)))
+    (write-region "" nil from nil 'nomessage)
+    (unwind-protect
+        (let ((events (filewatch-tests--collect
+                       dir '(change)
+                       (lambda () (rename-file from to)))))
+          (should (equal (mapcar #'cdr events) `((renamed ,from ,to)))))
+      (delete-directory dir t))))
+
+(ert-deftest filewatch-tests--recursive ()
+  (skip-unless (featurep 'filewatch))
+  (let* ((dir (make-temp-file "filewatch-tests-" t))
+         (subdir (expand-file-name "sub" dir))
+         (file (expand-file-name "file" subdir)))
+    (make-directory subdir)
+    (unwind-protect
+        (progn
+          (should-not (filewatch-tests--collect
+                       dir '(change)
+                       (lambda () (write-region "" nil file nil 'nomessage))))
+          (delete-file file)
+          (let ((events (filewatch-tests--collect
+                         dir '(change recursive)
+                         (lambda ()
+                           (write-region "" nil file nil 'nomessage)))))
+            (should (member `(created ,file) (mapcar #'cdr events)))))
+      (delete-directory dir t))))
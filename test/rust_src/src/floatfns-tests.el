@@ -37,4 +37,81 @@
 (ert-deftest float-divide-by-zero ()
   (should (isnan (/ 0.0 0))))
 
+;; Hyperbolic functions, their inverses and the other special functions.
+
+(defun floatfns-tests--close (a b)
+  "Return non-nil if A and B are equal to within a few ulps."
+  (<= (abs (- a b)) (* 1e-15 (max 1.0 (abs a) (abs b)))))
+
+(ert-deftest special-functions-hyperbolic ()
+  (should (floatfns-tests--close (sinh 1.0) 1.1752011936438014))
+  (should (floatfns-tests--close (cosh 1.0) 1.5430806348152437))
+  (should (floatfns-tests--close (tanh 1.0) 0.7615941559557649))
+  (should (= (tanh 1.0e3) 1.0))
+  (should (= (sinh 1.0e4) 1.0e+INF))
+  (should (floatfns-tests--close (asinh (sinh 2.0)) 2.0))
+  (should (floatfns-tests--close (acosh (cosh 2.0)) 2.0))
+  (should (floatfns-tests--close (atanh (tanh 0.5)) 0.5))
+  (should (= (asinh -1.0e300) (- (asinh 1.0e300))))
+  (should (isnan (acosh 0.5)))
+  (should (= (atanh 1.0) 1.0e+INF))
+  (should (isnan (atanh 2.0))))
+
+(ert-deftest special-functions-gamma ()
+  (should (= (gamma 5) 24.0))
+  (should (floatfns-tests--close (gamma 0.5) (sqrt float-pi)))
+  (should (= (gamma 0.0) 1.0e+INF))
+  (should (= (gamma -0.0) -1.0e+INF))
+  (should (isnan (gamma -2.0)))
+  (should (= (gamma 200.0) 1.0e+INF))
+  (should (floatfns-tests--close (lgamma 200.0) 857.9336698258574))
+  (should (floatfns-tests--close (lgamma -0.5) (log (* 2 (sqrt float-pi)))))
+  (should (= (lgamma -2.0) 1.0e+INF)))
+
+(ert-deftest special-functions-erf ()
+  (should (= (erf 0.0) 0.0))
+  (should (floatfns-tests--close (erf 1.0) 0.8427007929497149))
+  (should (= (erf -1.0e+INF) -1.0))
+  (should (floatfns-tests--close (erfc 1.0) (- 1 (erf 1.0))))
+  (should (> (erfc 10.0) 0.0))
+  (should (isnan (erf 0.0e+NaN))))
+
+(ert-deftest special-functions-misc ()
+  (should (= (hypot 3 4) 5.0))
+  (should (floatfns-tests--close (hypot 1.0e300 1.0e300) (* (sqrt 2.0) 1.0e300)))
+  (should (= (hypot 1.0e+INF 0.0e+NaN) 1.0e+INF))
+  (should (= (cbrt 27) 3.0))
+  (should (= (cbrt -8.0) -2.0))
+  (should (= (log1p 1.0e-20) 1.0e-20))
+  (should (= (expm1 1.0e-20) 1.0e-20))
+  (should (floatfns-tests--close (expm1 (log1p 0.5)) 0.5))
+  (should (= (fma 2 3 4) 10.0))
+  ;; A separate multiplication would round 1+2^-52 squared to 1+2^-51.
+  (let ((x (+ 1.0 (expt 2.0 -52))))
+    (should (= (fma x x (- (* x x))) (expt 2.0 -104))))
+  (should (isnan (fma 0.0 1.0e+INF 1.0)))
+  (should-error (hypot "3" 4) :type 'wrong-type-argument))
+
+;; Floats print as the shortest string that reads back exactly.
+
+(ert-deftest float-print-shortest ()
+  (should (equal (prin1-to-string 0.1) "0.1"))
+  (should (equal (prin1-to-string 100.0) "100.0"))
+  (should (equal (prin1-to-string -0.0) "-0.0"))
+  (should (equal (prin1-to-string 1e15) "1e+15"))
+  (should (equal (prin1-to-string 1.5e-5) "1.5e-05"))
+  (should (equal (prin1-to-string (/ 1.0 3)) "0.3333333333333333"))
+  (should (equal (number-to-string 5e-324) "5e-324"))
+  (should (equal (prin1-to-string 1.0e+INF) "1.0e+INF")))
+
+(ert-deftest float-print-read-round-trip ()
+  (dotimes (_ 1000)
+    (let ((f (* (- (random 2000000) 1000000.0)
+                (expt 10.0 (- (random 600) 300)))))
+      (should (eql (read (prin1-to-string f)) f))
+      (should (eql (string-to-number (number-to-string f)) f))))
+  (dolist (f (list float-pi float-e 5e-324 2.2250738585072014e-308
+                   1.7976931348623157e+308 (+ 1.0 (expt 2.0 -52))))
+    (should (eql (read (prin1-to-string f)) f))))
+
 (provide 'floatfns-tests)
@@ -47,4 +47,24 @@
   (insert "\tsome more text")
   (should (equal (current-indentation) 8)))
 
+(ert-deftest current-column-grapheme-clusters ()
+  (with-temp-buffer
+    (insert "\U0001F1EB\U0001F1F7e\u0301\u2764\uFE0Fx")
+    (should (eq (current-column) 6))
+    (move-to-column 2)
+    (should (eq (point) 3))
+    (let ((east-asian-ambiguous-width 2))
+      (erase-buffer)
+      (insert "\u03B1\u03B2")
+      (should (eq (current-column) 4)))))
+
+(ert-deftest current-column-ascii-grapheme-clusters ()
+  (with-temp-buffer
+    (dolist (cluster '("1\uFE0F\u20E3" "#\uFE0F" "e\u0301"))
+      (erase-buffer)
+      (insert cluster "x")
+      (should (eq (current-column) (string-width (buffer-string))))
+      (move-to-column (1- (current-column)))
+      (should (eq (point) (1+ (length cluster)))))))
+
 ;;; indent-tests.el ends here
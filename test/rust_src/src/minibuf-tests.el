@@ -405,4 +405,41 @@
       (insert "test")
       (should (string= (minibuffer-contents) "test")))))
 
+;; Fuzzy matching.
+
+(ert-deftest completion-fuzzy-match-positions ()
+  (let ((completion-ignore-case nil))
+    (should (equal (mapcar (lambda (m) (list (car m) (nth 2 m)))
+                           (completion-fuzzy-match
+                            "fb" '("foo-bar" "xfb" "fxb" "nope" "FooBar")))
+                   '(("foo-bar" (0 4)) ("xfb" (1 2)) ("fxb" (0 2)))))
+    (should (equal (mapcar #'car (completion-fuzzy-match "" '("a" "b")))
+                   '("a" "b")))
+    (should-not (completion-fuzzy-match "abc" '("acb" "ab")))
+    (should (equal (nth 2 (car (completion-fuzzy-match "aab" '("aaab"))))
+                   '(1 2 3)))
+    ;; Symbols and alists.
+    (should (equal (mapcar #'car (completion-fuzzy-match "ab" '(xab ("ab" . 1))))
+                   '("xab" "ab")))
+    (should (floatp (nth 1 (car (completion-fuzzy-match "a" '("a"))))))
+    (should-error (completion-fuzzy-match "a" '(1)) :type 'wrong-type-argument)))
+
+(ert-deftest completion-fuzzy-match-case ()
+  (let ((completion-ignore-case nil))
+    (should-not (completion-fuzzy-match "fb" '("FooBar"))))
+  (let ((completion-ignore-case t))
+    (should (equal (nth 2 (car (completion-fuzzy-match "fb" '("FooBar"))))
+                   '(0 3)))
+    (should (completion-fuzzy-match "FB" '("foobar")))))
+
+(ert-deftest completion-fuzzy-match-sort ()
+  (let ((completion-ignore-case nil))
+    (should (equal (mapcar #'car (completion-fuzzy-match
+                                  "fb" '("afxxxb" "foo-bar" "fb" "xfb") t))
+                   '("fb" "foo-bar" "xfb" "afxxxb")))
+    ;; Equal scores keep the order of the collection.
+    (should (equal (mapcar #'car (completion-fuzzy-match
+                                  "ab" '("yab" "xab") t))
+                   '("yab" "xab")))))
+
 ;;; minibuf-tests.el ends here
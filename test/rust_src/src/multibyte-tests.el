@@ -0,0 +1,59 @@
+;;; multibyte-tests.el --- Tests for multibyte.rs -*- lexical-binding: t -*-
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest multibyte-tests--string-normalize ()
+  (should (equal (string-normalize "é" 'nfc) "é"))
+  (should (equal (string-normalize "é" 'nfd) "é"))
+  (should (equal (string-normalize "ﬁ" 'nfc) "ﬁ"))
+  (should (equal (string-normalize "ﬁ" 'nfkc) "fi"))
+  (should (equal (string-normalize "Ａé" 'nfkd) "Aé"))
+  ;; Combining marks are put in canonical order.
+  (should (equal (string-normalize "ạ́" 'nfd) "ạ́"))
+  ;; Hangul syllables.
+  (should (equal (string-normalize "가" 'nfd) "가"))
+  (should (equal (string-normalize "가" 'nfc) "가"))
+  (should (equal (string-normalize "" 'nfc) ""))
+  (let ((s "plain ascii"))
+    (should (eq (string-normalize s 'nfkd) s)))
+  (should-error (string-normalize "a" 'nfx) :type 'wrong-choice))
+
+(ert-deftest multibyte-tests--string-normalize-raw-bytes ()
+  ;; Raw bytes are left alone, and nothing is composed across them.
+  (let ((s (concat "e" (string (unibyte-char-to-multibyte #xff)) "́")))
+    (should (equal (string-normalize s 'nfc) s)))
+  (let ((s (string-to-unibyte "caf\xc3\xa9")))
+    (should (eq (string-normalize s 'nfd) s))))
+
+(ert-deftest multibyte-tests--string-normalized-p ()
+  (should (eq (string-normalized-p "abc" 'nfc) t))
+  (should (eq (string-normalized-p "é" 'nfc) t))
+  (should-not (string-normalized-p "é" 'nfd))
+  (should-not (string-normalized-p "é" 'nfc))
+  (should (eq (string-normalized-p "é" 'nfc t) 'maybe))
+  (should (eq (string-normalized-p "é" 'nfd t) t))
+  (should-not (string-normalized-p "é" 'nfd t))
+  (should-not (string-normalized-p "ﬁ" 'nfkc t)))
+
+(ert-deftest multibyte-tests--normalize-region ()
+  (with-temp-buffer
+    (insert "café naïve")
+    (let ((end (copy-marker (point-max)))
+          (mark (copy-marker 3)))
+      (goto-char (point-max))
+      (should (normalize-region (point-min) (point-max) 'nfd))
+      (should (equal (buffer-string) "café naïve"))
+      (should (= (point) (point-max)))
+      (should (= end (point-max)))
+      (should (= mark 3))
+      (should-not (normalize-region (point-min) (point-max) 'nfd))
+      (should (normalize-region (point-min) (point-max) 'nfc))
+      (should (equal (buffer-string) "café naïve"))))
+  (with-temp-buffer
+    (insert (propertize "ab" 'face 'bold) "ﬁ" (propertize "cd" 'face 'italic))
+    (normalize-region (point-min) (point-max) 'nfkc)
+    (should (equal (buffer-string) "abficd"))
+    (should (eq (get-text-property 1 'face) 'bold))
+    (should (eq (get-text-property 5 'face) 'italic))))
@@ -0,0 +1,79 @@
+;;; random-tests.el --- Tests for random.rs -*- lexical-binding: t -*-
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest random-tests--reproducible ()
+  ;; These values are fixed by the documented algorithm.
+  (let ((gen (make-random-generator 42)))
+    (should (equal (mapcar (lambda (_) (random-generator-integer gen 0 100))
+                           (number-sequence 1 5))
+                   '(22 33 33 56 50))))
+  (should (= (random-generator-float (make-random-generator 42))
+             0.08386297105988216))
+  (should (equal (random-generator-shuffle (make-random-generator 42)
+                                           (number-sequence 1 10))
+                 '(6 10 4 8 3 1 5 9 2 7)))
+  (let ((gen (make-random-generator "seed")))
+    (should (equal (mapcar (lambda (_) (random-generator-integer gen 1 7))
+                           (number-sequence 1 5))
+                   '(3 4 6 3 3))))
+  ;; Integer seeds are taken modulo 2^64.
+  (let ((a (make-random-generator -1))
+        (b (make-random-generator (1- (expt 2 64)))))
+    (should (equal a b))
+    (should (= (random-generator-integer a 0 10) 8))))
+
+(ert-deftest random-tests--independent ()
+  (let* ((a (make-random-generator 7))
+         (b (copy-random-generator a))
+         (first (random-generator-integer a 0 1000000)))
+    (random "some seed")
+    (random)
+    (should (= (random-generator-integer b 0 1000000) first))
+    (should (equal a b))
+    (random-generator-float a)
+    (should-not (equal a b))
+    (should (equal (read (prin1-to-string b)) b))))
+
+(ert-deftest random-tests--ranges ()
+  (let ((gen (make-random-generator)))
+    (should (random-generator-p gen))
+    (dotimes (_ 1000)
+      (let ((n (random-generator-integer gen -5 5))
+            (f (random-generator-float gen)))
+        (should (<= -5 n 4))
+        (should (and (floatp f) (<= 0.0 f) (< f 1.0)))))
+    (should (= (random-generator-integer gen 3 4) 3))
+    (let ((big (random-generator-integer gen (expt 2 100) (expt 2 101))))
+      (should (<= (expt 2 100) big))
+      (should (< big (expt 2 101))))
+    (should-error (random-generator-integer gen 5 5) :type 'args-out-of-range)
+    (should-error (random-generator-integer gen 0 1.5)
+                  :type 'wrong-type-argument)))
+
+(ert-deftest random-tests--shuffle ()
+  (let ((gen (make-random-generator 1))
+        (list (number-sequence 1 20))
+        (vector (vconcat (number-sequence 1 20))))
+    (let ((shuffled (random-generator-shuffle gen list)))
+      (should (equal (sort (copy-sequence shuffled) #'<) list))
+      (should (equal list (number-sequence 1 20))))
+    (let ((shuffled (random-generator-shuffle gen vector)))
+      (should (vectorp shuffled))
+      (should-not (eq shuffled vector))
+      (should (equal (sort (append shuffled nil) #'<) (append vector nil))))
+    (let ((shuffled (random-generator-shuffle gen "héllo")))
+      (should (stringp shuffled))
+      (should (equal (sort (string-to-list shuffled) #'<)
+                     (sort (string-to-list "héllo") #'<))))
+    (should (equal (random-generator-shuffle gen nil) nil))
+    (should (equal (random-generator-shuffle gen [x]) [x]))))
+
+(ert-deftest random-tests--type-errors ()
+  (should-not (random-generator-p (record 'random-generator 1 2)))
+  (should-not (random-generator-p [1 2 3]))
+  (should-error (random-generator-float (record 'random-generator))
+                :type 'wrong-type-argument)
+  (should-error (make-random-generator 1.5) :type 'wrong-type-argument))
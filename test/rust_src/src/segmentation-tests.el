@@ -0,0 +1,35 @@
+;;; segmentation-tests.el --- Tests for segmentation.rs -*- lexical-binding: t -*-
+
+;;; Code:
+
+(require 'ert)
+
+(ert-deftest segmentation-tests--string-grapheme-boundaries ()
+  (should (equal (string-grapheme-boundaries "") '(0)))
+  (should (equal (string-grapheme-boundaries "abc") '(0 1 2 3)))
+  (should (equal (string-grapheme-boundaries "e\u0301x") '(0 2 3)))
+  (should (equal (string-grapheme-boundaries "a\r\nb") '(0 1 3 4)))
+  ;; Emoji ZWJ sequence, then two flags.
+  (should (equal (string-grapheme-boundaries
+                  "\U0001F469\u200D\U0001F4BB\U0001F1EB\U0001F1F7\U0001F1E9\U0001F1EA")
+                 '(0 3 5 7)))
+  ;; Raw bytes are clusters of their own.
+  (should (equal (string-grapheme-boundaries
+                  (concat "a" (string (unibyte-char-to-multibyte #xff)) "\u0301"))
+                 '(0 1 3)))
+  (should (equal (string-grapheme-boundaries (string-to-unibyte "a\xff")) '(0 1 2))))
+
+(ert-deftest segmentation-tests--string-word-boundaries ()
+  (should (equal (string-word-boundaries "") '(0)))
+  (should (equal (string-word-boundaries "hello, world") '(0 5 6 7 12)))
+  (should (equal (string-word-boundaries "can't  3.14") '(0 5 7 11))))
+
+(ert-deftest segmentation-tests--region-boundaries ()
+  (with-temp-buffer
+    (insert "xe\u0301 wo\u0308rd")
+    (should (equal (grapheme-boundaries 2 5) '(2 4 5)))
+    (should (equal (grapheme-boundaries 5 2) '(2 4 5)))
+    (should (equal (word-boundaries (point-min) (point-max)) '(1 4 5 10)))
+    (should-error (word-boundaries 0 3) :type 'args-out-of-range)))
+
+;;; segmentation-tests.el ends here
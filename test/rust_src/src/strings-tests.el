@@ -37,4 +37,27 @@
   ;; Test single unicode character with multiple code-points
   (should (eq (string-width "é") 1)))
 
+(ert-deftest string-width-grapheme-clusters ()
+  ;; A combining mark adds nothing to its base.
+  (should (eq (string-width "e\u0301") 1))
+  ;; A pair of regional indicators is one flag.
+  (should (eq (string-width "\U0001F1EB\U0001F1F7") 2))
+  (should (eq (string-width "\U0001F1EB\U0001F1F7\U0001F1E9\U0001F1EA") 4))
+  ;; The emoji joined by ZERO WIDTH JOINER are drawn as one.
+  (should (eq (string-width "\U0001F469\u200D\U0001F4BB")
+              (char-width ?\U0001F469)))
+  ;; Variation selectors choose text or emoji presentation.
+  (should (eq (string-width "\u2764\uFE0F") 2))
+  (should (eq (string-width "\u231A\uFE0E") 1))
+  (should (eq (string-width "1\uFE0F\u20E3") 2)))
+
+(ert-deftest string-width-ambiguous ()
+  (let ((east-asian-ambiguous-width 2))
+    (should (eq (string-width "\u03B1\u03B2") 4))
+    (should (eq (string-width "ab") 2)))
+  (let ((east-asian-ambiguous-width 1))
+    (should (eq (string-width "\u03B1\u03B2") 2)))
+  (let ((east-asian-ambiguous-width nil))
+    (should (eq (string-width "\u03B1") (char-width ?\u03B1)))))
+
 ;;; strings-tests ends here
@@ -0,0 +1,64 @@
+;;; time-tests.el --- Tests for time.rs
+
+;;; Code:
+
+(require 'ert)
+(require 'benchmark)
+
+(ert-deftest test-parse-rfc3339-time ()
+  (should (equal (parse-rfc3339-time "1985-04-12T23:20:50.52Z")
+                 '(48219605052 . 100)))
+  (should (equal (parse-rfc3339-time "1996-12-19T16:39:57-08:00")
+                 '(851042397 . 1)))
+  (should (equal (parse-rfc3339-time "2009-W53-7 00:00+01:00")
+                 '(1262473200 . 1)))
+  (should (equal (parse-rfc3339-time "2020-060T00:00:00,5Z")
+                 '(15829344005 . 10)))
+  (should (equal (parse-rfc3339-time "1970-01-01T01:00:00" 3600) '(0 . 1)))
+  (should (equal (parse-rfc3339-time "1970-01-01T00:00:00" t) '(0 . 1)))
+  (should-error (parse-rfc3339-time "1970-01-01T00:00:00"))
+  (should-error (parse-rfc3339-time "1970-02-30T00:00:00Z"))
+  (should-error (parse-rfc3339-time "yesterday")))
+
+(ert-deftest test-ticks-hz-time ()
+  (let ((time (parse-rfc3339-time "1985-04-12T23:20:50.52Z")))
+    (should (< (abs (- (float-time time) 482196050.52)) 1e-6))
+    (should (equal (format-time-string "%Y-%m-%d %H:%M:%S.%2N" time t)
+                   "1985-04-12 23:20:50.52"))
+    (should (equal (format-time-string "%s" time t) "482196050"))
+    (should (equal (decode-time time t) '(50 20 23 12 4 1985 5 nil 0)))
+    (should (equal (time-add time 0) '(7357 47698 520000 0)))
+    (should (time-less-p '(0 . 1) time)))
+  (should (equal (time-add '(-1 . 3) 0) '(-1 65535 666666 666666)))
+  (should-error (float-time '(1 . 0)))
+  (should-error (float-time '(1 . -1))))
+
+(ert-deftest test-format-rfc3339-time ()
+  (should (equal (format-rfc3339-time '(48219605052 . 100) t)
+                 "1985-04-12T23:20:50.52Z"))
+  (should (equal (format-rfc3339-time '(851042397 . 1) -28800)
+                 "1996-12-19T16:39:57-08:00"))
+  (should (equal (format-rfc3339-time '(1 . 3) t) "1970-01-01T00:00:00.333333333Z"))
+  (should (equal (format-rfc3339-time '(1 . 3) t 3) "1970-01-01T00:00:00.333Z"))
+  (should (equal (format-rfc3339-time '(0 0 500000) t) "1970-01-01T00:00:00.5Z"))
+  (should (equal (format-rfc3339-time 0 "UTC0") "1970-01-01T00:00:00+00:00"))
+  (should-error (format-rfc3339-time 0 t 10) :type 'args-out-of-range)
+  (dolist (string '("2019-12-31T23:59:59.000Z" "2000-02-29T12:00:00+05:30"))
+    (should (equal (format-rfc3339-time (parse-rfc3339-time string)
+                                        (if (string-suffix-p "Z" string)
+                                            t
+                                          19800))
+                   string))))
+
+(ert-deftest test-current-monotonic-time ()
+  (let ((t1 (current-monotonic-time))
+        (t2 (current-monotonic-time))
+        (boot (current-monotonic-time t)))
+    (should (integerp (car t1)))
+    (should (= (cdr t1) 1000000000))
+    (should-not (time-less-p t2 t1))
+    (should (<= 0 (float-time (time-subtract t2 t1))))
+    (should (<= 0 (float-time boot))))
+  (let ((elapsed (benchmark-elapse (sleep-for 0.01))))
+    (should (floatp elapsed))
+    (should (<= 0.01 elapsed))))
@@ -0,0 +1,78 @@
+;;; timezone-tests.el --- Tests for timezone.rs
+
+;;; Code:
+
+(require 'ert)
+
+(defun timezone-tests--zone-file (zone)
+  "Return the zoneinfo file for ZONE."
+  (expand-file-name zone (or (getenv "TZDIR") "/usr/share/zoneinfo")))
+
+(defun timezone-tests--zone-p (zone)
+  "Return non-nil if the zoneinfo file for ZONE exists."
+  (file-exists-p (timezone-tests--zone-file zone)))
+
+(ert-deftest timezone-tests--decode-encode ()
+  (skip-unless (fboundp 'time-zone-transitions))
+  (skip-unless (timezone-tests--zone-p "Europe/Paris"))
+  ;; 2021-07-01T00:00Z and 2021-01-01T00:00Z.
+  (should (equal (decode-time 1625097600 "Europe/Paris")
+                 '(0 0 2 1 7 2021 4 t 7200)))
+  (should (equal (decode-time 1609459200 "Europe/Paris")
+                 '(0 0 1 1 1 2021 5 nil 3600)))
+  (should (equal (encode-time 0 0 2 1 7 2021 "Europe/Paris")
+                 (encode-time 0 0 0 1 7 2021 t)))
+  ;; An ambiguous time is the earlier one.
+  (should (equal (encode-time 0 30 2 31 10 2021 "Europe/Paris")
+                 (encode-time 0 30 0 31 10 2021 t)))
+  (should (equal (format-time-string "%F %T %Z %z" 1625097600
+                                     "Europe/Paris")
+                 "2021-07-01 02:00:00 CEST +0200"))
+  (should (equal (current-time-zone 1609459200 "Europe/Paris")
+                 '(3600 "CET"))))
+
+(ert-deftest timezone-tests--epoch-seconds ()
+  (skip-unless (fboundp 'time-zone-transitions))
+  (skip-unless (timezone-tests--zone-p "Europe/Paris"))
+  (dolist (time '(0 1625097600 -86401))
+    (should (equal (format-time-string "%s" time "Europe/Paris")
+                   (number-to-string time)))
+    (should (equal (format-time-string "%s" time "Europe/Paris")
+                   (format-time-string "%s" time t))))
+  (should (equal (format-time-string "%%s %6s|%_6s|%-6s" -5 "Europe/Paris")
+                 "%s -00005|    -5|-5")))
+
+(ert-deftest timezone-tests--tzdir ()
+  (skip-unless (fboundp 'time-zone-transitions))
+  (skip-unless (timezone-tests--zone-p "Europe/Paris"))
+  (let* ((paris (timezone-tests--zone-file "Europe/Paris"))
+         (dir (make-temp-file "timezone-tests" t))
+         (process-environment (cons (concat "TZDIR=" dir)
+                                    process-environment)))
+    (unwind-protect
+        (progn
+          ;; A zone missing from TZDIR is looked up again later.
+          (should-not (equal (nth 8 (decode-time 1625097600 "Test/Zone"))
+                             7200))
+          (make-directory (expand-file-name "Test" dir))
+          (copy-file paris (expand-file-name "Test/Zone" dir))
+          (should (equal (nth 8 (decode-time 1625097600 "Test/Zone"))
+                         7200)))
+      (delete-directory dir t))))
+
+(ert-deftest timezone-tests--posix-fallback ()
+  ;; Zones that are not zoneinfo files are still handled by libc.
+  (should (equal (format-time-string "%H %Z" 0 "XST-3") "03 XST"))
+  (should (equal (nth 8 (decode-time 0 "UTC0")) 0)))
+
+(ert-deftest timezone-tests--transitions ()
+  (skip-unless (fboundp 'time-zone-transitions))
+  (skip-unless (timezone-tests--zone-p "America/New_York"))
+  (let ((transitions (time-zone-transitions "America/New_York"
+                                            1609459200 1640995200)))
+    (should (equal transitions
+                   '((1615705200 -14400 t "EDT")
+                     (1636264800 -18000 nil "EST")))))
+  (should (time-zone-transitions "America/New_York"))
+  (should-error (time-zone-transitions "No/Such_Zone"))
+  (should-error (time-zone-transitions "../../etc/passwd")))
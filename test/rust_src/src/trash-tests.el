@@ -0,0 +1,80 @@
+;;; trash-tests.el --- Tests for trash.rs
+
+;;; Code:
+
+(require 'ert)
+(require 'seq)
+
+(defmacro trash-tests--with-home-trash (dir &rest body)
+  "Run BODY with the home trash and a scratch directory DIR in a new directory."
+  (declare (indent 1))
+  `(let* ((,dir (file-name-as-directory (make-temp-file "trash-tests-" t)))
+          (process-environment
+           (cons (concat "XDG_DATA_HOME=" ,dir "data") process-environment)))
+     (unwind-protect
+         (progn ,@body)
+       (delete-directory ,dir t))))
+
+(ert-deftest trash-tests--trash-and-restore ()
+  (trash-tests--with-home-trash dir
+    (let ((file (concat dir "file one")))
+      (write-region "contents" nil file)
+      (let ((trashed (trash-file file)))
+        (should-not (file-exists-p file))
+        (should (equal trashed (concat dir "data/Trash/files/file one")))
+        (should (file-exists-p trashed))
+        (with-temp-buffer
+          (insert-file-contents
+           (concat dir "data/Trash/info/file one.trashinfo"))
+          (should (string-match-p
+                   (concat "\\`\\[Trash Info\\]\nPath="
+                           (regexp-quote
+                            (replace-regexp-in-string " " "%20" file))
+                           "\nDeletionDate=[-0-9]+T[:0-9]+\n\\'")
+                   (buffer-string))))
+        (let ((entry (assoc trashed (trash-list))))
+          (should (equal (nth 1 entry) file))
+          (should (< (abs (float-time (time-subtract (current-time)
+                                                     (nth 2 entry))))
+                     60)))
+        ;; A second file with the same name gets another name.
+        (write-region "other" nil file)
+        (let ((trashed2 (trash-file file)))
+          (should-not (equal trashed2 trashed))
+          (write-region "new" nil file)
+          (should-error (trash-restore trashed) :type 'file-already-exists)
+          (delete-file file)
+          (should (equal (trash-restore trashed) file))
+          (should (file-exists-p file))
+          (should-not (assoc trashed (trash-list)))
+          (should (equal (trash-restore trashed2 (concat dir "sub/renamed"))
+                         (concat dir "sub/renamed"))))
+        (should-error (trash-restore file))))))
+
+(ert-deftest trash-tests--empty ()
+  (trash-tests--with-home-trash dir
+    (make-directory (concat dir "subdir"))
+    (write-region "" nil (concat dir "subdir/file"))
+    (write-region "" nil (concat dir "file"))
+    (let ((trashed (list (trash-file (concat dir "subdir"))
+                         (trash-file (concat dir "file"))))
+          (ours (lambda ()
+                  (seq-filter (lambda (f) (string-prefix-p dir f))
+                              (mapcar #'car (trash-list))))))
+      (should (equal (funcall ours) (sort (copy-sequence trashed) #'string<)))
+      ;; Only touch our own files, not those in other trash directories.
+      (trash-empty trashed)
+      (should-not (funcall ours))
+      (should-not (directory-files (concat dir "data/Trash/files")
+                                   nil directory-files-no-dot-files-regexp))
+      (should-not (directory-files (concat dir "data/Trash/info")
+                                   nil directory-files-no-dot-files-regexp)))))
+
+(ert-deftest trash-tests--move-file-to-trash ()
+  (trash-tests--with-home-trash dir
+    (let ((file (concat dir "file"))
+          (delete-by-moving-to-trash t)
+          (trash-directory nil))
+      (write-region "" nil file)
+      (delete-file file t)
+      (should (file-exists-p (concat dir "data/Trash/files/file"))))))
@@ -260,4 +260,42 @@
       (should (eq tc (upcase-initials ch))))))
 
 
+(ert-deftest casefiddle-tests-casing-language ()
+  ;; language  input  upper  lower  capitalize
+  (dolist (test '((tr "iIıİ" "İIIİ" "iııi" "İııi")
+                  (tr "I\u0307sa" "I\u0307SA" "isa" "I\u0307sa")
+                  (az "iki" "İKİ" "iki" "İki")
+                  ("tr_TR" "Istanbul" "ISTANBUL" "ıstanbul" "Istanbul")
+                  (lt "I\u0301" "I\u0301" "i\u0307\u0301" "I\u0301")
+                  (lt "ÌÍĨ" "ÌÍĨ"
+                      "i\u0307\u0300i\u0307\u0301i\u0307\u0303"
+                      "Ìi\u0307\u0301i\u0307\u0303")
+                  (lt "i\u0307\u0301" "I\u0301" "i\u0307\u0301" "I\u0301")
+                  (lt "Ij" "IJ" "ij" "Ij")
+                  (en "iIİ" "IIİ" "iii\u0307" "Iii\u0307")
+                  (nil "iIİ" "IIİ" "iii\u0307" "Iii\u0307")))
+    (let ((lang (car test))
+          (input (nth 1 test)))
+      (should (string-equal (nth 2 test) (upcase input lang)))
+      (should (string-equal (nth 3 test) (downcase input lang)))
+      (should (string-equal (nth 4 test) (capitalize input lang)))
+      (dolist (case `((upcase-region . ,(nth 2 test))
+                      (downcase-region . ,(nth 3 test))
+                      (capitalize-region . ,(nth 4 test))))
+        (with-temp-buffer
+          (insert input)
+          (if (eq (car case) 'capitalize-region)
+              (capitalize-region (point-min) (point-max) lang)
+            (funcall (car case) (point-min) (point-max) nil lang))
+          (should (string-equal (cdr case) (buffer-string))))))))
+
+(ert-deftest casefiddle-tests-char-casing-language ()
+  (should (eq ?İ (upcase ?i 'tr)))
+  (should (eq ?ı (downcase ?I "az")))
+  (should (eq ?i (downcase ?İ 'tr)))
+  (should (eq ?I (upcase ?i 'lt)))
+  (should (eq ?Ì (downcase ?Ì 'lt)))
+  (should-error (upcase "i" 42) :type 'wrong-type-argument))
+
+
 ;;; casefiddle-tests.el ends here